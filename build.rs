@@ -4,41 +4,216 @@ use std::{
     env,
     fs::{self, File},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
     process::Command,
     str,
 };
 
+/// Repository that MaxPre is cloned from if the `vendored` feature is
+/// disabled. Can be overridden with `MAXPRE_GIT_URL`.
+const MAXPRE_GIT_URL: &str = "https://bitbucket.org/coreo-group/maxpre2.git";
+
 fn main() {
     if std::env::var("DOCS_RS").is_ok() {
         // don't build c++ library on docs.rs due to network restrictions
         return;
     }
-
-    // Build C++ library
-    build();
+    if env::var("CARGO_FEATURE_CXX").is_err() {
+        // only the native Rust preprocessor is available
+        return;
+    }
 
     let out_dir = env::var("OUT_DIR").unwrap();
 
-    println!("cargo:rerun-if-changed=cppsrc/");
+    // Linking against a pre-built libmaxpre is not supported: the bindings
+    // need the `cmaxpre_ext_*` extensions, which are compiled from
+    // `cppext/cmaxpre_ext.cpp` in place of `cpreprocessorinterface.cpp` and
+    // access private members of MaxPre, so a library built from MaxPre alone
+    // never provides them. Patched MaxPre sources can be used with
+    // MAXPRE_SRC_DIR instead.
+    println!("cargo:rerun-if-env-changed=MAXPRE_LIB_DIR");
+    if env::var("MAXPRE_LIB_DIR").is_ok() {
+        panic!(
+            "MAXPRE_LIB_DIR is not supported since the bindings need extensions that are \
+            compiled together with the MaxPre sources, set MAXPRE_SRC_DIR to the source \
+            directory of the library instead"
+        );
+    }
+    println!("cargo:rerun-if-env-changed=MAXPRE_SRC_DIR");
+
+    // Get MaxPre source
+    println!("cargo:rerun-if-env-changed=MAXPRE_GIT_URL");
+    println!("cargo:rerun-if-env-changed=MAXPRE_COMMIT");
+    let git_override = env::var("MAXPRE_GIT_URL").is_ok() || env::var("MAXPRE_COMMIT").is_ok();
+    let (maxpre_dir, identifier) = if let Ok(src_dir) = env::var("MAXPRE_SRC_DIR") {
+        println!("cargo:rerun-if-changed={src_dir}");
+        let maxpre_dir = PathBuf::from(src_dir);
+        let identifier = describe_source("external", &maxpre_dir);
+        (maxpre_dir, identifier)
+    } else if cfg!(feature = "vendored") {
+        println!("cargo:rerun-if-changed=cppsrc/");
+        let maxpre_dir = vendored_source();
+        let identifier = describe_source("vendored", &maxpre_dir);
+        (maxpre_dir, identifier)
+    } else {
+        git_source(Path::new(&out_dir))
+    };
+    if git_override && !identifier.starts_with("git") {
+        println!(
+            "cargo:warning=MAXPRE_GIT_URL and MAXPRE_COMMIT are ignored unless the \
+            `vendored` feature is disabled and MAXPRE_SRC_DIR is unset"
+        );
+    }
+    check_header(&maxpre_dir.join("src/cpreprocessorinterface.h"));
+    // Expose which MaxPre was built, also used in the library signature
+    println!("cargo:rustc-env=MAXPRE_SOURCE={identifier}");
+    if let Some(commit) = head_commit(&maxpre_dir) {
+        println!("cargo:rustc-env=MAXPRE_COMMIT_ID={commit}");
+    }
 
-    #[cfg(target_os = "macos")]
-    println!("cargo:rustc-flags=-l dylib=c++");
+    // Build C++ library
+    build(&maxpre_dir, &identifier);
 
-    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-    println!("cargo:rustc-flags=-l dylib=stdc++");
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&maxpre_dir, &out_dir);
 
     // Built solver is in out_dir
     println!("cargo:rustc-link-search={}", out_dir);
     println!("cargo:rustc-link-search={}/lib", out_dir);
+
+    // Link C++ standard library. The build script runs on the host, so the
+    // target has to be checked via environment variables.
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap();
+    if target_os == "wasi" {
+        // wasi-sdk only ships static C++ libraries
+        if let Some(sysroot) = wasi_sysroot() {
+            for triple in ["wasm32-wasip1", "wasm32-wasi"] {
+                println!(
+                    "cargo:rustc-link-search=native={}",
+                    sysroot.join("lib").join(triple).display()
+                );
+            }
+        }
+        println!("cargo:rustc-link-lib=static=c++");
+        println!("cargo:rustc-link-lib=static=c++abi");
+        println!("cargo:rustc-link-lib=static=wasi-emulated-process-clocks");
+    } else if target_env == "msvc" {
+        // MSVC links the C++ standard library automatically
+    } else if target_os == "macos" || target_os == "ios" || target_os == "freebsd" {
+        println!("cargo:rustc-link-lib=dylib=c++");
+    } else {
+        println!("cargo:rustc-link-lib=dylib=stdc++");
+    }
 }
 
-fn build() {
+/// Checks that the C API header declares all functions that the Rust bindings use
+fn check_header(header: &Path) {
+    let header_src = fs::read_to_string(header)
+        .unwrap_or_else(|err| panic!("could not read MaxPre header {}: {err}", header.display()));
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let mut maxpre_dir_str = crate_dir.clone();
-    maxpre_dir_str.push_str("/cppsrc");
-    let maxpre_dir = Path::new(&maxpre_dir_str);
+    let ffi_src = fs::read_to_string(Path::new(&crate_dir).join("src/ffi.rs")).unwrap();
+    let missing: Vec<&str> = ffi_src
+        .match_indices("pub fn cmaxpre_")
+        .map(|(idx, _)| {
+            let decl = &ffi_src[idx + "pub fn ".len()..];
+            &decl[..decl.find('(').unwrap()]
+        })
+        // Extensions are implemented in this crate and not part of the header
+        .filter(|func| !func.starts_with("cmaxpre_ext_"))
+        .filter(|func| !header_src.contains(&format!("{func}(")))
+        .collect();
+    if !missing.is_empty() {
+        panic!(
+            "the MaxPre header {} is not compatible with this version of the bindings, \
+            missing functions: {}",
+            header.display(),
+            missing.join(", ")
+        );
+    }
+}
+
+/// Gets the MaxPre source that is packaged with the crate
+fn vendored_source() -> PathBuf {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let maxpre_dir = Path::new(&crate_dir).join("cppsrc");
+    if !maxpre_dir.join("src/preprocessor.cpp").exists() {
+        panic!(
+            "the vendored MaxPre source is missing in {}; if building from a git \
+            checkout, run `git submodule update --init`, or disable the `vendored` \
+            feature to clone MaxPre at build time",
+            maxpre_dir.display()
+        );
+    }
+    maxpre_dir
+}
 
+/// Clones MaxPre into the output directory and checks out the selected
+/// revision. Returns the source directory and an identifier of the checked out
+/// commit.
+fn git_source(out_dir: &Path) -> (PathBuf, String) {
+    let url = env::var("MAXPRE_GIT_URL").unwrap_or_else(|_| String::from(MAXPRE_GIT_URL));
+    // There is no default revision: the vendored sources do not record which
+    // upstream commit they are, and defaulting to a branch would make the
+    // build depend on when it runs
+    let rev = env::var("MAXPRE_COMMIT").unwrap_or_else(|_| {
+        panic!(
+            "building without the `vendored` feature requires MAXPRE_COMMIT to select the \
+            MaxPre revision to clone"
+        )
+    });
+    let maxpre_dir = out_dir.join("maxpre2");
+    let repo = match git2::Repository::open(&maxpre_dir) {
+        Ok(repo)
+            if repo
+                .find_remote("origin")
+                .is_ok_and(|rm| rm.url().ok() == Some(url.as_str())) =>
+        {
+            // Fetch to make sure that newly requested revisions are available
+            repo.find_remote("origin")
+                .unwrap()
+                .fetch::<&str>(&[], None, None)
+                .unwrap_or_else(|err| panic!("could not fetch MaxPre from {url}: {err}"));
+            repo
+        }
+        _ => {
+            if maxpre_dir.exists() {
+                fs::remove_dir_all(&maxpre_dir).unwrap();
+            }
+            git2::Repository::clone(&url, &maxpre_dir)
+                .unwrap_or_else(|err| panic!("could not clone MaxPre from {url}: {err}"))
+        }
+    };
+    let target = repo
+        .revparse_single(&format!("origin/{rev}"))
+        .or_else(|_| repo.revparse_single(&rev))
+        .unwrap_or_else(|err| panic!("could not find MaxPre revision {rev}: {err}"));
+    repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::new().force()))
+        .expect("could not check out MaxPre revision");
+    repo.set_head_detached(target.id())
+        .expect("could not check out MaxPre revision");
+    let commit = head_commit(&maxpre_dir).expect("could not resolve checked out MaxPre commit");
+    (maxpre_dir, format!("git {url}@{commit}"))
+}
+
+/// Gets the commit checked out in a MaxPre source directory, if it is a git
+/// repository
+fn head_commit(maxpre_dir: &Path) -> Option<git2::Oid> {
+    let repo = git2::Repository::open(maxpre_dir).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?.id();
+    Some(commit)
+}
+
+/// Describes a MaxPre source directory, including the commit if it is a git
+/// repository
+fn describe_source(kind: &str, maxpre_dir: &Path) -> String {
+    match head_commit(maxpre_dir) {
+        Some(commit) => format!("{kind}@{commit}"),
+        None => String::from(kind),
+    }
+}
+
+fn build(maxpre_dir: &Path, identifier: &str) {
     // Specify the build manually here instead of calling make for better portability
     let src_files = vec![
         "preprocessor.cpp",
@@ -53,18 +228,21 @@ fn build() {
         "touchedlist.cpp",
         "preprocessorinterface.cpp",
         "cardinalityconstraint.cpp",
-        "satlikeinterface.cpp",
-        "cpreprocessorinterface.cpp",
         "prooflogger.cpp",
         "satsolver/solvers/glucose3/utils/System.cc",
         "satsolver/solvers/glucose3/core/Solver.cc",
     ]
     .into_iter()
     .map(|sf| maxpre_dir.join("src").join(sf));
+    // The C API extensions include `cpreprocessorinterface.cpp` and are
+    // therefore compiled in its place, the same goes for the thread-safe
+    // build of `satlikeinterface.cpp`
+    let ext_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("cppext");
+    println!("cargo:rerun-if-changed={}", ext_dir.display());
 
     // Setup build
     let mut build = cc::Build::new();
-    build.cpp(true);
+    build.cpp(true).cpp_link_stdlib(None);
     if env::var("PROFILE").unwrap() == "debug" {
         build
             .opt_level(0)
@@ -75,11 +253,161 @@ fn build() {
         build.opt_level(3).define("NDEBUG", None).warnings(false);
     };
 
+    if env::var("CARGO_CFG_TARGET_ENV").unwrap() == "msvc" {
+        // Shims for POSIX headers and GCC builtins used by MaxPre
+        let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let compat_dir = Path::new(&crate_dir).join("compat/msvc");
+        println!("cargo:rerun-if-changed={}", compat_dir.display());
+        build
+            .include(&compat_dir)
+            .flag(format!("/FI{}", compat_dir.join("builtins.h").display()))
+            .flag("/EHsc")
+            // MaxPre's copy of Glucose lives in the namespace `GlucosePre`,
+            // but the MSVC branch of `utils/System.h` still defines
+            // `Glucose::cpuTime`, which fails to compile in `System.cc` and
+            // `Solver.cc` since there is no namespace `Glucose`. No other
+            // compiled code uses the identifier `Glucose`, so the define only
+            // renames this one definition.
+            .define("Glucose", Some("GlucosePre"))
+            .define("_CRT_SECURE_NO_WARNINGS", None);
+    }
+
+    if env::var("CARGO_CFG_TARGET_OS").unwrap() == "wasi" {
+        if env::var("CARGO_FEATURE_WASI").is_err() {
+            panic!("building for WASI targets requires the `wasi` feature");
+        }
+        if let Ok(sdk) = env::var("WASI_SDK_PATH") {
+            build.compiler(Path::new(&sdk).join("bin/clang++"));
+        }
+        if let Some(sysroot) = wasi_sysroot() {
+            build.flag(format!("--sysroot={}", sysroot.display()));
+        }
+        // WASI has neither exceptions nor process clocks, the latter are
+        // emulated by wasi-libc
+        build
+            .flag("-fno-exceptions")
+            .define("_WASI_EMULATED_PROCESS_CLOCKS", None);
+    }
+
+    add_sanitizers(&mut build);
+    add_custom_flags(&mut build);
+
     // Build MaxPre
     build
         .include(maxpre_dir.join("src"))
         .include(maxpre_dir.join("src/satsolver/solvers/glucose3"))
-        .define("GIT_IDENTIFIER", Some("\"maxpre-rs build\""))
+        .include(&ext_dir)
+        .define(
+            "GIT_IDENTIFIER",
+            Some(format!("\"maxpre-rs build, {identifier}\"").as_str()),
+        )
         .files(src_files)
+        .file(ext_dir.join("cmaxpre_ext.cpp"))
+        .file(ext_dir.join("satlike_ext.cpp"))
         .compile("maxpre");
 }
+
+/// Applies user configuration of the C++ build from features and environment
+/// variables
+fn add_custom_flags(build: &mut cc::Build) {
+    if cfg!(feature = "debug-cxx") {
+        build.debug(true);
+    }
+    if cfg!(feature = "lto") {
+        if build.get_compiler().is_like_clang() {
+            build.flag("-flto=thin");
+        } else if build.get_compiler().is_like_gnu() {
+            // Fat objects keep the library usable with non-LTO linkers
+            build.flag("-flto").flag("-ffat-lto-objects");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=MAXPRE_NATIVE");
+    if env::var("MAXPRE_NATIVE").is_ok_and(|val| val != "0") {
+        build.flag_if_supported("-march=native");
+    }
+    println!("cargo:rerun-if-env-changed=MAXPRE_CXXFLAGS");
+    if let Ok(flags) = env::var("MAXPRE_CXXFLAGS") {
+        for flag in flags.split_whitespace() {
+            build.flag(flag);
+        }
+    }
+}
+
+/// Instruments the C++ code with the sanitizers selected via features
+fn add_sanitizers(build: &mut cc::Build) {
+    let sanitizers: Vec<&str> = [("asan", "address"), ("ubsan", "undefined")]
+        .into_iter()
+        .filter(|(feature, _)| {
+            env::var(format!("CARGO_FEATURE_{}", feature.to_uppercase())).is_ok()
+        })
+        .map(|(_, sanitizer)| sanitizer)
+        .collect();
+    if sanitizers.is_empty() {
+        return;
+    }
+    build
+        .flag(format!("-fsanitize={}", sanitizers.join(",")))
+        .flag("-fno-omit-frame-pointer")
+        .flag("-fno-sanitize-recover=all")
+        .debug(true);
+    // If Rust code is instrumented as well (`-Zsanitizer=...`), rustc links
+    // the sanitizer runtime. Otherwise link the runtime of the C++ compiler.
+    let rustflags = env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let is_clang = build.get_compiler().is_like_clang();
+    for sanitizer in sanitizers {
+        if rustflags.contains(&format!("-Zsanitizer={sanitizer}")) {
+            continue;
+        }
+        match (sanitizer, is_clang) {
+            ("address", false) => println!("cargo:rustc-link-lib=dylib=asan"),
+            ("undefined", false) => println!("cargo:rustc-link-lib=dylib=ubsan"),
+            (_, true) => println!("cargo:rustc-link-arg=-fsanitize={sanitizer}"),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Gets the WASI sysroot from `WASI_SYSROOT` or `WASI_SDK_PATH`
+fn wasi_sysroot() -> Option<PathBuf> {
+    println!("cargo:rerun-if-env-changed=WASI_SYSROOT");
+    println!("cargo:rerun-if-env-changed=WASI_SDK_PATH");
+    if let Ok(sysroot) = env::var("WASI_SYSROOT") {
+        return Some(PathBuf::from(sysroot));
+    }
+    env::var("WASI_SDK_PATH")
+        .ok()
+        .map(|sdk| Path::new(&sdk).join("share/wasi-sysroot"))
+}
+
+#[cfg(feature = "bindgen")]
+fn generate_bindings(maxpre_dir: &Path, out_dir: &str) {
+    let maxpre_src = maxpre_dir.join("src");
+    let ext_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("cppext");
+
+    // The header uses default arguments from `global.hpp` and therefore has
+    // to be parsed as C++
+    let bindings = bindgen::Builder::default()
+        .header_contents(
+            "bindings.hpp",
+            "#include \"global.hpp\"\n#include \"cpreprocessorinterface.h\"\n\
+            #include \"cmaxpre_ext.h\"\n",
+        )
+        .clang_arg(format!("-I{}", maxpre_src.display()))
+        .clang_arg(format!("-I{}", ext_dir.display()))
+        .clang_args(["-x", "c++"])
+        .allowlist_function("cmaxpre_.*")
+        .blocklist_type("CMaxPre")
+        .raw_line("use super::CMaxPre;")
+        .use_core()
+        .ctypes_prefix("core::ffi")
+        .generate()
+        .expect("could not generate bindings for `cpreprocessorinterface.h`");
+    bindings
+        .write_to_file(Path::new(out_dir).join("bindings.rs"))
+        .expect("could not write bindings");
+
+    println!(
+        "cargo:rerun-if-changed={}",
+        maxpre_src.join("cpreprocessorinterface.h").display()
+    );
+}
@@ -0,0 +1,11 @@
+// GCC builtins used by MaxPre, mapped to MSVC intrinsics. This header is
+// force-included in every translation unit when compiling with MSVC.
+
+#ifndef MAXPRE_RS_COMPAT_BUILTINS_H
+#define MAXPRE_RS_COMPAT_BUILTINS_H
+
+#include <intrin.h>
+
+#define __builtin_popcount __popcnt
+
+#endif
@@ -0,0 +1,27 @@
+// Minimal emulation of `<sys/times.h>` for MSVC. MaxPre only uses this for
+// the timing in SATLike.
+
+#ifndef MAXPRE_RS_COMPAT_SYS_TIMES_H
+#define MAXPRE_RS_COMPAT_SYS_TIMES_H
+
+#include <time.h>
+
+struct tms {
+  clock_t tms_utime;
+  clock_t tms_stime;
+  clock_t tms_cutime;
+  clock_t tms_cstime;
+};
+
+// Reports the processor time in `tms_utime`. MSVC does not distinguish
+// between user and system time.
+static inline clock_t times(struct tms *buf) {
+  clock_t now = clock();
+  buf->tms_utime = now;
+  buf->tms_stime = 0;
+  buf->tms_cutime = 0;
+  buf->tms_cstime = 0;
+  return now;
+}
+
+#endif
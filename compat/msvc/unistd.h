@@ -0,0 +1,15 @@
+// Minimal emulation of `<unistd.h>` for MSVC, providing what MaxPre uses in
+// combination with `<sys/times.h>`.
+
+#ifndef MAXPRE_RS_COMPAT_UNISTD_H
+#define MAXPRE_RS_COMPAT_UNISTD_H
+
+#include <time.h>
+
+#define _SC_CLK_TCK 2
+
+static inline long sysconf(int name) {
+  return name == _SC_CLK_TCK ? (long)CLOCKS_PER_SEC : -1;
+}
+
+#endif
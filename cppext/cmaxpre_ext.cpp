@@ -0,0 +1,655 @@
+// Implementation of the maxpre-rs extensions of the MaxPre C API. This
+// translation unit includes the upstream C API implementation, so that the
+// extensions have access to its internal `Wrapper` type, and is compiled in
+// place of `cpreprocessorinterface.cpp`.
+
+#include "cpreprocessorinterface.cpp"
+
+#include "cmaxpre_ext.h"
+#include "satlikeinterface.hpp"
+
+#include <algorithm>
+#include <iostream>
+#include <map>
+#include <streambuf>
+#include <string>
+
+namespace {
+
+// MaxPre keeps the `Preprocessor` of a `PreprocessorInterface`, whether it
+// has preprocessed and the options set through the interface private. An explicit template instantiation may name
+// private members, which is used here to obtain member pointers to them.
+struct PreprocessorMember {
+  typedef maxPreprocessor::Preprocessor maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(PreprocessorMember);
+};
+
+struct PreprocessedMember {
+  typedef bool maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(PreprocessedMember);
+};
+
+struct InProcessModeMember {
+  typedef bool maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(InProcessModeMember);
+};
+
+struct TopWeightMember {
+  typedef uint64_t maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(TopWeightMember);
+};
+
+struct SolverVarToPPVarMember {
+  typedef std::vector<int> maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(SolverVarToPPVarMember);
+};
+
+struct PPVarToSolverVarMember {
+  typedef std::vector<int> maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(PPVarToSolverVarMember);
+};
+
+struct VariablesMember {
+  typedef int maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(VariablesMember);
+};
+
+struct LitToPPMember {
+  typedef int (maxPreprocessor::PreprocessorInterface::*type)(int);
+  friend type get(LitToPPMember);
+};
+
+struct UseBVEGateExtractionMember {
+  typedef bool maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(UseBVEGateExtractionMember);
+};
+
+struct UseLabelMatchingMember {
+  typedef bool maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(UseLabelMatchingMember);
+};
+
+struct BVELocalGrowMember {
+  typedef int maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(BVELocalGrowMember);
+};
+
+struct BVEGlobalGrowMember {
+  typedef int maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(BVEGlobalGrowMember);
+};
+
+struct OptionsMember {
+  typedef maxPreprocessor::Preprocessor::Options
+      maxPreprocessor::PreprocessorInterface::*type;
+  friend type get(OptionsMember);
+};
+
+template <typename Tag, typename Tag::type Member> struct Expose {
+  friend typename Tag::type get(Tag) { return Member; }
+};
+
+template struct Expose<PreprocessorMember,
+                       &maxPreprocessor::PreprocessorInterface::preprocessor>;
+template struct Expose<PreprocessedMember,
+                       &maxPreprocessor::PreprocessorInterface::preprocessed>;
+template struct Expose<InProcessModeMember,
+                       &maxPreprocessor::PreprocessorInterface::inProcessMode>;
+template struct Expose<TopWeightMember,
+                       &maxPreprocessor::PreprocessorInterface::topWeight>;
+template struct Expose<SolverVarToPPVarMember,
+                       &maxPreprocessor::PreprocessorInterface::solverVarToPPVar>;
+template struct Expose<PPVarToSolverVarMember,
+                       &maxPreprocessor::PreprocessorInterface::PPVarToSolverVar>;
+template struct Expose<VariablesMember,
+                       &maxPreprocessor::PreprocessorInterface::variables>;
+template struct Expose<LitToPPMember,
+                       &maxPreprocessor::PreprocessorInterface::litToPP>;
+template struct Expose<
+    UseBVEGateExtractionMember,
+    &maxPreprocessor::PreprocessorInterface::useBVEGateExtraction>;
+template struct Expose<UseLabelMatchingMember,
+                       &maxPreprocessor::PreprocessorInterface::useLabelMatching>;
+template struct Expose<BVELocalGrowMember,
+                       &maxPreprocessor::PreprocessorInterface::BVElocalGrow>;
+template struct Expose<BVEGlobalGrowMember,
+                       &maxPreprocessor::PreprocessorInterface::BVEglobalGrow>;
+template struct Expose<OptionsMember,
+                       &maxPreprocessor::PreprocessorInterface::opt>;
+
+maxPreprocessor::Preprocessor &get_preprocessor(CMaxPre *handle) {
+  maxPreprocessor::PreprocessorInterface *interface =
+      ((maxPreprocessor::Wrapper *)handle)->preprocessor;
+  return interface->*get(PreprocessorMember());
+}
+
+// Reconstructs a model over the internal variables of the preprocessor into
+// the reconstructed assignment of the wrapper
+void reconstruct_model(maxPreprocessor::Wrapper *wrapper,
+                       const std::vector<bool> &model) {
+  wrapper->prepro_assignment.clear();
+  for (int var = 0; var < (int)model.size(); ++var)
+    wrapper->prepro_assignment.push_back(model[var] ? var + 1 : -(var + 1));
+  std::vector<int> litSet =
+      wrapper->preprocessor->reconstruct(wrapper->prepro_assignment, false);
+  wrapper->prepro_assignment.clear();
+  wrapper->reconstr_assignment.assign(litSet.size() + 1, 0);
+  for (int lit : litSet) {
+    if (abs(lit) >= wrapper->reconstr_assignment.size())
+      wrapper->reconstr_assignment.resize(abs(lit) + 1, 0);
+    wrapper->reconstr_assignment[abs(lit)] = lit;
+  }
+}
+
+bool is_technique(char technique) {
+  for (const char *valid = cmaxpre_ext_get_techniques(); *valid; ++valid) {
+    if (*valid == technique)
+      return true;
+  }
+  return false;
+}
+
+// The option maps that `Preprocessor::Options::parseValues` takes, with the
+// map for an option type selected by overloading
+struct OptionMaps {
+  std::map<std::string, int> ints;
+  std::map<std::string, bool> bools;
+  std::map<std::string, double> doubles;
+  std::map<std::string, uint64_t> uint64s;
+
+  std::map<std::string, int> &of(int) { return ints; }
+  std::map<std::string, bool> &of(bool) { return bools; }
+  std::map<std::string, double> &of(double) { return doubles; }
+  std::map<std::string, uint64_t> &of(uint64_t) { return uint64s; }
+};
+
+// Sets a single option by name. `parseValues` removes the options it knows
+// from the maps, so the option is valid if its map is empty afterwards.
+template <typename T> char set_option(CMaxPre *handle, const char *name, T value) {
+  maxPreprocessor::PreprocessorInterface *interface =
+      ((maxPreprocessor::Wrapper *)handle)->preprocessor;
+  OptionMaps maps;
+  maps.of(value)[name] = value;
+  (interface->*get(OptionsMember()))
+      .parseValues(maps.ints, maps.bools, maps.doubles, maps.uint64s);
+  return maps.of(value).empty() ? CMAXPRE_TRUE : CMAXPRE_FALSE;
+}
+
+// Output is silenced per thread by routing the standard streams through a
+// buffer that drops everything written by a thread while it is quiet and
+// forwards everything else to the original buffer. All output of MaxPre and
+// SatLike on the code paths used here goes through the standard streams, the
+// only `printf`s are in SatLike's `print_best_solution` and in the verbose
+// output of Glucose, which are never used. Silencing can be nested. Other
+// threads, and output that bypasses the standard streams, e.g., Rust's
+// standard output, are not affected.
+thread_local unsigned quiet_depth = 0;
+
+class QuietBuf : public std::streambuf {
+public:
+  explicit QuietBuf(std::streambuf *inner) : inner(inner) {}
+
+protected:
+  int_type overflow(int_type ch) override {
+    if (quiet_depth > 0 || traits_type::eq_int_type(ch, traits_type::eof()))
+      return traits_type::not_eof(ch);
+    return inner->sputc(traits_type::to_char_type(ch));
+  }
+
+  std::streamsize xsputn(const char_type *s, std::streamsize n) override {
+    if (quiet_depth > 0)
+      return n;
+    return inner->sputn(s, n);
+  }
+
+  int sync() override { return inner->pubsync(); }
+
+private:
+  std::streambuf *inner;
+};
+
+// Installs the quiet buffers when the shim is loaded, before any thread
+// could be quiet. They are never freed, so that the streams can still be
+// flushed when they are destroyed at exit.
+struct QuietStreams {
+  QuietBuf out{std::cout.rdbuf()};
+  QuietBuf err{std::cerr.rdbuf()};
+  QuietBuf log{std::clog.rdbuf()};
+
+  QuietStreams() {
+    std::cout.rdbuf(&out);
+    std::cerr.rdbuf(&err);
+    std::clog.rdbuf(&log);
+  }
+};
+
+QuietStreams *quiet_streams = new QuietStreams;
+
+} // namespace
+
+extern "C" {
+
+void cmaxpre_ext_quiet_begin() { ++quiet_depth; }
+
+void cmaxpre_ext_quiet_end() {
+  if (quiet_depth > 0)
+    --quiet_depth;
+}
+
+const char *cmaxpre_ext_get_techniques() {
+  // Collected from the characters that MaxPre accepts in a technique string.
+  // MaxPre also accepts `P` there, but does not implement it and
+  // `Log::charToTechnique` is undefined for it, so it is left out.
+  static const std::string techniques = [] {
+    const Preprocessor probe(std::vector<std::vector<int>>(),
+                             std::vector<uint64_t>(), 1);
+    std::string valid;
+    for (char c = '!'; c <= '~'; ++c) {
+      if (c != '[' && c != ']' && c != 'P' &&
+          probe.validTechniques(std::string(1, c)))
+        valid.push_back(c);
+    }
+    return valid;
+  }();
+  return techniques.c_str();
+}
+
+char cmaxpre_ext_get_technique_stats(CMaxPre *handle, char technique,
+                                     CMaxPreTechniqueStats *stats) {
+  if (!is_technique(technique))
+    return CMAXPRE_FALSE;
+  Log &log = get_preprocessor(handle).rLog;
+  Log::Technique tech = log.charToTechnique(technique);
+  stats->time = log.getTime(tech);
+  stats->time_limit = -1;
+  if (log.isTimeLimit() && (size_t)tech < log.tTimeLimit.size())
+    stats->time_limit = log.tTimeLimit[tech];
+  stats->invocations = 0;
+  for (Log::Technique asked : log.askHistory) {
+    if (asked == tech)
+      stats->invocations++;
+  }
+  stats->removed_clauses = log.tLog[tech].rClauses;
+  stats->removed_variables = log.tLog[tech].rVariables;
+  stats->removed_literals = log.tLog[tech].rLiterals;
+  stats->removed_labels = log.tLog[tech].rLabels;
+  return CMAXPRE_TRUE;
+}
+
+void cmaxpre_ext_get_options(CMaxPre *handle, CMaxPreOptions *opts) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  const Preprocessor::Options &opt = interface->*get(OptionsMember());
+  opts->bve_gate_extraction =
+      interface->*get(UseBVEGateExtractionMember()) ? CMAXPRE_TRUE
+                                                    : CMAXPRE_FALSE;
+  opts->label_matching = interface->*get(UseLabelMatchingMember())
+                             ? CMAXPRE_TRUE
+                             : CMAXPRE_FALSE;
+  opts->skip_technique = opt.skipTechnique;
+  opts->bve_sort_max_first = opt.BVEsortMaxFirst ? CMAXPRE_TRUE : CMAXPRE_FALSE;
+  opts->bve_local_grow_limit = interface->*get(BVELocalGrowMember());
+  opts->bve_global_grow_limit = interface->*get(BVEGlobalGrowMember());
+  opts->max_bbtms_vars = opt.BBTMS_maxVars;
+  opts->harden_in_model_search =
+      opt.hardenInModelSearch ? CMAXPRE_TRUE : CMAXPRE_FALSE;
+  opts->model_search_iter_limit = opt.modelSearchIterLimit;
+}
+
+char cmaxpre_ext_is_inprocessing(CMaxPre *handle) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  return interface->*get(InProcessModeMember()) ? CMAXPRE_TRUE : CMAXPRE_FALSE;
+}
+
+char cmaxpre_ext_is_preprocessed(CMaxPre *handle) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  return interface->*get(PreprocessedMember()) ? CMAXPRE_TRUE : CMAXPRE_FALSE;
+}
+
+char cmaxpre_ext_time_limit_hit(CMaxPre *handle) {
+  Log &log = get_preprocessor(handle).rLog;
+  // The same condition under which techniques are denied time
+  return log.isTimeLimit() && log.totalTimer.getTime().count() > log.timeLimit
+             ? CMAXPRE_TRUE
+             : CMAXPRE_FALSE;
+}
+
+char cmaxpre_ext_reset_removed_weight_for(CMaxPre *handle, unsigned obj_idx) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  if (!(wrapper->preprocessor->*get(InProcessModeMember())))
+    return CMAXPRE_FALSE;
+  std::vector<uint64_t> &removed = get_preprocessor(handle).trace.removedWeight;
+  if (obj_idx < removed.size())
+    removed[obj_idx] = 0;
+  // The C API caches the removed weight when preprocessing
+  if (obj_idx < wrapper->removed_weight.size())
+    wrapper->removed_weight[obj_idx] = 0;
+  return CMAXPRE_TRUE;
+}
+
+char cmaxpre_ext_set_int_option(CMaxPre *handle, const char *name, int value) {
+  return set_option(handle, name, value);
+}
+
+char cmaxpre_ext_set_bool_option(CMaxPre *handle, const char *name,
+                                 char value) {
+  return set_option(handle, name, value == CMAXPRE_TRUE);
+}
+
+char cmaxpre_ext_set_double_option(CMaxPre *handle, const char *name,
+                                   double value) {
+  return set_option(handle, name, value);
+}
+
+char cmaxpre_ext_set_uint64_option(CMaxPre *handle, const char *name,
+                                   uint64_t value) {
+  return set_option(handle, name, value);
+}
+
+char cmaxpre_ext_reconstruct_best_model(CMaxPre *handle) {
+  std::vector<bool> &model = get_preprocessor(handle).bestModel;
+  if (model.empty())
+    return CMAXPRE_FALSE;
+  reconstruct_model((Wrapper *)handle, model);
+  return CMAXPRE_TRUE;
+}
+
+void cmaxpre_ext_best_model_phases(CMaxPre *handle, int *lits, unsigned n) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  const std::vector<bool> &model = get_preprocessor(handle).bestModel;
+  const std::vector<int> &ppToSolver =
+      interface->*get(PPVarToSolverVarMember());
+  // The best model is over the internal variables
+  std::map<int, bool> phases;
+  for (size_t var = 0; var < model.size() && var < ppToSolver.size(); ++var) {
+    if (ppToSolver[var] > 0)
+      phases[ppToSolver[var]] = model[var];
+  }
+  for (unsigned i = 0; i < n; ++i) {
+    auto phase = phases.find(lits[i]);
+    if (phase == phases.end())
+      lits[i] = 0;
+    else if (!phase->second)
+      lits[i] = -lits[i];
+  }
+}
+
+char cmaxpre_ext_local_search(CMaxPre *handle, double time_limit) {
+  Preprocessor &preprocessor = get_preprocessor(handle);
+  std::vector<bool> initial{};
+  if (preprocessor.bestModel.size() == (size_t)preprocessor.pi.vars)
+    initial = preprocessor.bestModel;
+  std::vector<bool> model{};
+  if (!SatlikeInterface::do_search(preprocessor.pi, initial, model, time_limit))
+    return CMAXPRE_FALSE;
+  reconstruct_model((Wrapper *)handle, model);
+  preprocessor.modelCostCheck(model);
+  return CMAXPRE_TRUE;
+}
+
+char cmaxpre_ext_set_initial_model(CMaxPre *handle, uint64_t cost) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  Preprocessor &preprocessor = get_preprocessor(handle);
+  if (cost >= preprocessor.bestCost) {
+    wrapper->prepro_assignment.clear();
+    return CMAXPRE_FALSE;
+  }
+  // Original variables keep their index internally. `litToPP` does not apply
+  // here, it maps the variables of the preprocessed instance. Variables
+  // introduced by preprocessing are set to false.
+  std::vector<bool> model(preprocessor.pi.vars, false);
+  for (int lit : wrapper->prepro_assignment) {
+    if (lit > 0 && lit <= (int)model.size())
+      model[lit - 1] = true;
+  }
+  wrapper->prepro_assignment.clear();
+  // Labels that MaxPre introduced for non-unit soft clauses are not part of
+  // the model. Each is set to satisfy its soft unit clause if the hard
+  // clauses relaxed by it are satisfied, so that the model costs `cost`
+  // internally as well.
+  ProblemInstance &pi = preprocessor.pi;
+  int orig_vars = wrapper->preprocessor->getOriginalVariables();
+  for (int var = orig_vars; var < pi.vars; ++var) {
+    for (int lit : {posLit(var), negLit(var)}) {
+      if (!pi.isLitLabel(lit))
+        continue;
+      bool relaxed_sat = true;
+      for (int cl : pi.litClauses[litNegation(lit)]) {
+        if (pi.isClauseRemoved(cl) || !pi.clauses[cl].isHard())
+          continue;
+        bool sat = false;
+        for (int other : pi.clauses[cl].lit) {
+          if (litVariable(other) != var &&
+              model[litVariable(other)] == litValue(other)) {
+            sat = true;
+            break;
+          }
+        }
+        if (!sat) {
+          relaxed_sat = false;
+          break;
+        }
+      }
+      if (relaxed_sat)
+        model[var] = litValue(lit);
+    }
+  }
+  preprocessor.bestModel = model;
+  preprocessor.bestCost = cost;
+  return CMAXPRE_TRUE;
+}
+
+char cmaxpre_ext_log_proof(CMaxPre *handle, const char *path, int debug_level,
+                           char output_file) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  Preprocessor &preprocessor = get_preprocessor(handle);
+  // MaxPre only supports proofs for single-objective instances and the proof
+  // has to start from the original instance
+  if (preprocessor.pi.objectives > 1 || preprocessor.plog ||
+      interface->*get(PreprocessedMember()))
+    return CMAXPRE_FALSE;
+  interface->logProof(path, debug_level, output_file == CMAXPRE_FALSE);
+  if (!preprocessor.plog)
+    return CMAXPRE_FALSE;
+  return CMAXPRE_TRUE;
+}
+
+unsigned cmaxpre_ext_get_n_trace_ops(CMaxPre *handle) {
+  return get_preprocessor(handle).trace.operations.size();
+}
+
+int cmaxpre_ext_get_trace_op(CMaxPre *handle, unsigned op_idx) {
+  Trace &trace = get_preprocessor(handle).trace;
+  if (op_idx >= trace.operations.size())
+    return 0;
+  return trace.operations[op_idx];
+}
+
+unsigned cmaxpre_ext_get_trace_data_len(CMaxPre *handle, unsigned op_idx) {
+  Trace &trace = get_preprocessor(handle).trace;
+  if (op_idx >= trace.data.size())
+    return 0;
+  return trace.data[op_idx].size();
+}
+
+int cmaxpre_ext_get_trace_data(CMaxPre *handle, unsigned op_idx,
+                               unsigned data_idx) {
+  Trace &trace = get_preprocessor(handle).trace;
+  if (op_idx >= trace.data.size() || data_idx >= trace.data[op_idx].size())
+    return 0;
+  return trace.data[op_idx][data_idx];
+}
+
+int cmaxpre_ext_get_n_internal_vars(CMaxPre *handle) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  return interface->*get(VariablesMember());
+}
+
+int cmaxpre_ext_get_n_orig_vars(CMaxPre *handle) {
+  return ((Wrapper *)handle)->preprocessor->getOriginalVariables();
+}
+
+int cmaxpre_ext_get_n_aux_vars(CMaxPre *handle) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  int vars = get_preprocessor(handle).pi.vars;
+  // Outside of inprocessing mode, the internal variables start with the
+  // original ones, otherwise all variables of the instance are mapped
+  if (!(interface->*get(InProcessModeMember())))
+    return vars - interface->getOriginalVariables();
+  for (int var : interface->*get(SolverVarToPPVarMember())) {
+    if (var != 0)
+      vars--;
+  }
+  return vars;
+}
+
+unsigned cmaxpre_ext_get_n_solver_vars(CMaxPre *handle) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  return (interface->*get(SolverVarToPPVarMember())).size();
+}
+
+int cmaxpre_ext_internal_lit(CMaxPre *handle, int lit) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  return (interface->*get(LitToPPMember()))(lit);
+}
+
+char cmaxpre_ext_alter_weights(CMaxPre *handle, const int *labels,
+                               const uint64_t *weights, unsigned n) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  Preprocessor &preprocessor = get_preprocessor(handle);
+  if (!(interface->*get(InProcessModeMember())))
+    return CMAXPRE_FALSE;
+  const std::vector<int> &varMap = interface->*get(SolverVarToPPVarMember());
+  uint64_t topWeight = interface->*get(TopWeightMember());
+  // Validate all updates with the same checks as `alterWeight` before
+  // applying any of them. A label that is made hard is no label anymore, so
+  // later updates of it would fail.
+  std::vector<int> hardened;
+  for (unsigned i = 0; i < n; ++i) {
+    int lbl = labels[i];
+    if (lbl < 1 || lbl > (int)varMap.size() || varMap[lbl - 1] < 1)
+      return CMAXPRE_FALSE;
+    int iVar = varMap[lbl - 1] - 1;
+    int polarity = preprocessor.pi.labelPolarity(iVar, 0);
+    if (polarity != VAR_TRUE && polarity != VAR_FALSE)
+      return CMAXPRE_FALSE;
+    if (std::find(hardened.begin(), hardened.end(), lbl) != hardened.end())
+      return CMAXPRE_FALSE;
+    if (weights[i] >= topWeight)
+      hardened.push_back(lbl);
+  }
+  for (unsigned i = 0; i < n; ++i) {
+    bool altered = interface->alterWeight(labels[i], weights[i]);
+    assert(altered);
+    (void)altered;
+  }
+  return CMAXPRE_TRUE;
+}
+
+void cmaxpre_ext_preprocess(CMaxPre *handle, const char *techniques,
+                            int log_level, double time_limit,
+                            char add_removed_weight,
+                            char sort_labels_frequency) {
+  // For single-objective instances, `getInstance` appends the new weights to
+  // the weights of the previous call instead of replacing them
+  ((Wrapper *)handle)->weights.clear();
+  cmaxpre_preprocess(handle, techniques, log_level, time_limit,
+                     add_removed_weight, sort_labels_frequency);
+}
+
+int cmaxpre_ext_add_vars(CMaxPre *handle, unsigned n) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  if (!(interface->*get(InProcessModeMember())))
+    return 0;
+  const std::vector<int> &varMap = interface->*get(SolverVarToPPVarMember());
+  int first = varMap.size() + 1;
+  cmaxpre_ext_reserve_vars(handle, n);
+  for (unsigned i = 0; i < n; ++i) {
+    int var = interface->addVar(0);
+    assert(var == first + (int)i);
+    (void)var;
+  }
+  return first;
+}
+
+void cmaxpre_ext_init_reserve(CMaxPre *handle, uint64_t n) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  // One more for the clause that is currently being added
+  wrapper->clauses.reserve(n + 1);
+  wrapper->weights.reserve(n + 1);
+}
+
+void cmaxpre_ext_reserve_vars(CMaxPre *handle, unsigned n) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  if (!(interface->*get(InProcessModeMember())))
+    return;
+  ProblemInstance &pi = get_preprocessor(handle).pi;
+  pi.litClauses.reserve(pi.litClauses.size() + 2 * n);
+  std::vector<int> &solverToPP = interface->*get(SolverVarToPPVarMember());
+  solverToPP.reserve(solverToPP.size() + n);
+  std::vector<int> &ppToSolver = interface->*get(PPVarToSolverVarMember());
+  ppToSolver.reserve(ppToSolver.size() + n);
+}
+
+int cmaxpre_ext_prepro_lit(CMaxPre *handle, int lit) {
+  PreprocessorInterface *interface = ((Wrapper *)handle)->preprocessor;
+  int var = abs(lit);
+  // Outside of inprocessing mode, the internal variables are the original
+  // ones
+  if (interface->*get(InProcessModeMember())) {
+    const std::vector<int> &solverToPP =
+        interface->*get(SolverVarToPPVarMember());
+    if (var > (int)solverToPP.size())
+      return 0;
+    var = solverToPP[var - 1];
+  }
+  const std::vector<int> &ppToSolver =
+      interface->*get(PPVarToSolverVarMember());
+  if (var == 0 || var > (int)ppToSolver.size() || ppToSolver[var - 1] == 0)
+    return 0;
+  return lit > 0 ? ppToSolver[var - 1] : -ppToSolver[var - 1];
+}
+
+uint64_t cmaxpre_ext_get_n_prepro_clauses(CMaxPre *handle) {
+  return ((Wrapper *)handle)->clauses.size();
+}
+
+uint64_t cmaxpre_ext_get_prepro_weight(CMaxPre *handle, uint64_t cl_idx,
+                                       unsigned obj_idx) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  if (cl_idx >= wrapper->weights.size() ||
+      obj_idx >= wrapper->weights[cl_idx].size())
+    return wrapper->top_weight;
+  return wrapper->weights[cl_idx][obj_idx];
+}
+
+int cmaxpre_ext_get_prepro_lit(CMaxPre *handle, uint64_t cl_idx,
+                               uint64_t lit_idx) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  if (cl_idx >= wrapper->clauses.size() ||
+      lit_idx >= wrapper->clauses[cl_idx].size())
+    return 0;
+  return wrapper->clauses[cl_idx][lit_idx];
+}
+
+uint64_t cmaxpre_ext_get_n_prepro_labels(CMaxPre *handle) {
+  return ((Wrapper *)handle)->labels.size();
+}
+
+int cmaxpre_ext_get_prepro_label(CMaxPre *handle, uint64_t lbl_idx) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  if (lbl_idx >= wrapper->labels.size())
+    return 0;
+  return wrapper->labels[lbl_idx];
+}
+
+uint64_t cmaxpre_ext_get_n_prepro_fixed(CMaxPre *handle) {
+  return ((Wrapper *)handle)->fixed.size();
+}
+
+int cmaxpre_ext_get_prepro_fixed_lit(CMaxPre *handle, uint64_t lit_idx) {
+  Wrapper *wrapper = (Wrapper *)handle;
+  if (lit_idx >= wrapper->fixed.size())
+    return 0;
+  return wrapper->fixed[lit_idx];
+}
+}
@@ -0,0 +1,181 @@
+#ifndef CMAXPRE_EXT_H
+#define CMAXPRE_EXT_H
+
+// Extensions of the MaxPre C API that are implemented in maxpre-rs. These
+// expose data that MaxPre tracks internally but that is not accessible through
+// `cpreprocessorinterface.h`.
+
+#include "cpreprocessorinterface.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Gets the characters of all techniques that can be used in a technique string
+const char *cmaxpre_ext_get_techniques(void);
+
+// Statistics of a single preprocessing technique, accumulated over all calls
+// to cmaxpre_preprocess
+typedef struct CMaxPreTechniqueStats {
+  // Time spent in the technique in seconds
+  double time;
+  // Time allocated to the technique in seconds, negative if preprocessing was
+  // not time limited
+  double time_limit;
+  // Number of times the technique was requested, only tracked if
+  // preprocessing was time limited
+  unsigned invocations;
+  int removed_clauses;
+  int removed_variables;
+  int removed_literals;
+  int removed_labels;
+} CMaxPreTechniqueStats;
+
+// Gets the statistics of a technique, identified by its character in the
+// technique string. Returns CMAXPRE_FALSE if the character is not a valid
+// technique.
+char cmaxpre_ext_get_technique_stats(CMaxPre *, char technique,
+                                     CMaxPreTechniqueStats *stats);
+
+// The options of a preprocessor that can be set through the C API
+typedef struct CMaxPreOptions {
+  char bve_gate_extraction;
+  char label_matching;
+  int skip_technique;
+  char bve_sort_max_first;
+  int bve_local_grow_limit;
+  int bve_global_grow_limit;
+  int max_bbtms_vars;
+  char harden_in_model_search;
+  int model_search_iter_limit;
+} CMaxPreOptions;
+
+// Gets the current values of all options, including the ones that were never
+// set
+void cmaxpre_ext_get_options(CMaxPre *, CMaxPreOptions *opts);
+
+// Whether the preprocessor is in inprocessing mode
+char cmaxpre_ext_is_inprocessing(CMaxPre *);
+// Whether cmaxpre_preprocess has been called on the preprocessor
+char cmaxpre_ext_is_preprocessed(CMaxPre *);
+// Whether the time limit of preprocessing ran out, after which techniques are
+// not given any more time. Time limits accumulate over calls.
+char cmaxpre_ext_time_limit_hit(CMaxPre *);
+
+// Resets the removed weight of a single objective. Like
+// cmaxpre_reset_removed_weight, this is only possible in inprocessing mode and
+// returns CMAXPRE_FALSE otherwise.
+char cmaxpre_ext_reset_removed_weight_for(CMaxPre *, unsigned obj_idx);
+
+// Set an option by the name of the variable in MaxPre's
+// `Preprocessor::Options`. This covers options that are not part of the C API.
+// Returns CMAXPRE_FALSE if there is no option with the name and type.
+char cmaxpre_ext_set_int_option(CMaxPre *, const char *name, int value);
+char cmaxpre_ext_set_bool_option(CMaxPre *, const char *name, char value);
+char cmaxpre_ext_set_double_option(CMaxPre *, const char *name, double value);
+char cmaxpre_ext_set_uint64_option(CMaxPre *, const char *name,
+                                   uint64_t value);
+
+// Reconstructs the best model found by model search during preprocessing as
+// if it had been passed to cmaxpre_reconstruct. The reconstructed values can
+// be queried with cmaxpre_reconstructed_val. Returns CMAXPRE_FALSE if no model
+// has been found.
+char cmaxpre_ext_reconstruct_best_model(CMaxPre *);
+
+// Gets the values of variables of the preprocessed instance in the best model
+// found by model search. Each of the n variables in lits is replaced by its
+// literal that is true in the model, or by 0 if the model does not assign it.
+void cmaxpre_ext_best_model_phases(CMaxPre *, int *lits, unsigned n);
+
+// Runs SatLike local search on the current instance for the given time in
+// seconds, starting from the best model found so far, if any. Only the first
+// objective is considered. If a model is found, it is reconstructed as if it
+// had been passed to cmaxpre_reconstruct and, if it improves on the best model,
+// stored as the best model. Returns CMAXPRE_FALSE if no model was found.
+char cmaxpre_ext_local_search(CMaxPre *, double time_limit);
+
+// Uses the assignment added with cmaxpre_assignment_add as the best model if
+// its cost for the first objective, as computed by the caller on the original
+// instance, improves on the best model. The assignment must be over the
+// original variables. Returns CMAXPRE_FALSE if the model is not used.
+char cmaxpre_ext_set_initial_model(CMaxPre *, uint64_t cost);
+
+// Starts logging a VeriPB proof of preprocessing to the given file. Must be
+// called before preprocessing. If output_file is CMAXPRE_TRUE, the proof
+// certifies the preprocessed instance as output, otherwise the output is
+// implicit. Returns CMAXPRE_FALSE if proof logging could not be started.
+char cmaxpre_ext_log_proof(CMaxPre *, const char *path, int debug_level,
+                           char output_file);
+
+// Same as cmaxpre_preprocess, but the weights of the preprocessed instance are
+// correct when preprocessing multiple times
+void cmaxpre_ext_preprocess(CMaxPre *, const char *techniques, int log_level,
+                            double time_limit, char add_removed_weight,
+                            char sort_labels_frequency);
+
+// Alters the weights of multiple labels, as cmaxpre_alter_weight does for a
+// single label. All labels are validated before any weight is changed, if one
+// of them is invalid, no weight is changed and CMAXPRE_FALSE is returned.
+char cmaxpre_ext_alter_weights(CMaxPre *, const int *labels,
+                               const uint64_t *weights, unsigned n);
+
+// Reserves memory for n clauses while initializing, i.e., between
+// cmaxpre_init_start and cmaxpre_init_finalize
+void cmaxpre_ext_init_reserve(CMaxPre *, uint64_t n);
+
+// Adds n new variables, as n calls to cmaxpre_add_var with 0 would. Returns
+// the first of the consecutive new variables, or 0 if variables cannot be
+// added.
+int cmaxpre_ext_add_vars(CMaxPre *, unsigned n);
+// Reserves memory for n more variables without adding them
+void cmaxpre_ext_reserve_vars(CMaxPre *, unsigned n);
+// Maps a literal of the original instance to the preprocessed instance.
+// Returns 0 if the variable does not appear in the preprocessed instance.
+int cmaxpre_ext_prepro_lit(CMaxPre *, int lit);
+
+// Access to the reconstruction trace. Operations are identified by MaxPre's
+// internal codes and their data is as stored by MaxPre, i.e., over internal
+// literals (2 * var + negated, with 0-based variables).
+unsigned cmaxpre_ext_get_n_trace_ops(CMaxPre *);
+// Gets the code of an operation, 0 if the index is out of range
+int cmaxpre_ext_get_trace_op(CMaxPre *, unsigned op_idx);
+unsigned cmaxpre_ext_get_trace_data_len(CMaxPre *, unsigned op_idx);
+int cmaxpre_ext_get_trace_data(CMaxPre *, unsigned op_idx, unsigned data_idx);
+// The number of internal variables that reconstruction assigns and the number
+// of original variables that it returns values for
+int cmaxpre_ext_get_n_internal_vars(CMaxPre *);
+int cmaxpre_ext_get_n_orig_vars(CMaxPre *);
+// The number of internal variables that do not correspond to a variable of
+// the instance, i.e., labels and variables introduced by preprocessing
+int cmaxpre_ext_get_n_aux_vars(CMaxPre *);
+// The number of variables that solution literals are mapped for and the
+// mapping of a solution literal to an internal one (1-based). Returns 0 for
+// literals that are ignored when reconstructing.
+unsigned cmaxpre_ext_get_n_solver_vars(CMaxPre *);
+int cmaxpre_ext_internal_lit(CMaxPre *, int lit);
+
+// 64-bit access to the preprocessed instance as last read by
+// cmaxpre_preprocess, for instances with more clauses, labels or fixed
+// literals than fit in the unsigned indices of `cpreprocessorinterface.h`.
+// Weights of indices out of range are the top weight, literals, labels and
+// fixed literals out of range are 0.
+uint64_t cmaxpre_ext_get_n_prepro_clauses(CMaxPre *);
+uint64_t cmaxpre_ext_get_prepro_weight(CMaxPre *, uint64_t cl_idx,
+                                       unsigned obj_idx);
+int cmaxpre_ext_get_prepro_lit(CMaxPre *, uint64_t cl_idx, uint64_t lit_idx);
+uint64_t cmaxpre_ext_get_n_prepro_labels(CMaxPre *);
+int cmaxpre_ext_get_prepro_label(CMaxPre *, uint64_t lbl_idx);
+uint64_t cmaxpre_ext_get_n_prepro_fixed(CMaxPre *);
+int cmaxpre_ext_get_prepro_fixed_lit(CMaxPre *, uint64_t lit_idx);
+
+// Silences all output of the calling thread to the standard C++ streams
+// until the matching call to cmaxpre_ext_quiet_end. Other threads are not
+// affected. Calls can be nested.
+void cmaxpre_ext_quiet_begin(void);
+void cmaxpre_ext_quiet_end(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,29 @@
+// Thread-safe build of MaxPre's SATLike interface, compiled in place of
+// `satlikeinterface.cpp`.
+//
+// SATLike keeps the start time of its search in a static variable, which is
+// shared by all preprocessors that run local search at the same time. The
+// variable is renamed to a function-local thread-local variable here, so that
+// preprocessors in different threads do not race on it.
+
+#include <fstream>
+#include <iostream>
+#include <queue>
+#include <sstream>
+#include <sys/times.h>
+#include <unistd.h>
+
+#include "satlikeinterface.hpp"
+
+// This turns the declaration in `Alg_SATLike.h` into the declaration of the
+// function below and all uses into calls of it
+#define start_time (*satlike_start_time())
+
+#include "satlikeinterface.cpp"
+
+#undef start_time
+
+static struct tms *satlike_start_time() {
+  static thread_local struct tms start_time;
+  return &start_time;
+}
@@ -0,0 +1,208 @@
+/* C API of the maxpre crate, see src/capi.rs */
+
+#ifndef MAXPRE_RS_H
+#define MAXPRE_RS_H
+
+/* Generated with cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Status codes returned by the C API
+ */
+typedef enum MaxPreRsStatus {
+  /**
+   * The call succeeded
+   */
+  MAX_PRE_RS_STATUS_OK = 0,
+  /**
+   * A pointer argument was null
+   */
+  MAX_PRE_RS_STATUS_NULL_POINTER = 1,
+  /**
+   * A literal was zero or out of range
+   */
+  MAX_PRE_RS_STATUS_INVALID_LIT = 2,
+  /**
+   * A string argument was not valid UTF-8
+   */
+  MAX_PRE_RS_STATUS_INVALID_STRING = 3,
+  /**
+   * An output buffer was too small
+   */
+  MAX_PRE_RS_STATUS_BUFFER_TOO_SMALL = 4,
+  /**
+   * MaxPre rejected the call
+   */
+  MAX_PRE_RS_STATUS_REJECTED = 5,
+  /**
+   * The call is not valid in the current state of the preprocessor
+   */
+  MAX_PRE_RS_STATUS_INVALID_STATE = 6,
+  /**
+   * The call requires the preprocessor to be in inprocessing mode
+   */
+  MAX_PRE_RS_STATUS_NOT_INPROCESSING = 7,
+  /**
+   * The call panicked
+   */
+  MAX_PRE_RS_STATUS_PANICKED = 8,
+} MaxPreRsStatus;
+
+typedef struct MaxPreRs MaxPreRs;
+
+/**
+ * Builder collecting an instance before the preprocessor is created
+ */
+typedef struct MaxPreRsBuilder MaxPreRsBuilder;
+
+/**
+ * Statistics of a preprocessor that do not depend on an objective. Variables
+ * are given as their IPASIR index, zero if there is none.
+ */
+typedef struct MaxPreRsStats {
+  size_t n_objs;
+  size_t n_orig_hard_clauses;
+  int max_orig_var;
+  size_t n_prepro_hard_clauses;
+  int max_prepro_var;
+  /**
+   * Preprocessing time in seconds
+   */
+  double prepro_time;
+  /**
+   * Reconstruction time in seconds
+   */
+  double reconst_time;
+} MaxPreRsStats;
+
+/**
+ * Statistics of a preprocessor for one objective
+ */
+typedef struct MaxPreRsObjStats {
+  size_t n_orig_soft_clauses;
+  uint64_t orig_weight;
+  size_t n_prepro_soft_clauses;
+  uint64_t removed_weight;
+} MaxPreRsObjStats;
+
+/**
+ * Callback receiving a clause as `len` literals, and the weight of soft
+ * clauses (zero for hard clauses)
+ */
+typedef void (*MaxPreRsClauseCallback)(void *data, const int *lits, size_t len, uint64_t weight);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the signature of the preprocessor as a null-terminated string
+ */
+const char *maxpre_rs_signature(void);
+
+/**
+ * Creates a builder for an empty instance without objectives. The builder
+ * must be released with [`maxpre_rs_builder_build`] or
+ * [`maxpre_rs_builder_free`].
+ */
+MaxPreRsBuilder *maxpre_rs_builder_new(bool inprocessing);
+
+/**
+ * Releases a builder without creating a preprocessor
+ */
+void maxpre_rs_builder_free(MaxPreRsBuilder *builder);
+
+/**
+ * Adds a hard clause of `len` literals
+ */
+MaxPreRsStatus maxpre_rs_builder_add_hard(MaxPreRsBuilder *builder, const int *lits, size_t len);
+
+/**
+ * Adds a soft clause of `len` literals to objective `obj_idx`. Objectives up
+ * to `obj_idx` that do not exist yet are created.
+ */
+MaxPreRsStatus maxpre_rs_builder_add_soft(MaxPreRsBuilder *builder,
+                                          size_t obj_idx,
+                                          const int *lits,
+                                          size_t len,
+                                          uint64_t weight);
+
+/**
+ * Sets the offset of objective `obj_idx`, creating objectives up to it that
+ * do not exist yet
+ */
+MaxPreRsStatus maxpre_rs_builder_set_offset(MaxPreRsBuilder *builder,
+                                            size_t obj_idx,
+                                            intptr_t offset);
+
+/**
+ * Creates the preprocessor and releases the builder. Returns null if the
+ * instance is rejected, e.g., if the weights or variables exceed the range
+ * of MaxPre. The status is written to `status`, if it is not null. The
+ * preprocessor must be released with [`maxpre_rs_free`].
+ */
+MaxPreRs *maxpre_rs_builder_build(MaxPreRsBuilder *builder, MaxPreRsStatus *status);
+
+/**
+ * Releases a preprocessor
+ */
+void maxpre_rs_free(MaxPreRs *pp);
+
+/**
+ * Preprocesses with the techniques given as a null-terminated string, which
+ * are checked before they are passed to MaxPre. Without inprocessing, an
+ * instance can only be preprocessed once.
+ */
+MaxPreRsStatus maxpre_rs_preprocess(MaxPreRs *pp,
+                                    const char *techniques,
+                                    int log_level,
+                                    double time_limit);
+
+/**
+ * Writes the statistics of the preprocessor to `stats`
+ */
+MaxPreRsStatus maxpre_rs_stats(const MaxPreRs *pp, MaxPreRsStats *stats);
+
+/**
+ * Writes the statistics of objective `obj_idx` to `stats`
+ */
+MaxPreRsStatus maxpre_rs_obj_stats(const MaxPreRs *pp, size_t obj_idx, MaxPreRsObjStats *stats);
+
+/**
+ * Passes the preprocessed hard clauses to `callback`. The instance must be
+ * preprocessed first.
+ */
+MaxPreRsStatus maxpre_rs_prepro_hards(MaxPreRs *pp, MaxPreRsClauseCallback callback, void *data);
+
+/**
+ * Passes the preprocessed soft clauses of objective `obj_idx` to `callback`
+ * and writes the offset to `offset`, if it is not null
+ */
+MaxPreRsStatus maxpre_rs_prepro_softs(MaxPreRs *pp,
+                                      size_t obj_idx,
+                                      MaxPreRsClauseCallback callback,
+                                      void *data,
+                                      intptr_t *offset);
+
+/**
+ * Reconstructs a solution to the original instance from the `len` literals
+ * of a solution to the preprocessed instance. The reconstructed solution is
+ * written to `out` as one literal per original variable, so `out_len` must
+ * be at least the maximum original variable.
+ */
+MaxPreRsStatus maxpre_rs_reconstruct(MaxPreRs *pp,
+                                     const int *sol,
+                                     size_t len,
+                                     int *out,
+                                     size_t out_len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MAXPRE_RS_H */
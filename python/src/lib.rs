@@ -0,0 +1,155 @@
+//! # Python Bindings
+//!
+//! A [`pyo3`] module exposing the MaxPre wrapper of the `maxpre` crate to
+//! Python. Literals are passed in IPASIR form, i.e., as non-zero integers.
+
+use maxpre::{PreproClauses, PreproInit, Techniques};
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict};
+use rustsat::types::{Assignment, Clause, Lit, TernaryVal, Var};
+
+/// Soft clauses of one objective and its offset, as passed from Python
+type PyObjective = (Vec<(Vec<i32>, u64)>, isize);
+
+fn to_clause(lits: &[i32]) -> PyResult<Clause> {
+    lits.iter()
+        .map(|&l| Lit::from_ipasir(l).map_err(|_| PyValueError::new_err("invalid literal 0")))
+        .collect()
+}
+
+fn from_clause(cl: &Clause) -> Vec<i32> {
+    cl.iter().map(|l| l.to_ipasir()).collect()
+}
+
+/// The MaxPre preprocessor
+#[pyclass(unsendable, module = "maxpre")]
+struct MaxPre(maxpre::MaxPre);
+
+#[pymethods]
+impl MaxPre {
+    /// Creates a preprocessor from hard clauses and objectives, each given as
+    /// a list of weighted soft clauses and optionally an offset
+    #[new]
+    #[pyo3(signature = (hards, objs, inprocessing = false))]
+    fn new(
+        hards: Vec<Vec<i32>>,
+        objs: Vec<Bound<'_, PyAny>>,
+        inprocessing: bool,
+    ) -> PyResult<Self> {
+        let mut builder = maxpre::MaxPreBuilder::new().inprocessing(inprocessing);
+        let hards = hards
+            .iter()
+            .map(|cl| to_clause(cl))
+            .collect::<PyResult<Vec<_>>>()?;
+        builder = builder.hards(hards);
+        for obj in objs {
+            let (softs, offset): PyObjective = match obj.extract() {
+                Ok(obj) => obj,
+                Err(_) => (obj.extract()?, 0),
+            };
+            let softs = softs
+                .iter()
+                .map(|(cl, w)| Ok((to_clause(cl)?, *w)))
+                .collect::<PyResult<Vec<_>>>()?;
+            builder = builder.objective_with_offset(softs, offset);
+        }
+        builder
+            .build()
+            .map(MaxPre)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// The signature of the preprocessor
+    #[staticmethod]
+    fn signature() -> &'static str {
+        <maxpre::MaxPre as PreproInit>::signature()
+    }
+
+    /// Preprocesses the instance with the given techniques, by default the
+    /// default techniques of MaxPre
+    #[pyo3(signature = (techniques = "[bu]#[buvsrgc]", log_level = 0, time_limit = 1e9))]
+    fn preprocess(&mut self, techniques: &str, log_level: i32, time_limit: f64) -> PyResult<()> {
+        let techniques: Techniques = techniques
+            .parse()
+            .map_err(|err: maxpre::ParseError| PyValueError::new_err(err.to_string()))?;
+        self.0
+            .try_preprocess(&techniques, log_level, time_limit)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Gets the preprocessed instance as a list of hard clauses and a list of
+    /// objectives, each a list of weighted soft clauses and an offset
+    fn prepro_instance(&mut self) -> (Vec<Vec<i32>>, Vec<PyObjective>) {
+        let (hards, objs) = self.0.prepro_instance();
+        let hards = hards.iter().map(from_clause).collect();
+        let objs = objs
+            .into_iter()
+            .map(|(softs, offset)| {
+                let softs = softs
+                    .iter()
+                    .map(|(cl, w)| (from_clause(cl), *w as u64))
+                    .collect();
+                (softs, offset)
+            })
+            .collect();
+        (hards, objs)
+    }
+
+    /// Gets the labels of the preprocessed instance
+    fn prepro_labels(&self) -> Vec<i32> {
+        self.0
+            .prepro_labels()
+            .iter()
+            .map(|l| l.to_ipasir())
+            .collect()
+    }
+
+    /// Gets the literals fixed by preprocessing
+    fn prepro_fixed_lits(&self) -> Vec<i32> {
+        self.0
+            .prepro_fixed_lits()
+            .iter()
+            .map(|l| l.to_ipasir())
+            .collect()
+    }
+
+    /// Reconstructs a solution to the original instance from a solution to
+    /// the preprocessed instance, given as a list of literals. The result has
+    /// one literal per original variable.
+    fn reconstruct(&mut self, sol: Vec<i32>) -> PyResult<Vec<i32>> {
+        let sol: Assignment = to_clause(&sol)?.into_iter().collect();
+        let n_vars = self.0.max_orig_var().idx32() + 1;
+        let sol = self.0.reconstruct(sol);
+        Ok((0..n_vars)
+            .map(|idx| {
+                let var = Var::new(idx);
+                var.lit(sol.var_value(var) == TernaryVal::False).to_ipasir()
+            })
+            .collect())
+    }
+
+    /// Gets the statistics of the preprocessor as a dictionary
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = self.0.stats();
+        let var = |v: Option<Var>| v.map_or(0, Var::to_ipasir);
+        let dict = PyDict::new(py);
+        dict.set_item("n_objs", stats.n_objs)?;
+        dict.set_item("n_orig_hard_clauses", stats.n_orig_hard_clauses)?;
+        dict.set_item("n_orig_soft_clauses", stats.n_orig_soft_clauses)?;
+        dict.set_item("max_orig_var", var(stats.max_orig_var))?;
+        dict.set_item("orig_weight", stats.orig_weight)?;
+        dict.set_item("n_prepro_hard_clauses", stats.n_prepro_hard_clauses)?;
+        dict.set_item("n_prepro_soft_clauses", stats.n_prepro_soft_clauses)?;
+        dict.set_item("max_prepro_var", var(stats.max_prepro_var))?;
+        dict.set_item("removed_weight", stats.removed_weight)?;
+        dict.set_item("prepro_time", stats.prepro_time.as_secs_f64())?;
+        dict.set_item("reconst_time", stats.reconst_time.as_secs_f64())?;
+        Ok(dict)
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "maxpre")]
+fn maxpre_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<MaxPre>()?;
+    Ok(())
+}
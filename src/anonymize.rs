@@ -0,0 +1,185 @@
+//! # Instance Anonymization
+//!
+//! Randomly renames the variables and shuffles the clauses of the preprocessed
+//! instance with
+//! [`PreproClauses::prepro_instance_anonymized`](crate::PreproClauses::prepro_instance_anonymized),
+//! so that instances derived with this crate can be published without
+//! revealing variable names or the order in which the clauses were generated.
+
+use rustsat::{
+    instances::Cnf,
+    types::{Clause, Lit, Var},
+};
+
+use crate::SoftClauses;
+
+/// The permutation of the variables of an anonymized instance, which maps
+/// solutions of it back to the preprocessed instance, see
+/// [`PreproClauses::reconstruct_anonymized`](crate::PreproClauses::reconstruct_anonymized)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Anonymization {
+    /// The seed the instance was shuffled with
+    seed: u64,
+    /// The preprocessed variable of each variable of the anonymized instance
+    vars: Vec<Var>,
+}
+
+impl Anonymization {
+    /// Restores an anonymization from a stored seed and
+    /// [permutation](Anonymization::permutation)
+    pub fn new(seed: u64, vars: Vec<Var>) -> Self {
+        Anonymization { seed, vars }
+    }
+
+    /// Gets the seed the instance was shuffled with. Anonymizing the same
+    /// preprocessed instance with this seed gives the same instance.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Gets the preprocessed variable of each variable of the anonymized
+    /// instance, by the index of the anonymized variable
+    pub fn permutation(&self) -> &[Var] {
+        &self.vars
+    }
+
+    /// Maps a literal of the anonymized instance to the preprocessed instance,
+    /// returns [`None`] if the variable does not occur in the instance
+    pub fn prepro_lit(&self, lit: Lit) -> Option<Lit> {
+        let var = *self.vars.get(lit.var().idx())?;
+        Some(var.lit(lit.is_neg()))
+    }
+
+    /// Anonymizes an instance with a seed. The variables are renamed to a
+    /// dense range in a random order, and the hard clauses, the soft clauses
+    /// of each objective and the literals of each clause are shuffled. The
+    /// order of the objectives is kept.
+    pub(crate) fn anonymize(
+        hards: &Cnf,
+        objs: &[(SoftClauses, isize)],
+        seed: u64,
+    ) -> (Cnf, Vec<(SoftClauses, isize)>, Self) {
+        let mut rng = Rng::new(seed);
+        let max_var = hards
+            .iter()
+            .chain(objs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+            .flat_map(|cl| cl.iter().map(|l| l.var()))
+            .max();
+        let mut rename: Vec<Option<Var>> = vec![None; max_var.map_or(0, |v| v.idx() + 1)];
+        let mut vars: Vec<Var> = vec![];
+        for cl in hards
+            .iter()
+            .chain(objs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+        {
+            for lit in cl.iter() {
+                if rename[lit.var().idx()].is_none() {
+                    rename[lit.var().idx()] = Some(lit.var());
+                    vars.push(lit.var());
+                }
+            }
+        }
+        rng.shuffle(&mut vars);
+        for (idx, var) in vars.iter().enumerate() {
+            rename[var.idx()] = Some(Var::new(idx as u32));
+        }
+        let mut rename_cl = |cl: &Clause| -> Clause {
+            let mut lits: Vec<Lit> = cl
+                .iter()
+                .map(|l| rename[l.var().idx()].unwrap().lit(l.is_neg()))
+                .collect();
+            rng.shuffle(&mut lits);
+            lits.into_iter().collect()
+        };
+        let mut hards: Vec<Clause> = hards.iter().map(&mut rename_cl).collect();
+        let mut objs: Vec<(SoftClauses, isize)> = objs
+            .iter()
+            .map(|(softs, offset)| {
+                let softs = softs.iter().map(|(cl, w)| (rename_cl(cl), *w)).collect();
+                (softs, *offset)
+            })
+            .collect();
+        rng.shuffle(&mut hards);
+        for (softs, _) in &mut objs {
+            rng.shuffle(softs);
+        }
+        (
+            hards.into_iter().collect(),
+            objs,
+            Anonymization { seed, vars },
+        )
+    }
+}
+
+/// A xorshift generator, which is enough for shuffling and keeps the crate
+/// free of a dependency on random number generation
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift needs a non-zero state
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+
+    /// Fisher-Yates shuffle
+    fn shuffle<T>(&mut self, vals: &mut [T]) {
+        for idx in (1..vals.len()).rev() {
+            vals.swap(idx, self.next(idx + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::Cnf,
+        lit,
+        types::{Clause, Lit},
+    };
+
+    use super::Anonymization;
+
+    fn sorted<I: IntoIterator<Item = Lit>>(lits: I) -> Vec<Lit> {
+        let mut lits: Vec<Lit> = lits.into_iter().collect();
+        lits.sort_unstable();
+        lits
+    }
+
+    #[test]
+    fn anonymize() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![3], !lit![7]);
+        cnf.add_ternary(lit![7], lit![9], !lit![3]);
+        let objs = vec![(vec![(clause![!lit![9]], 2), (clause![lit![3]], 1)], 4)];
+        let (hards, anon_objs, anon) = Anonymization::anonymize(&cnf, &objs, 5);
+        assert_eq!(anon.seed(), 5);
+        assert_eq!(
+            sorted(anon.permutation().iter().map(|v| v.pos_lit())),
+            vec![lit![3], lit![7], lit![9]]
+        );
+        assert_eq!(anon.prepro_lit(lit![3]), None);
+        // Mapping back gives the original instance up to the order
+        let back = |cl: &Clause| sorted(cl.iter().filter_map(|&l| anon.prepro_lit(l)));
+        let mut orig: Vec<_> = cnf.iter().map(|cl| sorted(cl.iter().copied())).collect();
+        let mut mapped: Vec<_> = hards.iter().map(back).collect();
+        orig.sort_unstable();
+        mapped.sort_unstable();
+        assert_eq!(mapped, orig);
+        assert_eq!(anon_objs[0].1, 4);
+        let mut softs: Vec<_> = anon_objs[0]
+            .0
+            .iter()
+            .map(|(cl, w)| (back(cl), *w))
+            .collect();
+        softs.sort_unstable();
+        assert_eq!(softs, vec![(vec![lit![3]], 1), (vec![!lit![9]], 2)]);
+        assert_eq!(Anonymization::anonymize(&cnf, &objs, 5).2, anon);
+    }
+}
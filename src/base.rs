@@ -2,20 +2,46 @@
 //!
 //! The low-abstraction MaxPre interface working on hard and soft clauses.
 
-use core::ffi::{c_int, c_uint, CStr};
-use std::ffi::CString;
+use core::{
+    ffi::{c_char, c_int, c_uint, CStr},
+    ops::Range,
+    time::Duration,
+};
+use std::{ffi::CString, io};
 
+#[cfg(not(target_os = "wasi"))]
 use cpu_time::ProcessTime;
+// WASI does not provide process CPU time, use wall clock time instead
 use rustsat::{
     instances::Cnf,
-    types::{Assignment, Clause, Lit, Var, WClsIter},
+    types::{Assignment, Clause, Lit, RsHashMap, RsHashSet, TernaryVal, Var},
 };
+#[cfg(target_os = "wasi")]
+use std::time::Instant as ProcessTime;
 
-use crate::Error;
+use crate::{Error, GroupId};
 
-use super::{ffi, Options, PreproClauses, Stats};
+use super::{
+    ffi, Assumption, BbtmsOptions, BveOptions, Capabilities, ClauseOrigin, InputError,
+    LabelOptions, LabelStats, ModelSearchOptions, OptionValue, Options, PreproClauses, PreproInit,
+    PreproState, Preservation, Provenance, Reconstructor, Redundancy, RemovedWeight, Scalarized,
+    SkipTechnique, SoftClauses, Stats, Technique, TechniqueInfo, TechniqueStats, Techniques,
+    TraceOp, VarSpace, Version,
+};
 
 /// The main low-abstraction preprocessor type
+///
+/// [`MaxPre`] intentionally does not implement [`Clone`]. MaxPre's
+/// `PreprocessorInterface` cannot be copied, not even by the C++ extensions
+/// of this crate: it owns the proof log as a non-copyable `std::ofstream`
+/// (`plogfile`) and its preprocessor holds a pointer to the state of the
+/// embedded SAT solver. Replaying the inputs on a fresh preprocessor does not
+/// produce the same state either if the time limit is hit during
+/// preprocessing.
+///
+/// [`MaxPre`] is [`Send`] and independent preprocessors can be used in
+/// different threads at the same time. For reading the result of
+/// preprocessing from multiple threads, see [`MaxPre::into_shared`].
 pub struct MaxPre {
     /// The handle for the C API
     handle: *mut ffi::CMaxPre,
@@ -23,9 +49,140 @@ pub struct MaxPre {
     offsets: Vec<isize>,
     /// Statistics of the preprocessor
     stats: Stats,
+    /// The originally loaded instance, kept for evaluating solutions and
+    /// resetting the preprocessor. This is [`None`] if the preprocessor was
+    /// created from a raw handle.
+    orig: Option<OrigInstance>,
+    /// All options that have been set on the preprocessor
+    opts: Options,
+    /// All options that have been set by name, in the order they were set
+    named_opts: Vec<(CString, OptionValue)>,
+    /// Map from labels to the objective and soft clause they were created for
+    label_map: RsHashMap<Lit, (usize, Clause)>,
+    /// The fixed literals already returned by
+    /// [`PreproClauses::fixed_lits_since_last_call`]
+    reported_fixed: RsHashSet<Lit>,
+    /// The objectives in the order they are loaded into MaxPre, see
+    /// [`PreproClauses::set_objective_order`]
+    obj_order: Vec<usize>,
+    /// The state in the inprocessing workflow
+    state: PreproState,
+    /// The redundant clauses, kept outside of MaxPre
+    redundant: Vec<Clause>,
+    /// The variables declared with
+    /// [`PreproClauses::declare_assumption_vars`]
+    assumption_vars: RsHashSet<Var>,
+    /// The number of preprocessing calls each technique was run in
+    runs: RsHashMap<Technique, usize>,
+    /// Whether all output of MaxPre is suppressed, see [`MaxPre::set_quiet`]
+    quiet: bool,
+    /// Whether techniques that introduce variables are left out, see
+    /// [`MaxPre::forbid_aux_vars`]
+    forbid_aux_vars: bool,
+    /// What preprocessing preserves, see [`MaxPre::set_preservation`]
+    preservation: Preservation,
+    /// The origins of clauses, if provenance is tracked, see
+    /// [`MaxPre::track_provenance`]
+    origins: Option<ClauseOrigins>,
 }
 
-impl PreproClauses for MaxPre {
+/// The origins of the clauses of the preprocessed instance of a [`MaxPre`]
+/// object that tracks provenance
+#[derive(Default)]
+struct ClauseOrigins {
+    /// The number of preprocessing calls so far
+    round: usize,
+    /// The origins by objective, [`None`] for hard clauses, and sorted
+    /// literals of the clauses
+    origins: RsHashMap<(Option<usize>, Vec<Lit>), ClauseOrigin>,
+}
+
+impl ClauseOrigins {
+    /// Starts tracking the origins of the clauses of an instance
+    fn new(orig: Option<&OrigInstance>) -> Self {
+        let mut origins = ClauseOrigins::default();
+        if let Some(orig) = orig {
+            for cl in orig.hards.iter().flat_map(Cnf::iter) {
+                origins.insert(None, cl, ClauseOrigin::Original);
+            }
+            for (obj_idx, (softs, _)) in orig.softs.iter().enumerate() {
+                for (cl, _) in softs {
+                    origins.insert(Some(obj_idx), cl, ClauseOrigin::Original);
+                }
+            }
+        }
+        origins
+    }
+
+    fn key(obj_idx: Option<usize>, clause: &Clause) -> (Option<usize>, Vec<Lit>) {
+        let mut lits = clause.iter().copied().collect::<Vec<_>>();
+        lits.sort_unstable();
+        lits.dedup();
+        (obj_idx, lits)
+    }
+
+    /// Records the origin of a clause, unless it is already known
+    fn insert(&mut self, obj_idx: Option<usize>, clause: &Clause, origin: ClauseOrigin) {
+        self.origins
+            .entry(Self::key(obj_idx, clause))
+            .or_insert(origin);
+    }
+
+    fn get(&self, obj_idx: Option<usize>, clause: &Clause) -> ClauseOrigin {
+        self.origins
+            .get(&Self::key(obj_idx, clause))
+            .cloned()
+            .unwrap_or(ClauseOrigin::Added { round: self.round })
+    }
+
+    /// Updates the origins after a preprocessing call that ran `techniques`
+    /// and produced an instance. Clauses that are not part of the instance
+    /// anymore are forgotten.
+    fn update(&mut self, hards: &Cnf, softs: &[SoftClauses], techniques: &str) {
+        let produced = ClauseOrigin::Preprocessed {
+            round: self.round,
+            techniques: crate::techniques_in(techniques),
+        };
+        let mut origins = RsHashMap::default();
+        let clauses = hards.iter().map(|cl| (None, cl)).chain(
+            softs
+                .iter()
+                .enumerate()
+                .flat_map(|(obj_idx, softs)| softs.iter().map(move |(cl, _)| (Some(obj_idx), cl))),
+        );
+        for (obj_idx, cl) in clauses {
+            let key = Self::key(obj_idx, cl);
+            let origin = self
+                .origins
+                .remove(&key)
+                .unwrap_or_else(|| produced.clone());
+            origins.insert(key, origin);
+        }
+        self.origins = origins;
+        self.round += 1;
+    }
+}
+
+/// The instance that a [`MaxPre`] object was initialized with. The hard
+/// clauses are only kept if requested with [`MaxPreBuilder::keep_original`].
+struct OrigInstance {
+    hards: Option<Cnf>,
+    softs: Vec<(SoftClauses, isize)>,
+    inprocessing: bool,
+}
+
+impl OrigInstance {
+    /// Checks whether an assignment satisfies all hard clauses, if they are
+    /// kept
+    fn is_model(&self, assign: &Assignment) -> bool {
+        self.hards
+            .iter()
+            .flat_map(Cnf::iter)
+            .all(|cl| cl.evaluate(assign) == TernaryVal::True)
+    }
+}
+
+impl PreproInit for MaxPre {
     fn signature() -> &'static str {
         let c_chars = unsafe { ffi::cmaxpre_signature() };
         let c_str = unsafe { CStr::from_ptr(c_chars) };
@@ -34,128 +191,166 @@ impl PreproClauses for MaxPre {
             .expect("MaxPre signature returned invalid UTF-8")
     }
 
-    fn new<CI: WClsIter>(hards: Cnf, softs: Vec<(CI, isize)>, inprocessing: bool) -> Self {
-        let mut top = 1;
-        let softs: Vec<(Vec<(Clause, usize)>, isize)> = softs
-            .into_iter()
-            .map(|(cls, ofs)| (cls.into_iter().collect(), ofs))
-            .collect();
-        top = softs.iter().fold(top, |top, softs| {
-            softs.0.iter().fold(top, |top, (_, w)| top + w)
-        });
-        let mut stats = Stats {
-            n_objs: softs.len(),
-            n_orig_hard_clauses: hards.len(),
-            ..Default::default()
+    fn new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let (hards, softs) = crate::collect_instance(hards, softs);
+        Self::from_instance(hards, softs, inprocessing, false)
+    }
+
+    fn scalarize(&self, weights: &[u64]) -> Result<Scalarized<Self>, Error> {
+        let Some((orig, hards)) = self.orig_hards() else {
+            return Err(Error::Generic);
         };
-        let handle = unsafe { ffi::cmaxpre_init_start(top as u64, ffi::map_bool(inprocessing)) };
-        hards.into_iter().for_each(|cl| {
-            cl.into_iter().for_each(|l| {
-                stats.max_orig_var = Self::track_max_var(stats.max_orig_var, l.var());
-                unsafe { ffi::cmaxpre_init_add_lit(handle, l.to_ipasir()) }
-            });
-            unsafe { ffi::cmaxpre_init_add_lit(handle, 0) };
-        });
-        let mut offsets = Vec::new();
-        softs.into_iter().enumerate().for_each(|(idx, softs)| {
-            offsets.push(softs.1);
-            stats.n_orig_soft_clauses.push(softs.0.len());
-            softs.0.into_iter().for_each(|(cl, w)| {
-                // Add zero weight for all previous objectives
-                (0..idx).for_each(|_| unsafe { ffi::cmaxpre_init_add_weight(handle, 0) });
-                // Add weight for the objective with index
-                unsafe { ffi::cmaxpre_init_add_weight(handle, w as u64) };
-                // Add literals
-                cl.into_iter().for_each(|l| {
-                    stats.max_orig_var = Self::track_max_var(stats.max_orig_var, l.var());
-                    unsafe { ffi::cmaxpre_init_add_lit(handle, l.to_ipasir()) }
-                });
-                unsafe { ffi::cmaxpre_init_add_lit(handle, 0) };
-            })
-        });
-        unsafe { ffi::cmaxpre_init_finalize(handle) };
-        Self {
-            handle,
-            offsets,
-            stats,
-        }
+        Scalarized::new(
+            hards.clone(),
+            orig.softs.clone(),
+            &self.label_map,
+            weights,
+            orig.inprocessing,
+        )
     }
+}
 
+impl PreproClauses for MaxPre {
     fn preprocess(&mut self, techniques: &str, log_level: c_int, time_limit: f64) {
         let start = ProcessTime::now();
-        let techniques = CString::new(techniques).unwrap();
-        unsafe {
-            ffi::cmaxpre_preprocess(
-                self.handle,
+        // Declared assumption variables require the equivalence of the
+        // instance, which is the strongest preservation
+        let preservation = if self.assumption_vars.is_empty() {
+            self.preservation
+        } else {
+            Preservation::Equivalence
+        };
+        let techniques = if preservation == Preservation::Satisfiability && !self.forbid_aux_vars {
+            CString::new(techniques).unwrap()
+        } else {
+            let allowed = |t: Technique| {
+                preservation.allows(t) && !(self.forbid_aux_vars && t.introduces_vars())
+            };
+            // Unit propagation is used if no other technique is left, so that
+            // the preprocessed instance is available
+            let restricted = Techniques::new(String::from(techniques))
+                .retain(allowed)
+                .unwrap_or_else(|| Techniques::new(String::from(Technique::Up.char())));
+            CString::new(restricted.as_str()).unwrap()
+        };
+        crate::count_runs(&mut self.runs, techniques.to_str().unwrap());
+        self.quietly(|handle| unsafe {
+            ffi::cmaxpre_ext_preprocess(
+                handle,
                 techniques.as_ptr(),
                 log_level,
                 time_limit,
                 ffi::FALSE,
                 ffi::FALSE,
             )
-        };
-        self.stats.prepro_time += start.elapsed();
+        });
+        self.stats.last_prepro_time = start.elapsed();
+        self.stats.prepro_time += self.stats.last_prepro_time;
+        self.state = PreproState::Preprocessed;
+        if let Some(mut origins) = self.origins.take() {
+            let (hards, softs) = self.read_prepro_instance();
+            origins.update(&hards, &softs, techniques.to_str().unwrap());
+            self.origins = Some(origins);
+        }
+        if !self.redundant.is_empty() {
+            let (hards, softs) = self.read_prepro_instance();
+            let fixed = self.prepro_fixed_lits();
+            crate::retain_redundant(
+                &mut self.redundant,
+                hards
+                    .iter()
+                    .chain(softs.iter().flat_map(|s| s.iter().map(|(cl, _)| cl))),
+                &fixed,
+            );
+        }
+    }
+
+    fn state(&self) -> PreproState {
+        self.state
+    }
+
+    fn is_inprocessing(&self) -> bool {
+        unsafe { ffi::cmaxpre_ext_is_inprocessing(self.handle) == ffi::TRUE }
+    }
+
+    fn hit_time_limit(&self) -> bool {
+        unsafe { ffi::cmaxpre_ext_time_limit_hit(self.handle) == ffi::TRUE }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let techniques = Technique::ALL
+            .into_iter()
+            .filter(|technique| {
+                let mut raw = ffi::CMaxPreTechniqueStats::default();
+                unsafe {
+                    ffi::cmaxpre_ext_get_technique_stats(
+                        self.handle,
+                        technique.char() as c_char,
+                        &mut raw,
+                    ) == ffi::TRUE
+                }
+            })
+            .collect();
+        Capabilities {
+            inprocessing: self.is_inprocessing(),
+            proofs: cfg!(feature = "proofs"),
+            techniques,
+            max_total_weight: crate::MAX_TOTAL_WEIGHT,
+            // Literals are passed as non-zero `c_int`s
+            max_var_idx: c_int::MAX as u32 - 1,
+        }
+    }
+
+    fn technique_runs(&self, technique: Technique) -> usize {
+        self.runs.get(&technique).copied().unwrap_or(0)
     }
 
     fn top_weight(&self) -> u64 {
         unsafe { ffi::cmaxpre_get_top_weight(self.handle) }
     }
 
-    fn n_prepro_clauses(&self) -> c_uint {
-        unsafe { ffi::cmaxpre_get_n_prepro_clauses(self.handle) }
+    fn n_prepro_clauses(&self) -> usize {
+        unsafe { ffi::cmaxpre_ext_get_n_prepro_clauses(self.handle) as usize }
+    }
+
+    fn n_prepro_labels(&self) -> usize {
+        unsafe { ffi::cmaxpre_ext_get_n_prepro_labels(self.handle) as usize }
+    }
+
+    fn n_prepro_fixed_lits(&self) -> usize {
+        unsafe { ffi::cmaxpre_ext_get_n_prepro_fixed(self.handle) as usize }
+    }
+
+    fn n_prepro_vars(&self) -> usize {
+        let mut vars = self.prepro_lits().map(|l| l.var()).collect::<Vec<_>>();
+        vars.sort_unstable();
+        vars.dedup();
+        vars.len()
     }
 
-    fn n_prepro_labels(&self) -> c_uint {
-        unsafe { ffi::cmaxpre_get_n_prepro_labels(self.handle) }
+    fn max_prepro_var(&self) -> Option<Var> {
+        self.prepro_lits().map(|l| l.var()).max()
     }
 
-    fn n_prepro_fixed_lits(&self) -> c_uint {
-        unsafe { ffi::cmaxpre_get_n_prepro_fixed(self.handle) }
+    fn n_aux_vars(&self) -> usize {
+        unsafe { ffi::cmaxpre_ext_get_n_aux_vars(self.handle) as usize }
     }
 
     fn prepro_instance(&mut self) -> (Cnf, Vec<(Vec<(Clause, usize)>, isize)>) {
-        let n_cls = self.n_prepro_clauses();
-        let top = self.top_weight();
-        let mut hards = Cnf::new();
-        let mut softs: Vec<Vec<(Clause, usize)>> = vec![Default::default(); self.stats.n_objs];
-        for cl_idx in 0..n_cls {
-            // Get clause
-            let mut clause = Clause::new();
-            let mut lit_idx = 0;
-            loop {
-                let lit = unsafe { ffi::cmaxpre_get_prepro_lit(self.handle, cl_idx, lit_idx) };
-                if lit == 0 {
-                    break;
-                }
-                let lit = Lit::from_ipasir(lit).unwrap();
+        let (hards, softs) = self.read_prepro_instance();
+        hards
+            .iter()
+            .chain(softs.iter().flat_map(|s| s.iter().map(|(cl, _)| cl)))
+            .flat_map(|cl| cl.iter())
+            .for_each(|lit| {
                 self.stats.max_prepro_var =
                     Self::track_max_var(self.stats.max_prepro_var, lit.var());
-                clause.add(lit);
-                lit_idx += 1;
-            }
-            // Get weights
-            let mut is_hard = true;
-            for obj_idx in 0..self.stats.n_objs {
-                let w = unsafe {
-                    ffi::cmaxpre_get_prepro_weight(self.handle, cl_idx, obj_idx as c_uint)
-                };
-                if w == 0 {
-                    continue;
-                }
-                if w != top {
-                    // Soft clause
-                    if softs.len() < obj_idx + 1 {
-                        softs.resize(obj_idx + 1, Default::default());
-                    }
-                    softs[obj_idx].push((clause.clone(), w as usize));
-                    is_hard = false;
-                }
-            }
-            if is_hard {
-                // Hard clause
-                hards.add_clause(clause);
-            }
-        }
+            });
         self.stats.n_prepro_hard_clauses = hards.len();
         self.stats.n_prepro_soft_clauses = softs.iter().map(|s| s.len()).collect();
         self.stats.removed_weight.clear();
@@ -163,8 +358,7 @@ impl PreproClauses for MaxPre {
             .into_iter()
             .enumerate()
             .map(|(idx, s)| {
-                let rem_weight =
-                    unsafe { ffi::cmaxpre_get_removed_weight(self.handle, idx as c_uint) } as usize;
+                let rem_weight = self.removed_weight_of(idx);
                 self.stats.removed_weight.push(rem_weight);
                 let offset = rem_weight as isize + self.offsets[idx];
                 (s, offset)
@@ -178,20 +372,45 @@ impl PreproClauses for MaxPre {
         let mut lbls = Vec::new();
         for lbl_idx in 0..n_lbls {
             lbls.push(
-                Lit::from_ipasir(unsafe { ffi::cmaxpre_get_prepro_label(self.handle, lbl_idx) })
-                    .unwrap(),
+                Lit::from_ipasir(unsafe {
+                    ffi::cmaxpre_ext_get_prepro_label(self.handle, lbl_idx as u64)
+                })
+                .unwrap(),
             );
         }
         lbls
     }
 
+    fn strata(&self, obj_idx: usize) -> Vec<(u64, Vec<Lit>)> {
+        self.read_prepro_instance()
+            .1
+            .get(obj_idx)
+            .map_or_else(Vec::new, crate::weight_strata)
+    }
+
+    fn label_map(&self) -> &RsHashMap<Lit, (usize, Clause)> {
+        &self.label_map
+    }
+
+    fn set_label_map(&mut self, map: RsHashMap<Lit, (usize, Clause)>) {
+        self.label_map = map;
+    }
+
+    fn objective_names(&self) -> &[Option<String>] {
+        &self.stats.obj_names
+    }
+
+    fn set_objective_names(&mut self, names: Vec<Option<String>>) {
+        crate::set_objective_names(&mut self.stats, names);
+    }
+
     fn prepro_fixed_lits(&self) -> Vec<Lit> {
         let n_fixed = self.n_prepro_fixed_lits();
         let mut fixed = Vec::new();
         for fixed_idx in 0..n_fixed {
             fixed.push(
                 Lit::from_ipasir(unsafe {
-                    ffi::cmaxpre_get_prepro_fixed_lit(self.handle, fixed_idx)
+                    ffi::cmaxpre_ext_get_prepro_fixed_lit(self.handle, fixed_idx as u64)
                 })
                 .unwrap(),
             );
@@ -199,36 +418,167 @@ impl PreproClauses for MaxPre {
         fixed
     }
 
+    fn fixed_lits_since_last_call(&mut self) -> Vec<Lit> {
+        // MaxPre collects the fixed literals anew on every preprocessing call
+        // and does not keep their order, so track which were returned
+        let fixed = self.prepro_fixed_lits();
+        fixed
+            .into_iter()
+            .filter(|&lit| self.reported_fixed.insert(lit))
+            .collect()
+    }
+
     fn max_orig_var(&self) -> Var {
         Lit::from_ipasir(unsafe { ffi::cmaxpre_get_original_variables(self.handle) })
             .unwrap()
             .var()
     }
 
+    fn n_orig_clauses(&self) -> usize {
+        self.stats.n_orig_hard_clauses + self.stats.n_orig_soft_clauses.iter().sum::<usize>()
+    }
+
+    fn n_orig_soft_clauses(&self, obj_idx: usize) -> usize {
+        self.stats.n_orig_soft_clauses[obj_idx]
+    }
+
+    fn n_orig_vars(&self) -> usize {
+        self.stats
+            .max_orig_var
+            .map_or(0, |max_var| max_var.idx() + 1)
+    }
+
+    fn orig_weight(&self, obj_idx: usize) -> usize {
+        self.stats.orig_weight[obj_idx]
+    }
+
+    fn orig_offset(&self, obj_idx: usize) -> isize {
+        self.offsets[obj_idx]
+    }
+
     fn upper_bound(&self) -> u64 {
         unsafe { ffi::cmaxpre_get_upper_bound(self.handle) }
     }
 
     fn reconstruct(&mut self, sol: Assignment) -> Assignment {
+        self.reconstruct_lits(sol)
+    }
+
+    fn reconstruct_lits<I: IntoIterator<Item = Lit>>(&mut self, lits: I) -> Assignment {
         let start = ProcessTime::now();
-        sol.into_iter()
+        lits.into_iter()
             .for_each(|l| unsafe { ffi::cmaxpre_assignment_add(self.handle, l.to_ipasir()) });
         unsafe { ffi::cmaxpre_reconstruct(self.handle) };
-        let max_var = self.max_orig_var();
-        let rec = (1..max_var.pos_lit().to_ipasir() + 1)
-            .map(|l| {
-                if unsafe { ffi::cmaxpre_reconstructed_val(self.handle, l) } > 0 {
-                    Lit::from_ipasir(l).unwrap()
-                } else {
-                    Lit::from_ipasir(-l).unwrap()
-                }
-            })
-            .collect();
+        let rec = self.reconstructed();
         self.stats.reconst_time += start.elapsed();
         rec
     }
 
+    fn reconstruct_partial(&mut self, sol: Assignment) -> Assignment {
+        // MaxPre assigns all variables that neither the solution nor the
+        // trace determines to true, these are left unassigned instead
+        let mut determined: RsHashSet<Var> = sol.iter().map(|l| l.var()).collect();
+        for op in self.trace() {
+            determined.extend(op.vars());
+        }
+        self.reconstruct(sol)
+            .into_iter()
+            .filter(|l| determined.contains(&l.var()))
+            .collect()
+    }
+
+    fn best_model(&mut self) -> Option<(Assignment, Vec<u64>)> {
+        let orig = self.orig.as_ref()?;
+        if unsafe { ffi::cmaxpre_ext_reconstruct_best_model(self.handle) } == ffi::FALSE {
+            return None;
+        }
+        let model = self.reconstructed();
+        // The model might have been found before later techniques changed the
+        // instance, check that it is still valid after reconstruction
+        if !orig.is_model(&model) {
+            return None;
+        }
+        let costs = crate::costs(&orig.softs, &model);
+        Some((model, costs))
+    }
+
+    fn phase_hints(&self) -> Vec<Lit> {
+        let mut lits: Vec<c_int> = self.prepro_lits().map(|l| l.var().to_ipasir()).collect();
+        lits.sort_unstable();
+        lits.dedup();
+        unsafe {
+            ffi::cmaxpre_ext_best_model_phases(self.handle, lits.as_mut_ptr(), lits.len() as c_uint)
+        };
+        lits.into_iter()
+            .filter_map(|lit| Lit::from_ipasir(lit).ok())
+            .collect()
+    }
+
+    fn eval_objectives(&self, sol: &Assignment, space: VarSpace) -> Vec<u64> {
+        match space {
+            VarSpace::Original => {
+                let orig = self
+                    .orig
+                    .as_ref()
+                    .expect("original instance of MaxPre created from a raw handle is unknown");
+                crate::costs(&orig.softs, sol)
+            }
+            VarSpace::Preprocessed => {
+                let (_, softs) = self.read_prepro_instance();
+                softs
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, softs)| {
+                        let cost: u64 = softs
+                            .iter()
+                            .filter(|(cl, _)| cl.evaluate(sol) != TernaryVal::True)
+                            .map(|(_, w)| *w as u64)
+                            .sum();
+                        cost + self.removed_weight_of(idx) as u64
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn set_initial_model(&mut self, model: Assignment) -> Result<(), Error> {
+        let Some(orig) = &self.orig else {
+            return Err(Error::Generic);
+        };
+        if !orig.is_model(&model) {
+            return Err(Error::Generic);
+        }
+        // MaxPre only tracks the cost of the first objective
+        let cost = self
+            .obj_order
+            .first()
+            .map_or(0, |&obj_idx| crate::costs(&orig.softs, &model)[obj_idx]);
+        model
+            .into_iter()
+            .for_each(|l| unsafe { ffi::cmaxpre_assignment_add(self.handle, l.to_ipasir()) });
+        unsafe { ffi::cmaxpre_ext_set_initial_model(self.handle, cost) };
+        Ok(())
+    }
+
+    fn local_search(&mut self, time_limit: f64) -> Option<Assignment> {
+        if self.quietly(|handle| unsafe { ffi::cmaxpre_ext_local_search(handle, time_limit) })
+            == ffi::FALSE
+        {
+            return None;
+        }
+        let model = self.reconstructed();
+        if self
+            .orig
+            .as_ref()
+            .is_some_and(|orig| !orig.is_model(&model))
+        {
+            return None;
+        }
+        Some(model)
+    }
+
     fn add_var(&mut self) -> Result<Var, Error> {
+        self.check_inprocessing()?;
         let v = unsafe { ffi::cmaxpre_add_var(self.handle, 0) };
         if v == 0 {
             return Err(Error::Generic);
@@ -236,94 +586,326 @@ impl PreproClauses for MaxPre {
         Ok(Lit::from_ipasir(v).unwrap().var())
     }
 
+    fn add_vars(&mut self, n: usize) -> Result<Range<Var>, Error> {
+        self.check_inprocessing()?;
+        let n = Self::var_count(n)?;
+        let first = unsafe { ffi::cmaxpre_ext_add_vars(self.handle, n) };
+        if first == 0 {
+            return Err(Error::Generic);
+        }
+        let first = Lit::from_ipasir(first).unwrap().var();
+        Ok(first..first + n)
+    }
+
+    fn reserve_vars(&mut self, n: usize) -> Result<(), Error> {
+        let n = Self::var_count(n)?;
+        unsafe { ffi::cmaxpre_ext_reserve_vars(self.handle, n) };
+        Ok(())
+    }
+
     fn add_clause(&mut self, clause: Clause) -> Result<(), Error> {
-        clause.into_iter().for_each(|l| unsafe {
+        self.check_inprocessing()?;
+        clause.iter().for_each(|l| unsafe {
             ffi::cmaxpre_add_lit(self.handle, l.to_ipasir());
         });
         if unsafe { ffi::cmaxpre_add_lit(self.handle, 0) } == ffi::FALSE {
             return Err(Error::Generic);
         }
+        self.state.modify();
+        if let Some(origins) = &mut self.origins {
+            let round = origins.round;
+            origins.insert(None, &clause, ClauseOrigin::Added { round });
+        }
         Ok(())
     }
 
+    fn add_clause_with_redundancy(
+        &mut self,
+        clause: Clause,
+        redundancy: Redundancy,
+    ) -> Result<(), Error> {
+        match redundancy {
+            Redundancy::Irredundant => self.add_clause(clause),
+            Redundancy::Redundant => {
+                self.check_inprocessing()?;
+                self.redundant.push(clause);
+                Ok(())
+            }
+        }
+    }
+
+    fn prepro_redundant_clauses(&self) -> Vec<Clause> {
+        self.redundant.clone()
+    }
+
+    fn declare_assumption_vars(&mut self, vars: &[Var]) {
+        self.assumption_vars.extend(vars.iter().copied());
+    }
+
+    fn map_assumption(&self, lit: Lit) -> Option<Assumption> {
+        if !self.assumption_vars.contains(&lit.var()) {
+            return None;
+        }
+        if self.state == PreproState::Loaded {
+            return Some(Assumption::Lit(lit));
+        }
+        let fixed = self.prepro_fixed_lits();
+        if fixed.contains(&lit) {
+            return Some(Assumption::Satisfied);
+        }
+        if fixed.contains(&!lit) {
+            return Some(Assumption::Falsified);
+        }
+        let prepro = unsafe { ffi::cmaxpre_ext_prepro_lit(self.handle, lit.to_ipasir()) };
+        Some(Lit::from_ipasir(prepro).map_or(Assumption::Satisfied, Assumption::Lit))
+    }
+
     fn add_label(&mut self, label: Lit, weight: usize) -> Result<Lit, Error> {
+        self.check_inprocessing()?;
         let l = unsafe { ffi::cmaxpre_add_label(self.handle, label.to_ipasir(), weight as u64) };
         if l == 0 {
             return Err(Error::Generic);
         }
+        self.state.modify();
         Ok(Lit::from_ipasir(l).unwrap())
     }
 
+    fn add_group<CI: IntoIterator<Item = Clause>>(
+        &mut self,
+        obj_idx: usize,
+        clauses: CI,
+        weight: usize,
+    ) -> Result<GroupId, Error> {
+        self.check_inprocessing()?;
+        // The C API only supports adding labels to the first objective
+        if self.internal_obj(obj_idx) != 0 {
+            return Err(Error::Generic);
+        }
+        // Passing 0 makes MaxPre use a new variable as the label
+        let l = unsafe { ffi::cmaxpre_add_label(self.handle, 0, weight as u64) };
+        if l == 0 {
+            return Err(Error::Generic);
+        }
+        self.state.modify();
+        let label = Lit::from_ipasir(l).unwrap();
+        for mut clause in clauses {
+            clause.add(label);
+            self.add_clause(clause)?;
+        }
+        Ok(GroupId(label))
+    }
+
     fn alter_weight(&mut self, label: Lit, weight: usize) -> Result<(), Error> {
+        self.check_inprocessing()?;
         if unsafe { ffi::cmaxpre_alter_weight(self.handle, label.to_ipasir(), weight as u64) }
             == ffi::FALSE
         {
             return Err(Error::Generic);
         }
+        self.state.modify();
+        Ok(())
+    }
+
+    fn alter_weights<I: IntoIterator<Item = (Lit, u64)>>(
+        &mut self,
+        updates: I,
+    ) -> Result<(), Error> {
+        self.check_inprocessing()?;
+        let (labels, weights): (Vec<c_int>, Vec<u64>) = updates
+            .into_iter()
+            .map(|(label, weight)| (label.to_ipasir(), weight))
+            .unzip();
+        if unsafe {
+            ffi::cmaxpre_ext_alter_weights(
+                self.handle,
+                labels.as_ptr(),
+                weights.as_ptr(),
+                labels.len() as c_uint,
+            )
+        } == ffi::FALSE
+        {
+            return Err(Error::Generic);
+        }
+        self.state.modify();
         Ok(())
     }
 
     fn label_to_var(&mut self, label: Lit) -> Result<(), Error> {
+        self.check_inprocessing()?;
         if unsafe { ffi::cmaxpre_label_to_var(self.handle, label.to_ipasir()) } == ffi::FALSE {
             return Err(Error::Generic);
         }
+        self.state.modify();
         Ok(())
     }
 
     fn reset_removed_weight(&mut self) -> Result<(), Error> {
+        self.check_inprocessing()?;
         if unsafe { ffi::cmaxpre_reset_removed_weight(self.handle) } == ffi::FALSE {
             return Err(Error::Generic);
         }
         Ok(())
     }
 
-    fn removed_weight(&mut self) -> Vec<usize> {
+    fn reset_removed_weight_for(&mut self, obj_idx: usize) -> Result<(), Error> {
+        self.check_inprocessing()?;
+        if obj_idx >= self.stats.n_objs
+            || unsafe {
+                ffi::cmaxpre_ext_reset_removed_weight_for(
+                    self.handle,
+                    self.internal_obj(obj_idx) as c_uint,
+                )
+            } == ffi::FALSE
+        {
+            return Err(Error::Generic);
+        }
+        Ok(())
+    }
+
+    fn removed_weight(&mut self) -> RemovedWeight {
         self.stats.removed_weight = (0..self.stats.n_objs)
-            .map(|obj_idx| unsafe {
-                ffi::cmaxpre_get_removed_weight(self.handle, obj_idx as c_uint)
-            } as usize)
+            .map(|obj_idx| self.removed_weight_of(obj_idx))
             .collect();
-        self.stats.removed_weight.clone()
+        let fixed = self.prepro_fixed_lits();
+        let objs = self.orig.as_ref().map_or(&[][..], |orig| &orig.softs[..]);
+        RemovedWeight::new(objs, &fixed, &self.stats.removed_weight)
+    }
+
+    fn set_objective_order(&mut self, order: &[usize]) -> Result<(), Error> {
+        if !crate::is_permutation(order, self.stats.n_objs) || self.orig_hards().is_none() {
+            return Err(Error::Generic);
+        }
+        self.obj_order = order.to_vec();
+        self.reset()
+    }
+
+    fn objective_order(&self) -> Vec<usize> {
+        self.obj_order.clone()
     }
 
     fn set_options(&mut self, opts: Options) {
-        if let Some(val) = opts.bve_sort_max_first {
-            unsafe { ffi::cmaxpre_set_bve_gate_extraction(self.handle, ffi::map_bool(val)) };
+        let opts = opts.resolved();
+        self.apply_options(&opts);
+        self.opts.update(&opts);
+    }
+
+    fn options(&self) -> Options {
+        let mut raw = ffi::CMaxPreOptions::default();
+        unsafe { ffi::cmaxpre_ext_get_options(self.handle, &mut raw) };
+        Options {
+            bve: BveOptions {
+                gate_extraction: Some(raw.bve_gate_extraction == ffi::TRUE),
+                sort_max_first: Some(raw.bve_sort_max_first == ffi::TRUE),
+                local_grow_limit: Some(raw.bve_local_grow_limit),
+                global_grow_limit: Some(raw.bve_global_grow_limit),
+            },
+            bbtms: BbtmsOptions {
+                max_vars: Some(raw.max_bbtms_vars),
+            },
+            model_search: ModelSearchOptions {
+                harden: Some(raw.harden_in_model_search == ffi::TRUE),
+                iter_limit: Some(raw.model_search_iter_limit),
+            },
+            labels: LabelOptions {
+                matching: Some(raw.label_matching == ffi::TRUE),
+            },
+            skip_technique: Some(SkipTechnique::from_c_value(raw.skip_technique)),
+            ..Default::default()
         }
-        if let Some(val) = opts.label_matching {
-            unsafe { ffi::cmaxpre_set_label_matching(self.handle, ffi::map_bool(val)) };
-        }
-        if let Some(val) = opts.skip_technique {
-            unsafe { ffi::cmaxpre_set_skip_technique(self.handle, val) };
-        }
-        if let Some(val) = opts.bve_sort_max_first {
-            unsafe { ffi::cmaxpre_set_bve_sort_max_first(self.handle, ffi::map_bool(val)) };
-        }
-        if let Some(val) = opts.bve_local_grow_limit {
-            unsafe { ffi::cmaxpre_set_bve_local_grow_limit(self.handle, val) };
-        }
-        if let Some(val) = opts.bve_global_grow_limit {
-            unsafe { ffi::cmaxpre_set_bve_global_grow_limit(self.handle, val) };
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        let Some((orig, hards)) = self.orig_hards() else {
+            return Err(Error::Generic);
+        };
+        unsafe { ffi::cmaxpre_release(self.handle) };
+        let (handle, stats) = Self::init(hards, &orig.softs, &self.obj_order, orig.inprocessing);
+        self.handle = handle;
+        let obj_names = core::mem::take(&mut self.stats.obj_names);
+        self.stats = stats;
+        self.stats.obj_names = obj_names;
+        self.reported_fixed.clear();
+        self.state = PreproState::Loaded;
+        self.redundant.clear();
+        self.runs.clear();
+        if self.origins.is_some() {
+            self.origins = Some(ClauseOrigins::new(self.orig.as_ref()));
         }
-        if let Some(val) = opts.max_bbtms_vars {
-            unsafe { ffi::cmaxpre_set_max_bbtms_vars(self.handle, val) };
+        self.apply_options(&self.opts);
+        for (name, value) in &self.named_opts {
+            self.apply_named_option(name, *value);
         }
-        if let Some(val) = opts.harden_in_model_search {
-            unsafe { ffi::cmaxpre_set_harden_in_model_search(self.handle, ffi::map_bool(val)) };
+        Ok(())
+    }
+
+    fn label_stats(&self) -> LabelStats {
+        let top = self.top_weight();
+        let n_objs = self.stats.n_objs;
+        let mut n_prepro_labels = vec![0; n_objs];
+        let mut prepro_weight = vec![0; n_objs];
+        for cl_idx in 0..self.n_prepro_clauses() as u64 {
+            for obj_idx in 0..n_objs {
+                let weight = unsafe {
+                    ffi::cmaxpre_ext_get_prepro_weight(self.handle, cl_idx, obj_idx as c_uint)
+                };
+                if weight == 0 || weight == top {
+                    continue;
+                }
+                let obj_idx = self.obj_order.get(obj_idx).copied().unwrap_or(obj_idx);
+                n_prepro_labels[obj_idx] += 1;
+                prepro_weight[obj_idx] += weight;
+            }
         }
-        if let Some(val) = opts.model_search_iter_limits {
-            unsafe { ffi::cmaxpre_set_model_search_iter_limit(self.handle, val) };
+        LabelStats {
+            n_orig_labels: self.stats.n_orig_soft_clauses.clone(),
+            orig_weight: self.stats.orig_weight.iter().map(|&w| w as u64).collect(),
+            n_prepro_labels,
+            prepro_weight,
         }
     }
 
     fn print_instance(&self) {
-        unsafe { ffi::cmaxpre_print_instance_stdout(self.handle) }
+        unsafe { ffi::cmaxpre_print_instance_stdout(self.handle, ffi::INPUT_FORMAT_WPMS22) }
+    }
+
+    fn write_prepro_dimacs(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        crate::write_objective_names(writer, &self.stats.obj_names)?;
+        let top = self.top_weight();
+        let n_objs = self.stats.n_objs;
+        for cl_idx in 0..self.n_prepro_clauses() as u64 {
+            let lits = || {
+                (0..)
+                    .map(move |lit_idx| unsafe {
+                        ffi::cmaxpre_ext_get_prepro_lit(self.handle, cl_idx, lit_idx)
+                    })
+                    .take_while(|&lit| lit != 0)
+                    .map(|lit| Lit::from_ipasir(lit).unwrap())
+            };
+            let mut is_hard = true;
+            for obj_idx in 0..n_objs {
+                let weight = unsafe {
+                    ffi::cmaxpre_ext_get_prepro_weight(self.handle, cl_idx, obj_idx as c_uint)
+                };
+                if weight == 0 || weight == top {
+                    continue;
+                }
+                is_hard = false;
+                let obj_idx = self.obj_order.get(obj_idx).copied().unwrap_or(obj_idx);
+                crate::write_dimacs_clause(writer, n_objs, Some((obj_idx, weight)), lits())?;
+            }
+            if is_hard {
+                crate::write_dimacs_clause(writer, n_objs, None, lits())?;
+            }
+        }
+        Ok(())
     }
 
     fn print_solution(&self, sol: Assignment, weight: usize) {
         sol.into_iter()
             .for_each(|l| unsafe { ffi::cmaxpre_assignment_add(self.handle, l.to_ipasir()) });
-        unsafe { ffi::cmaxpre_print_solution_stdout(self.handle, weight as u64) }
+        unsafe {
+            ffi::cmaxpre_print_solution_stdout(self.handle, weight as u64, ffi::INPUT_FORMAT_WPMS22)
+        }
     }
 
     fn print_map(&self) {
@@ -345,9 +927,368 @@ impl PreproClauses for MaxPre {
     fn stats(&self) -> Stats {
         self.stats.clone()
     }
+
+    fn technique_stats(&self) -> Vec<TechniqueStats> {
+        Technique::ALL
+            .into_iter()
+            .filter_map(|technique| {
+                let mut raw = ffi::CMaxPreTechniqueStats::default();
+                let valid = unsafe {
+                    ffi::cmaxpre_ext_get_technique_stats(
+                        self.handle,
+                        technique.char() as c_char,
+                        &mut raw,
+                    )
+                };
+                debug_assert_eq!(valid, ffi::TRUE);
+                let stats = TechniqueStats {
+                    technique,
+                    time: Duration::from_secs_f64(raw.time),
+                    invocations: if raw.time_limit < 0. {
+                        None
+                    } else {
+                        Some(raw.invocations as usize)
+                    },
+                    removed_clauses: raw.removed_clauses as isize,
+                    removed_vars: raw.removed_variables as isize,
+                    removed_lits: raw.removed_literals as isize,
+                    removed_labels: raw.removed_labels as isize,
+                    hit_limit: raw.time_limit > 0. && raw.time >= raw.time_limit,
+                };
+                // Only report techniques that have been run
+                if stats.time.is_zero() && stats.invocations.unwrap_or(0) == 0 {
+                    return None;
+                }
+                Some(stats)
+            })
+            .collect()
+    }
+
+    fn trace(&self) -> Vec<TraceOp> {
+        let n_ops = unsafe { ffi::cmaxpre_ext_get_n_trace_ops(self.handle) };
+        (0..n_ops)
+            .map(|op_idx| {
+                let len = unsafe { ffi::cmaxpre_ext_get_trace_data_len(self.handle, op_idx) };
+                let data: Vec<c_int> = (0..len)
+                    .map(|data_idx| unsafe {
+                        ffi::cmaxpre_ext_get_trace_data(self.handle, op_idx, data_idx)
+                    })
+                    .collect();
+                match unsafe { ffi::cmaxpre_ext_get_trace_op(self.handle, op_idx) } {
+                    1 => TraceOp::Fix {
+                        lit: Lit::new(data[0] as u32, data[1] == 0),
+                    },
+                    2 => TraceOp::Bve {
+                        var: Var::new(data[0] as u32),
+                        clauses: internal_clauses(&data[1..]),
+                    },
+                    3 => TraceOp::Bce {
+                        lit: internal_lit(data[0]),
+                        clause: data[1..].iter().map(|&l| internal_lit(l)).collect(),
+                    },
+                    4 => TraceOp::Bcr {
+                        lbl1: internal_lit(data[0]),
+                        lbl2: internal_lit(data[1]),
+                        clauses: internal_clauses(&data[2..]),
+                    },
+                    5 => TraceOp::Equal {
+                        lit: internal_lit(data[1]),
+                        equal_to: internal_lit(data[0]),
+                    },
+                    6 => TraceOp::Ls {
+                        label: internal_lit(data[0]),
+                        lit: internal_lit(data[1]),
+                        clause: data[2..].iter().map(|&l| internal_lit(l)).collect(),
+                    },
+                    7 => TraceOp::LabelElim {
+                        lbl1: internal_lit(data[0]),
+                        lbl2: internal_lit(data[1]),
+                        taut: internal_lit(data[2]),
+                    },
+                    op => panic!("unknown trace operation {op}"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Converts a literal in MaxPre's internal representation
+fn internal_lit(lit: c_int) -> Lit {
+    Lit::new((lit >> 1) as u32, lit & 1 == 1)
+}
+
+/// Converts a sequence of internal literals of clauses that are each
+/// terminated by -1
+fn internal_clauses(data: &[c_int]) -> Vec<Clause> {
+    data.split(|&l| l == -1)
+        // The last clause is terminated as well, leaving an empty trailing split
+        .take(data.iter().filter(|&&l| l == -1).count())
+        .map(|cl| cl.iter().map(|&l| internal_lit(l)).collect())
+        .collect()
 }
 
 impl MaxPre {
+    /// Checks that the preprocessor is in inprocessing mode, which the C API
+    /// requires for modifying the instance
+    fn check_inprocessing(&self) -> Result<(), Error> {
+        if self.is_inprocessing() {
+            Ok(())
+        } else {
+            Err(Error::NotInprocessing)
+        }
+    }
+
+    /// Converts a number of variables to add for the C API
+    fn var_count(n: usize) -> Result<c_uint, Error> {
+        c_uint::try_from(n).map_err(|_| Error::InvalidInput(InputError::TooManyVars(n)))
+    }
+
+    /// Gets the original instance and its hard clauses, if they are kept
+    fn orig_hards(&self) -> Option<(&OrigInstance, &Cnf)> {
+        let orig = self.orig.as_ref()?;
+        Some((orig, orig.hards.as_ref()?))
+    }
+
+    /// Creates a preprocessor for a collected instance, keeping the original
+    /// hard clauses if `keep_original` is set
+    fn from_instance(
+        hards: Cnf,
+        softs: Vec<(SoftClauses, isize)>,
+        inprocessing: bool,
+        keep_original: bool,
+    ) -> Self {
+        let obj_order: Vec<usize> = (0..softs.len()).collect();
+        let (handle, stats) = Self::init(&hards, &softs, &obj_order, inprocessing);
+        let offsets = softs.iter().map(|(_, ofs)| *ofs).collect();
+        let mut label_map = RsHashMap::default();
+        for (obj_idx, (softs, _)) in softs.iter().enumerate() {
+            for (cl, _) in softs {
+                if cl.len() == 1 {
+                    label_map
+                        .entry(cl[0])
+                        .or_insert_with(|| (obj_idx, cl.clone()));
+                }
+            }
+        }
+        Self {
+            handle,
+            offsets,
+            stats,
+            orig: Some(OrigInstance {
+                hards: keep_original.then_some(hards),
+                softs,
+                inprocessing,
+            }),
+            opts: Options::default(),
+            named_opts: vec![],
+            label_map,
+            reported_fixed: RsHashSet::default(),
+            obj_order,
+            state: PreproState::Loaded,
+            redundant: vec![],
+            assumption_vars: RsHashSet::default(),
+            runs: RsHashMap::default(),
+            quiet: false,
+            forbid_aux_vars: false,
+            preservation: Preservation::default(),
+            origins: None,
+        }
+    }
+
+    /// Initializes a C preprocessor handle with an instance and collects the
+    /// statistics of the original instance. The objectives are loaded in the
+    /// given order.
+    fn init(
+        hards: &Cnf,
+        softs: &[(SoftClauses, isize)],
+        obj_order: &[usize],
+        inprocessing: bool,
+    ) -> (*mut ffi::CMaxPre, Stats) {
+        let top = softs.iter().fold(1, |top, softs| {
+            softs.0.iter().fold(top, |top, (_, w)| top + w)
+        });
+        let mut stats = Stats {
+            n_objs: softs.len(),
+            n_orig_hard_clauses: hards.len(),
+            ..Default::default()
+        };
+        let handle = unsafe { ffi::cmaxpre_init_start(top as u64, ffi::map_bool(inprocessing)) };
+        let n_clauses = hards.len() + softs.iter().map(|softs| softs.0.len()).sum::<usize>();
+        unsafe { ffi::cmaxpre_ext_init_reserve(handle, n_clauses as u64) };
+        hards.iter().for_each(|cl| {
+            cl.iter().for_each(|l| {
+                stats.max_orig_var = Self::track_max_var(stats.max_orig_var, l.var());
+                unsafe { ffi::cmaxpre_init_add_lit(handle, l.to_ipasir()) }
+            });
+            unsafe { ffi::cmaxpre_init_add_lit(handle, 0) };
+        });
+        softs.iter().for_each(|softs| {
+            stats.n_orig_soft_clauses.push(softs.0.len());
+            stats.orig_weight.push(softs.0.iter().map(|(_, w)| w).sum());
+        });
+        obj_order.iter().enumerate().for_each(|(idx, &obj_idx)| {
+            softs[obj_idx].0.iter().for_each(|(cl, w)| {
+                // Add zero weight for all previous objectives
+                (0..idx).for_each(|_| unsafe { ffi::cmaxpre_init_add_weight(handle, 0) });
+                // Add weight for the objective with index
+                unsafe { ffi::cmaxpre_init_add_weight(handle, *w as u64) };
+                // Add literals
+                cl.iter().for_each(|l| {
+                    stats.max_orig_var = Self::track_max_var(stats.max_orig_var, l.var());
+                    unsafe { ffi::cmaxpre_init_add_lit(handle, l.to_ipasir()) }
+                });
+                unsafe { ffi::cmaxpre_init_add_lit(handle, 0) };
+            })
+        });
+        unsafe { ffi::cmaxpre_init_finalize(handle) };
+        (handle, stats)
+    }
+
+    /// Applies [resolved](Options::resolved) options to the C preprocessor
+    fn apply_options(&self, opts: &Options) {
+        if let Some(val) = opts.bve.gate_extraction {
+            unsafe { ffi::cmaxpre_set_bve_gate_extraction(self.handle, ffi::map_bool(val)) };
+        }
+        if let Some(val) = opts.labels.matching {
+            unsafe { ffi::cmaxpre_set_label_matching(self.handle, ffi::map_bool(val)) };
+        }
+        if let Some(val) = opts.skip_technique {
+            unsafe { ffi::cmaxpre_set_skip_technique(self.handle, val.c_value()) };
+        }
+        if let Some(val) = opts.bve.sort_max_first {
+            unsafe { ffi::cmaxpre_set_bve_sort_max_first(self.handle, ffi::map_bool(val)) };
+        }
+        if let Some(val) = opts.bve.local_grow_limit {
+            unsafe { ffi::cmaxpre_set_bve_local_grow_limit(self.handle, val) };
+        }
+        if let Some(val) = opts.bve.global_grow_limit {
+            unsafe { ffi::cmaxpre_set_bve_global_grow_limit(self.handle, val) };
+        }
+        if let Some(val) = opts.bbtms.max_vars {
+            unsafe { ffi::cmaxpre_set_max_bbtms_vars(self.handle, val) };
+        }
+        if let Some(val) = opts.model_search.harden {
+            unsafe { ffi::cmaxpre_set_harden_in_model_search(self.handle, ffi::map_bool(val)) };
+        }
+        if let Some(val) = opts.model_search.iter_limit {
+            unsafe { ffi::cmaxpre_set_model_search_iter_limit(self.handle, val) };
+        }
+    }
+
+    /// Applies an option by name to the C preprocessor, returns whether the
+    /// option exists
+    fn apply_named_option(&self, name: &CStr, value: OptionValue) -> bool {
+        let name = name.as_ptr();
+        let res = match value {
+            OptionValue::Int(val) => unsafe {
+                ffi::cmaxpre_ext_set_int_option(self.handle, name, val)
+            },
+            OptionValue::Bool(val) => unsafe {
+                ffi::cmaxpre_ext_set_bool_option(self.handle, name, ffi::map_bool(val))
+            },
+            OptionValue::Double(val) => unsafe {
+                ffi::cmaxpre_ext_set_double_option(self.handle, name, val)
+            },
+            OptionValue::U64(val) => unsafe {
+                ffi::cmaxpre_ext_set_uint64_option(self.handle, name, val)
+            },
+        };
+        res == ffi::TRUE
+    }
+
+    /// Reads the clauses of the preprocessed instance from MaxPre, without
+    /// updating the statistics
+    fn read_prepro_instance(&self) -> (Cnf, Vec<SoftClauses>) {
+        let n_cls = self.n_prepro_clauses();
+        let top = self.top_weight();
+        let mut hards = Cnf::new();
+        let mut softs: Vec<SoftClauses> = vec![Default::default(); self.stats.n_objs];
+        for cl_idx in 0..n_cls {
+            // Get clause
+            let mut clause = Clause::new();
+            let mut lit_idx = 0;
+            loop {
+                let lit =
+                    unsafe { ffi::cmaxpre_ext_get_prepro_lit(self.handle, cl_idx as u64, lit_idx) };
+                if lit == 0 {
+                    break;
+                }
+                clause.add(Lit::from_ipasir(lit).unwrap());
+                lit_idx += 1;
+            }
+            // Get weights
+            let mut is_hard = true;
+            for obj_idx in 0..self.stats.n_objs {
+                let w = unsafe {
+                    ffi::cmaxpre_ext_get_prepro_weight(
+                        self.handle,
+                        cl_idx as u64,
+                        obj_idx as c_uint,
+                    )
+                };
+                if w == 0 {
+                    continue;
+                }
+                if w != top {
+                    // Soft clause
+                    let obj_idx = self.obj_order.get(obj_idx).copied().unwrap_or(obj_idx);
+                    if softs.len() < obj_idx + 1 {
+                        softs.resize(obj_idx + 1, Default::default());
+                    }
+                    softs[obj_idx].push((clause.clone(), w as usize));
+                    is_hard = false;
+                }
+            }
+            if is_hard {
+                // Hard clause
+                hards.add_clause(clause);
+            }
+        }
+        (hards, softs)
+    }
+
+    /// Gets the weight removed from an objective by preprocessing
+    fn removed_weight_of(&self, obj_idx: usize) -> usize {
+        let obj_idx = self.internal_obj(obj_idx);
+        unsafe { ffi::cmaxpre_get_removed_weight(self.handle, obj_idx as c_uint) as usize }
+    }
+
+    /// Gets the index of an objective in MaxPre, see
+    /// [`PreproClauses::set_objective_order`]
+    fn internal_obj(&self, obj_idx: usize) -> usize {
+        self.obj_order
+            .iter()
+            .position(|&idx| idx == obj_idx)
+            .unwrap_or(obj_idx)
+    }
+
+    /// Reads the reconstructed assignment of all original variables from the
+    /// C API
+    fn reconstructed(&self) -> Assignment {
+        let max_var = self.max_orig_var();
+        (1..max_var.pos_lit().to_ipasir() + 1)
+            .map(|l| {
+                if unsafe { ffi::cmaxpre_reconstructed_val(self.handle, l) } > 0 {
+                    Lit::from_ipasir(l).unwrap()
+                } else {
+                    Lit::from_ipasir(-l).unwrap()
+                }
+            })
+            .collect()
+    }
+
+    /// Iterates over all literals in the preprocessed clauses
+    fn prepro_lits(&self) -> impl Iterator<Item = Lit> + '_ {
+        (0..self.n_prepro_clauses()).flat_map(move |cl_idx| {
+            (0..)
+                .map(move |lit_idx| unsafe {
+                    ffi::cmaxpre_ext_get_prepro_lit(self.handle, cl_idx as u64, lit_idx)
+                })
+                .take_while(|&lit| lit != 0)
+                .map(|lit| Lit::from_ipasir(lit).unwrap())
+        })
+    }
+
     /// Tracks a maximum variable
     fn track_max_var(max_var: Option<Var>, new_var: Var) -> Option<Var> {
         match max_var {
@@ -363,24 +1304,2488 @@ impl MaxPre {
     }
 }
 
-impl Drop for MaxPre {
-    fn drop(&mut self) {
-        unsafe { ffi::cmaxpre_release(self.handle) }
+impl MaxPre {
+    /// Initializes a new preprocessor as [`PreproInit::new`], but validates the
+    /// instance before passing it to MaxPre, which does not check it. Soft
+    /// clauses with weight zero do not affect the objective and are dropped,
+    /// so the indices of soft clauses in the preprocessor skip them.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidInput`] if a soft clause is empty or a tautology, or
+    ///   if a variable exceeds [`Capabilities::max_var_idx`]
+    /// - [`Error::WeightOverflow`] if a weight does not fit in [`usize`] or the
+    ///   total weight exceeds [`Capabilities::max_total_weight`]
+    pub fn try_new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Result<Self, Error>
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let (hards, mut softs) = crate::try_collect_instance(hards, softs)?;
+        Self::validate(&hards, &mut softs)?;
+        Ok(Self::from_instance(hards, softs, inprocessing, false))
+    }
+
+    /// Validates an instance for [`MaxPre::try_new`] and
+    /// [`MaxPreBuilder::build`] and drops soft clauses with weight zero
+    fn validate(hards: &Cnf, softs: &mut [(SoftClauses, isize)]) -> Result<(), Error> {
+        let max_var_idx = c_int::MAX as u32 - 1;
+        let check_vars = |cl: &Clause| match cl.iter().find(|l| l.var().idx32() > max_var_idx) {
+            Some(lit) => Err(Error::InvalidInput(InputError::VarOutOfRange(lit.var()))),
+            None => Ok(()),
+        };
+        hards.iter().try_for_each(check_vars)?;
+        let mut total: u64 = 0;
+        for (obj_idx, (cls, _)) in softs.iter().enumerate() {
+            for (cl_idx, (cl, w)) in cls.iter().enumerate() {
+                check_vars(cl)?;
+                if cl.is_empty() {
+                    return Err(Error::InvalidInput(InputError::EmptySoftClause {
+                        obj_idx,
+                        cl_idx,
+                    }));
+                }
+                if cl.iter().any(|&l| cl.iter().any(|&other| other == !l)) {
+                    return Err(Error::InvalidInput(InputError::TautologicalSoftClause {
+                        obj_idx,
+                        cl_idx,
+                    }));
+                }
+                total = total
+                    .checked_add(*w as u64)
+                    .filter(|&total| total <= crate::MAX_TOTAL_WEIGHT)
+                    .ok_or(Error::WeightOverflow)?;
+            }
+        }
+        for (cls, _) in softs.iter_mut() {
+            cls.retain(|(_, w)| *w > 0);
+        }
+        Ok(())
+    }
+
+    /// Gets the version of the linked MaxPre library
+    pub fn version() -> Version {
+        Version::parse(<Self as PreproInit>::signature())
+    }
+
+    /// Gets the full id of the MaxPre commit that was built, if the sources
+    /// were a git checkout. This is resolved by the build script after
+    /// checking out the sources, while [`Version::git_commit`] is parsed from
+    /// the signature of the linked library.
+    pub fn commit() -> Option<&'static str> {
+        option_env!("MAXPRE_COMMIT_ID")
+    }
+
+    /// Gets all techniques that the linked MaxPre library accepts in a
+    /// technique string, as far as they are known to the bindings
+    pub fn supported_techniques() -> Vec<TechniqueInfo> {
+        let chars = unsafe { CStr::from_ptr(ffi::cmaxpre_ext_get_techniques()) };
+        chars
+            .to_bytes()
+            .iter()
+            .filter_map(|&c| TechniqueInfo::from_char(char::from(c)))
+            .collect()
+    }
+
+    /// Sets an option by the name MaxPre uses for it internally, e.g.,
+    /// `"BVE_sizelimit"`. This gives access to options of MaxPre that are not
+    /// part of [`Options`]. Like other options, options set by name are kept
+    /// when [resetting](PreproClauses::reset).
+    ///
+    /// # Errors
+    ///
+    /// If MaxPre has no option with the name, or the type of the value does
+    /// not match the option
+    pub fn set_option_by_name(&mut self, name: &str, value: OptionValue) -> Result<(), Error> {
+        let Ok(name) = CString::new(name) else {
+            return Err(Error::Generic);
+        };
+        if !self.apply_named_option(&name, value) {
+            return Err(Error::Generic);
+        }
+        self.named_opts.push((name, value));
+        Ok(())
+    }
+
+    /// Sets whether all output of MaxPre is suppressed. Some code paths of
+    /// MaxPre, e.g., warnings and SatLike local search, print regardless of
+    /// the log level. While preprocessing or local search runs on a quiet
+    /// preprocessor, everything the calling thread writes to the C++ standard
+    /// streams is dropped. Other threads, including other preprocessors, and
+    /// output that does not go through the C++ streams, e.g., Rust's
+    /// [`println`], are not affected. The explicit printing methods, e.g.,
+    /// [`PreproClauses::print_instance`], are not affected either.
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    /// Whether all output of MaxPre is suppressed, see [`MaxPre::set_quiet`]
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Sets whether techniques that introduce auxiliary variables, see
+    /// [`Technique::introduces_vars`], are left out when preprocessing. The
+    /// labels MaxPre introduces for non-unit soft clauses when loading the
+    /// instance are not affected, [`PreproClauses::n_aux_vars`] counts all
+    /// introduced variables.
+    pub fn forbid_aux_vars(&mut self, forbid: bool) {
+        self.forbid_aux_vars = forbid;
+    }
+
+    /// Sets what preprocessing preserves of the instance. Techniques in the
+    /// technique string that the [`Preservation`] does not allow are skipped
+    /// when preprocessing.
+    pub fn set_preservation(&mut self, preservation: Preservation) {
+        self.preservation = preservation;
+    }
+
+    /// Gets what preprocessing preserves of the instance
+    pub fn preservation(&self) -> Preservation {
+        self.preservation
+    }
+
+    /// Runs a call to the C API that might print, suppressing its output if
+    /// the preprocessor is quiet
+    fn quietly<T>(&self, call: impl FnOnce(*mut ffi::CMaxPre) -> T) -> T {
+        if !self.quiet {
+            return call(self.handle);
+        }
+        unsafe { ffi::cmaxpre_ext_quiet_begin() };
+        let res = call(self.handle);
+        unsafe { ffi::cmaxpre_ext_quiet_end() };
+        res
+    }
+
+    /// Starts tracking which preprocessing call produced or last modified each
+    /// clause of the preprocessed instance, see [`MaxPre::provenance`]. This
+    /// keeps the origin of every clause in memory. Clauses that MaxPre
+    /// introduces when loading the instance, e.g., for labelling non-unit soft
+    /// clauses, are attributed to the first preprocessing call. Clauses added
+    /// with [`PreproClauses::add_clause`] are tagged as added, other
+    /// modifications through the inprocessing interface are attributed to the
+    /// next preprocessing call.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidState`] if the instance was already preprocessed
+    /// - [`Error::Generic`] if the original hard clauses are not
+    ///   [kept](MaxPreBuilder::keep_original)
+    pub fn track_provenance(&mut self) -> Result<(), Error> {
+        if self.state != PreproState::Loaded {
+            return Err(Error::InvalidState);
+        }
+        if self.orig_hards().is_none() {
+            return Err(Error::Generic);
+        }
+        if self.origins.is_none() {
+            self.origins = Some(ClauseOrigins::new(self.orig.as_ref()));
+        }
+        Ok(())
+    }
+
+    /// Gets the origins of the clauses of the preprocessed instance, returns
+    /// [`None`] if provenance is not [tracked](MaxPre::track_provenance)
+    pub fn provenance(&self) -> Option<Provenance> {
+        let origins = self.origins.as_ref()?;
+        let (hards, softs) = self.read_prepro_instance();
+        Some(Provenance {
+            hards: hards.iter().map(|cl| origins.get(None, cl)).collect(),
+            softs: softs
+                .iter()
+                .enumerate()
+                .map(|(obj_idx, softs)| {
+                    softs
+                        .iter()
+                        .map(|(cl, _)| origins.get(Some(obj_idx), cl))
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+
+    /// Gets a [`Reconstructor`] for the current reconstruction trace. The
+    /// reconstructor does not reflect later calls to
+    /// [`PreproClauses::preprocess`].
+    pub fn reconstructor(&self) -> Reconstructor {
+        let n_solver_vars = unsafe { ffi::cmaxpre_ext_get_n_solver_vars(self.handle) };
+        let internal = (1..=n_solver_vars as c_int)
+            .map(|var| {
+                let lit = unsafe { ffi::cmaxpre_ext_internal_lit(self.handle, var) };
+                Lit::from_ipasir(lit).ok()
+            })
+            .collect();
+        let n_internal_vars = unsafe { ffi::cmaxpre_ext_get_n_internal_vars(self.handle) };
+        let n_orig_vars = unsafe { ffi::cmaxpre_ext_get_n_orig_vars(self.handle) };
+        Reconstructor::new(
+            internal,
+            n_internal_vars as usize,
+            n_orig_vars as usize,
+            self.trace(),
+        )
+    }
+
+    /// Reconstructs multiple assignments in parallel, see
+    /// [`Reconstructor::par_reconstruct`]
+    #[cfg(feature = "rayon")]
+    pub fn par_reconstruct(&self, sols: &[Assignment]) -> Vec<Assignment> {
+        self.reconstructor().par_reconstruct(sols)
+    }
+
+    /// Releases the preprocessor and returns its final statistics, e.g., for
+    /// logging a summary of preprocessing when the preprocessor is torn down
+    pub fn close(mut self) -> Stats {
+        let stats = self.stats();
+        self.release();
+        stats
+    }
+
+    /// Releases the preprocessor and returns its final statistics and a
+    /// [`Reconstructor`] for reconstructing solutions after the preprocessor
+    /// is released
+    pub fn close_with_reconstructor(mut self) -> (Stats, Reconstructor) {
+        let stats = self.stats();
+        let reconstructor = self.reconstructor();
+        self.release();
+        (stats, reconstructor)
+    }
+
+    /// Releases the handle, after which dropping the preprocessor does nothing
+    fn release(&mut self) {
+        let handle = std::mem::replace(&mut self.handle, std::ptr::null_mut());
+        if !handle.is_null() {
+            unsafe { ffi::cmaxpre_release(handle) }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use rustsat::{instances::Cnf, lit, types::Clause};
+/// Builder for [`MaxPre`] that collects the instance and all configuration
+/// before the C preprocessor is created
+#[derive(Default)]
+pub struct MaxPreBuilder {
+    inprocessing: bool,
+    hards: Vec<Clause>,
+    softs: Vec<(Vec<(Clause, u64)>, isize)>,
+    opts: Options,
+    named_opts: Vec<(String, OptionValue)>,
+    quiet: bool,
+    forbid_aux_vars: bool,
+    preservation: Preservation,
+    keep_original: bool,
+}
 
-    use crate::PreproClauses;
+impl MaxPreBuilder {
+    /// Creates a builder for an empty instance without objectives
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    use super::MaxPre;
+    /// Sets whether to create the preprocessor in inprocessing mode
+    #[must_use]
+    pub fn inprocessing(mut self, inprocessing: bool) -> Self {
+        self.inprocessing = inprocessing;
+        self
+    }
 
-    #[test]
-    fn construct() {
-        let mut cnf = Cnf::new();
-        cnf.add_binary(lit![0], lit![2]);
-        MaxPre::new::<Vec<(Clause, usize)>>(cnf, vec![], true);
+    /// Sets whether all output of MaxPre is suppressed, see
+    /// [`MaxPre::set_quiet`]
+    #[must_use]
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Sets whether techniques that introduce auxiliary variables are left
+    /// out, see [`MaxPre::forbid_aux_vars`]
+    #[must_use]
+    pub fn forbid_aux_vars(mut self, forbid: bool) -> Self {
+        self.forbid_aux_vars = forbid;
+        self
+    }
+
+    /// Sets what preprocessing preserves, see [`MaxPre::set_preservation`]
+    #[must_use]
+    pub fn preservation(mut self, preservation: Preservation) -> Self {
+        self.preservation = preservation;
+        self
+    }
+
+    /// Sets whether a copy of the original hard clauses is kept. This is
+    /// needed for [resetting](PreproClauses::reset),
+    /// [reordering the objectives](PreproClauses::set_objective_order),
+    /// [scalarizing](PreproInit::scalarize) and
+    /// [tracking provenance](MaxPre::track_provenance), and for checking that
+    /// reconstructed models satisfy the original hard clauses. The copy keeps
+    /// the instance in memory twice, once on the Rust side and once in MaxPre.
+    #[must_use]
+    pub fn keep_original(mut self, keep: bool) -> Self {
+        self.keep_original = keep;
+        self
+    }
+
+    /// Reserves memory for `n` more hard clauses
+    #[must_use]
+    pub fn reserve_clauses(mut self, n: usize) -> Self {
+        self.hards.reserve(n);
+        self
+    }
+
+    /// Sets options. Options that are set in multiple calls are overwritten.
+    #[must_use]
+    pub fn option(mut self, opts: Options) -> Self {
+        self.opts.update(&opts.resolved());
+        self
+    }
+
+    /// Sets an option by name, see [`MaxPre::set_option_by_name`]
+    #[must_use]
+    pub fn named_option(mut self, name: &str, value: OptionValue) -> Self {
+        self.named_opts.push((String::from(name), value));
+        self
+    }
+
+    /// Adds hard clauses
+    #[must_use]
+    pub fn hards<H: IntoIterator<Item = Clause>>(mut self, hards: H) -> Self {
+        self.hards.extend(hards);
+        self
+    }
+
+    /// Adds an objective given by weighted soft clauses
+    #[must_use]
+    pub fn objective<CI: IntoIterator<Item = (Clause, u64)>>(self, softs: CI) -> Self {
+        self.objective_with_offset(softs, 0)
+    }
+
+    /// Adds an objective given by weighted soft clauses and an offset
+    #[must_use]
+    pub fn objective_with_offset<CI: IntoIterator<Item = (Clause, u64)>>(
+        mut self,
+        softs: CI,
+        offset: isize,
+    ) -> Self {
+        self.softs.push((softs.into_iter().collect(), offset));
+        self
+    }
+
+    /// Creates the preprocessor. The instance is validated the same way as
+    /// by [`MaxPre::try_new`] and soft clauses with weight zero are dropped.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidInput`] if a soft clause is empty or a tautology, or
+    ///   if a variable exceeds [`Capabilities::max_var_idx`]
+    /// - [`Error::WeightOverflow`] if a weight does not fit in [`usize`] or the
+    ///   total weight exceeds [`Capabilities::max_total_weight`]
+    /// - [`Error::Generic`] if a named option does not exist
+    pub fn build(self) -> Result<MaxPre, Error> {
+        let (hards, mut softs) = crate::try_collect_instance(self.hards, self.softs)?;
+        MaxPre::validate(&hards, &mut softs)?;
+        let mut maxpre = MaxPre::from_instance(hards, softs, self.inprocessing, self.keep_original);
+        maxpre.set_options(self.opts);
+        maxpre.set_quiet(self.quiet);
+        maxpre.forbid_aux_vars(self.forbid_aux_vars);
+        maxpre.set_preservation(self.preservation);
+        for (name, value) in &self.named_opts {
+            maxpre.set_option_by_name(name, *value)?;
+        }
+        Ok(maxpre)
+    }
+}
+
+#[cfg(feature = "proofs")]
+impl MaxPre {
+    /// Logs a proof of preprocessing to a file. This must be called before
+    /// the first call to [`PreproClauses::preprocess`] and the proof is
+    /// concluded when that call returns.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, the instance has multiple objectives,
+    /// or preprocessing has already started
+    pub fn set_proof_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+        format: crate::ProofFormat,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        // MaxPre does not report if the file cannot be opened. Do not truncate
+        // here, since MaxPre might reject logging after the check.
+        if std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .is_err()
+        {
+            return Err(Error::Generic);
+        }
+        let Some(path) = path.to_str().and_then(|p| CString::new(p).ok()) else {
+            return Err(Error::Generic);
+        };
+        let output_file = ffi::map_bool(format == crate::ProofFormat::VeriPbWithOutput);
+        if unsafe { ffi::cmaxpre_ext_log_proof(self.handle, path.as_ptr(), 0, output_file) }
+            == ffi::FALSE
+        {
+            return Err(Error::Generic);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "raw-ffi")]
+impl MaxPre {
+    /// Consumes the preprocessor and returns the raw handle for the C API.
+    /// The caller becomes responsible for releasing the handle with
+    /// [`ffi::cmaxpre_release`] or by passing it back to
+    /// [`MaxPre::from_raw`].
+    pub fn into_raw(mut self) -> *mut ffi::CMaxPre {
+        std::mem::replace(&mut self.handle, std::ptr::null_mut())
+    }
+
+    /// Takes ownership of a raw handle for the C API.
+    ///
+    /// Since the original instance is not known, a preprocessor created this
+    /// way cannot be [reset](PreproClauses::reset) and does not have
+    /// statistics of the original instance.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be a handle obtained from [`MaxPre::into_raw`] or an
+    /// initialized (i.e., [`ffi::cmaxpre_init_finalize`] has been called)
+    /// handle created through the C API that is not owned by anything else.
+    /// `n_objs` must be the number of objectives of the instance that the
+    /// handle was initialized with.
+    pub unsafe fn from_raw(handle: *mut ffi::CMaxPre, n_objs: usize) -> Self {
+        Self {
+            handle,
+            offsets: vec![0; n_objs],
+            stats: Stats {
+                n_objs,
+                ..Default::default()
+            },
+            orig: None,
+            opts: Options::default(),
+            named_opts: vec![],
+            label_map: RsHashMap::default(),
+            reported_fixed: RsHashSet::default(),
+            obj_order: (0..n_objs).collect(),
+            state: if unsafe { ffi::cmaxpre_ext_is_preprocessed(handle) } == ffi::TRUE {
+                PreproState::Preprocessed
+            } else {
+                PreproState::Loaded
+            },
+            redundant: vec![],
+            assumption_vars: RsHashSet::default(),
+            runs: RsHashMap::default(),
+            quiet: false,
+            forbid_aux_vars: false,
+            preservation: Preservation::default(),
+            origins: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for MaxPre {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaxPre")
+            .field("inprocessing", &unsafe {
+                ffi::cmaxpre_ext_is_inprocessing(self.handle) == ffi::TRUE
+            })
+            .field("preprocessed", &unsafe {
+                ffi::cmaxpre_ext_is_preprocessed(self.handle) == ffi::TRUE
+            })
+            .field("n_objs", &self.stats.n_objs)
+            .field("n_orig_hard_clauses", &self.stats.n_orig_hard_clauses)
+            .field("n_orig_soft_clauses", &self.stats.n_orig_soft_clauses)
+            .field("max_orig_var", &self.max_orig_var())
+            .field("n_prepro_clauses", &self.n_prepro_clauses())
+            .field("n_prepro_labels", &self.n_prepro_labels())
+            .field("n_prepro_fixed_lits", &self.n_prepro_fixed_lits())
+            .field("max_prepro_var", &self.max_prepro_var())
+            .field("options", &self.options())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Adds hard clauses, see [`PreproClauses::add_clause`]
+///
+/// # Panics
+///
+/// If the preprocessor is not in inprocessing mode
+impl Extend<Clause> for MaxPre {
+    fn extend<T: IntoIterator<Item = Clause>>(&mut self, iter: T) {
+        for clause in iter {
+            if self.add_clause(clause).is_err() {
+                panic!("adding clauses requires inprocessing mode");
+            }
+        }
+    }
+}
+
+/// Adds soft clauses to the first objective, each relaxed with a new label as
+/// by [`PreproClauses::add_group`]. A weight of at least the
+/// [top weight](PreproClauses::top_weight) makes the clause hard.
+///
+/// # Panics
+///
+/// If the preprocessor is not in inprocessing mode or has no objective
+impl Extend<(Clause, u64)> for MaxPre {
+    fn extend<T: IntoIterator<Item = (Clause, u64)>>(&mut self, iter: T) {
+        for (clause, weight) in iter {
+            let weight = usize::try_from(weight).expect("weight does not fit in usize");
+            if self.add_group(0, [clause], weight).is_err() {
+                panic!("adding soft clauses requires inprocessing mode and an objective");
+            }
+        }
+    }
+}
+
+/// Creates a preprocessor for a satisfiability instance, not in inprocessing
+/// mode
+impl FromIterator<Clause> for MaxPre {
+    fn from_iter<T: IntoIterator<Item = Clause>>(iter: T) -> Self {
+        MaxPre::new::<_, _, Vec<(Clause, u64)>>(iter, vec![], false)
+    }
+}
+
+/// Creates a preprocessor for a single objective given by soft clauses,
+/// without hard clauses and not in inprocessing mode
+impl FromIterator<(Clause, u64)> for MaxPre {
+    fn from_iter<T: IntoIterator<Item = (Clause, u64)>>(iter: T) -> Self {
+        MaxPre::new(Cnf::new(), [(iter, 0)], false)
+    }
+}
+
+// The C++ preprocessor behind the handle is exclusively owned by the `MaxPre`
+// object and is not tied to the thread that created it
+unsafe impl Send for MaxPre {}
+
+impl Drop for MaxPre {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::Cnf,
+        lit,
+        types::{Assignment, Clause, Lit, TernaryVal},
+        var,
+    };
+
+    use crate::{
+        testing::InstanceConfig, AutoPlan, ClauseOrigin, InputError, LabelStats, PreproClauses,
+        PreproInit, PreproStatus, Preservation, Stratified, Technique, TechniqueBudget, Techniques,
+        TraceOp, VarSpace,
+    };
+
+    use super::{c_uint, MaxPre, MaxPreBuilder};
+
+    #[test]
+    fn construct() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![2]);
+        MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf, vec![], true);
+    }
+
+    #[test]
+    fn orig_stats() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![3]], 1)];
+        let maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        assert_eq!(maxpre.n_orig_clauses(), 3);
+        assert_eq!(maxpre.n_orig_soft_clauses(0), 2);
+        assert_eq!(maxpre.n_orig_vars(), 4);
+        assert_eq!(maxpre.orig_weight(0), 3);
+    }
+
+    #[test]
+    fn prepro_vars() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![2]);
+        cnf.add_unit(lit![1]);
+        let mut maxpre = MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf, vec![], true);
+        assert_eq!(maxpre.n_prepro_vars(), 0);
+        assert_eq!(maxpre.max_prepro_var(), None);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (hards, _) = maxpre.prepro_instance();
+        let max_var = hards.iter().flat_map(|cl| cl.iter()).map(|l| l.var()).max();
+        assert_eq!(maxpre.max_prepro_var(), max_var);
+        assert_eq!(maxpre.n_prepro_vars(), 2);
+    }
+
+    #[test]
+    fn best_model() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 1),
+            (clause![!lit![2]], 1),
+            (clause![!lit![3]], 3),
+        ];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.set_quiet(true);
+        assert!(maxpre.best_model().is_none());
+        maxpre.preprocess("[H]", 0, 0.1);
+        let (model, costs) = maxpre.best_model().unwrap();
+        assert_eq!(costs, vec![maxpre.upper_bound()]);
+        assert_eq!(model.max_var(), Some(var![3]));
+    }
+
+    #[test]
+    fn phase_hints() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 1),
+            (clause![!lit![2]], 1),
+            (clause![!lit![3]], 3),
+        ];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre.phase_hints().is_empty());
+        let model: Assignment = [lit![0], !lit![1], lit![2], !lit![3]].into_iter().collect();
+        assert!(maxpre.set_initial_model(model.clone()).is_ok());
+        let hints = maxpre.phase_hints();
+        assert_eq!(hints.len(), maxpre.n_prepro_vars());
+        assert_eq!(maxpre.reconstruct(hints.into_iter().collect()), model);
+    }
+
+    fn random_instance(seed: u64) -> (Cnf, Vec<(crate::testing::Softs, isize)>) {
+        crate::testing::random_instance(&crate::testing::InstanceConfig::DEFAULT, seed)
+    }
+
+    /// A quiet preprocessor, for tests running SatLike local search, which
+    /// prints regardless of the log level
+    fn quiet_maxpre(
+        cnf: Cnf,
+        softs: Vec<(crate::testing::Softs, isize)>,
+        inprocessing: bool,
+    ) -> MaxPre {
+        let mut builder = MaxPreBuilder::new()
+            .inprocessing(inprocessing)
+            .quiet(true)
+            .hards(cnf);
+        for (obj, offset) in softs {
+            builder = builder.objective_with_offset(obj, offset);
+        }
+        builder
+            .build()
+            .unwrap_or_else(|_| panic!("instance is valid"))
+    }
+
+    /// A preprocessor that keeps the original instance, for tests that reset
+    /// the preprocessor
+    fn keeping_maxpre(
+        cnf: Cnf,
+        softs: Vec<(crate::testing::Softs, isize)>,
+        inprocessing: bool,
+    ) -> MaxPre {
+        let mut builder = MaxPreBuilder::new()
+            .inprocessing(inprocessing)
+            .keep_original(true)
+            .hards(cnf);
+        for (obj, offset) in softs {
+            builder = builder.objective_with_offset(obj, offset);
+        }
+        builder
+            .build()
+            .unwrap_or_else(|_| panic!("instance is valid"))
+    }
+
+    #[test]
+    fn concurrent() {
+        fn assert_send<T: Send>() {}
+        assert_send::<MaxPre>();
+
+        let preprocess = |seed: u64, techniques: &str| {
+            let (cnf, softs) = random_instance(seed);
+            let mut maxpre = MaxPre::new(cnf, softs, false);
+            maxpre.preprocess(techniques, 0, 1e9);
+            let (hards, objs) = maxpre.prepro_instance();
+            (hards.into_iter().collect::<Vec<_>>(), objs)
+        };
+        let expected: Vec<_> = (0..8)
+            .map(|seed| preprocess(seed, "[bu]#[buvsrgc]"))
+            .collect();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|seed| scope.spawn(move || preprocess(seed, "[bu]#[buvsrgc]")))
+                .collect();
+            // Local search randomizes its results, so only run it alongside
+            let local_search: Vec<_> = (0..4)
+                .map(|seed| {
+                    scope.spawn(move || {
+                        let (cnf, softs) = random_instance(seed);
+                        let mut maxpre = MaxPre::new(cnf, softs, false);
+                        maxpre.set_quiet(true);
+                        maxpre.preprocess("[H]", 0, 0.1);
+                    })
+                })
+                .collect();
+            for (handle, expected) in handles.into_iter().zip(&expected) {
+                assert_eq!(&handle.join().unwrap(), expected);
+            }
+            local_search
+                .into_iter()
+                .for_each(|handle| handle.join().unwrap());
+        });
+    }
+
+    #[test]
+    fn local_search() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 1),
+            (clause![!lit![2]], 1),
+            (clause![!lit![3]], 3),
+        ];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs, 0)], false);
+        maxpre.set_quiet(true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let model = maxpre.local_search(0.1).unwrap();
+        assert!(cnf.iter().all(|cl| cl.evaluate(&model) == TernaryVal::True));
+        assert!(maxpre.upper_bound() <= 7);
+    }
+
+    /// Preprocesses and runs local search when started as a child process by
+    /// [`quiet`], the environment variable says whether to be quiet or
+    /// whether to run on a thread next to a quiet one
+    #[test]
+    fn quiet_child() {
+        let Some(quiet) = std::env::var_os("MAXPRE_QUIET_CHILD") else {
+            return;
+        };
+        let run = |quiet: bool| {
+            let mut cnf = Cnf::new();
+            cnf.add_binary(lit![0], lit![1]);
+            let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+            let mut maxpre = MaxPreBuilder::new()
+                .hards(cnf)
+                .objective(softs)
+                .quiet(quiet)
+                .build()
+                .unwrap_or_else(|_| panic!());
+            maxpre.preprocess("[u]", 0, 1e9);
+            assert!(maxpre.local_search(0.1).is_some());
+        };
+        if quiet == "thread" {
+            unsafe { crate::ffi::cmaxpre_ext_quiet_begin() };
+            std::thread::spawn(move || run(false)).join().unwrap();
+            unsafe { crate::ffi::cmaxpre_ext_quiet_end() };
+        } else {
+            run(quiet == "1");
+        }
+    }
+
+    #[test]
+    fn quiet() {
+        let output = |quiet: &str| {
+            let out = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "base::tests::quiet_child", "--nocapture"])
+                .env("MAXPRE_QUIET_CHILD", quiet)
+                .output()
+                .unwrap();
+            assert!(out.status.success());
+            String::from_utf8(out.stdout).unwrap() + &String::from_utf8(out.stderr).unwrap()
+        };
+        // When quiet, only the output of the test harness remains
+        let is_maxpre = |line: &str| line.starts_with("c ");
+        assert!(output("0").lines().any(is_maxpre));
+        assert!(!output("1").lines().any(is_maxpre));
+        // Only the thread that is quiet is silenced
+        assert!(output("thread").lines().any(is_maxpre));
+    }
+
+    #[test]
+    fn initial_model() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut maxpre = keeping_maxpre(cnf, vec![(softs, 0)], false);
+        let infeasible: Assignment = [!lit![0], !lit![1]].into_iter().collect();
+        assert!(maxpre.set_initial_model(infeasible).is_err());
+        let model: Assignment = [lit![0], !lit![1]].into_iter().collect();
+        assert!(maxpre.set_initial_model(model.clone()).is_ok());
+        assert_eq!(maxpre.upper_bound(), 2);
+        assert_eq!(maxpre.best_model(), Some((model, vec![2])));
+    }
+
+    #[test]
+    fn initial_model_labels() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![0], !lit![1]);
+        cnf.add_binary(!lit![2], !lit![3]);
+        let softs = vec![
+            (clause![lit![2], lit![3]], 3),
+            (clause![lit![0], lit![3]], 1),
+        ];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        // The first soft clause is falsified, the second satisfied
+        let model: Assignment = [lit![0], !lit![1], !lit![2], !lit![3]]
+            .into_iter()
+            .collect();
+        assert!(maxpre.set_initial_model(model.clone()).is_ok());
+        assert_eq!(maxpre.upper_bound(), 3);
+        // The labels are set so that the model has the same cost in the
+        // preprocessed instance
+        let hints: Assignment = maxpre.phase_hints().into_iter().collect();
+        let (hards, _) = maxpre.prepro_instance();
+        assert!(hards
+            .iter()
+            .all(|cl| cl.evaluate(&hints) == TernaryVal::True));
+        assert_eq!(
+            maxpre.eval_objectives(&hints, crate::VarSpace::Preprocessed),
+            vec![3]
+        );
+        assert_eq!(maxpre.reconstruct(hints), model);
+    }
+
+    #[test]
+    fn prepro_instance_reindexed() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![10]);
+        cnf.add_binary(!lit![10], lit![20]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![20]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (hards, objs, reindexer) = maxpre.prepro_instance_reindexed();
+        assert!(hards.iter().any(|cl| *cl == clause![lit![0], lit![1]]));
+        assert!(hards.iter().any(|cl| *cl == clause![!lit![1], lit![2]]));
+        assert!(objs[0].0.contains(&(clause![!lit![0]], 2)));
+        assert!(objs[0].0.contains(&(clause![!lit![2]], 1)));
+        let sol: Assignment = [!lit![0], lit![1], lit![2]].into_iter().collect();
+        let rec = maxpre.reconstruct_reindexed(sol, &reindexer);
+        assert_eq!(rec.lit_value(lit![0]), TernaryVal::False);
+        assert_eq!(rec.lit_value(lit![10]), TernaryVal::True);
+        assert_eq!(rec.lit_value(lit![20]), TernaryVal::True);
+    }
+
+    #[test]
+    fn add_vars() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(vec![(clause![lit![2]], 1)], 0)], false);
+        assert!(maxpre.add_vars(2).is_err());
+        let mut maxpre = MaxPre::new(cnf, vec![(vec![(clause![lit![2]], 1)], 0)], true);
+        assert!(maxpre.reserve_vars(4).is_ok());
+        let Ok(vars) = maxpre.add_vars(3) else {
+            panic!("failed to add variables");
+        };
+        assert_eq!(vars, var![3]..var![6]);
+        let Ok(var) = maxpre.add_var() else {
+            panic!("failed to add variable");
+        };
+        assert_eq!(var, var![6]);
+        let Ok(vars) = maxpre.add_vars(0) else {
+            panic!("failed to add variables");
+        };
+        assert!(vars.is_empty());
+        if usize::BITS > c_uint::BITS {
+            let too_many = c_uint::MAX as usize + 1;
+            assert!(matches!(
+                maxpre.add_vars(too_many),
+                Err(crate::Error::InvalidInput(InputError::TooManyVars(n))) if n == too_many
+            ));
+            assert!(maxpre.reserve_vars(too_many).is_err());
+        }
+    }
+
+    #[test]
+    fn new_from_iterators() {
+        let hards = [clause![lit![0], lit![1]]];
+        let softs = (0..2).map(|v| (clause![!rustsat::types::Var::new(v).pos_lit()], 1u64 << 40));
+        let maxpre = MaxPre::new(hards, [(softs, 0)], false);
+        assert_eq!(maxpre.n_orig_clauses(), 3);
+        assert_eq!(maxpre.orig_weight(0), 1 << 41);
+    }
+
+    #[test]
+    fn skip_technique() {
+        let (cnf, softs) = random_instance(3);
+        let mut maxpre = MaxPre::new(cnf.clone(), softs.clone(), false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let expected = maxpre.prepro_instance();
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.set_options(crate::Options {
+            skip_technique: Some(crate::SkipTechnique::Never),
+            ..Default::default()
+        });
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let (hards, objs) = maxpre.prepro_instance();
+        assert!(hards.iter().eq(expected.0.iter()));
+        assert_eq!(objs, expected.1);
+        assert_eq!(crate::SkipTechnique::Sample(8).c_value(), 8);
+        assert_eq!(
+            crate::SkipTechnique::Sample(u32::MAX).c_value(),
+            core::ffi::c_int::MAX
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn option_groups() {
+        let opts = crate::Options::default()
+            .bve(crate::BveOptions::default().gate_extraction(false))
+            .model_search(crate::ModelSearchOptions::default().iter_limit(5));
+        assert_eq!(opts.bve.gate_extraction, Some(false));
+        assert_eq!(opts.model_search.iter_limit, Some(5));
+        let flat = crate::Options {
+            bve_gate_extraction: Some(false),
+            model_search_iter_limits: Some(5),
+            max_bbtms_vars: Some(10),
+            ..Default::default()
+        }
+        .resolved();
+        assert_eq!(flat.bve, opts.bve);
+        assert_eq!(flat.model_search, opts.model_search);
+        assert_eq!(flat.bbtms.max_vars, Some(10));
+        assert_eq!(flat.max_bbtms_vars, None);
+        let (cnf, softs) = random_instance(4);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.set_options(flat);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+    }
+
+    #[test]
+    fn options() {
+        let (cnf, softs) = random_instance(5);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        let opts = maxpre.options();
+        assert_eq!(opts.bve.gate_extraction, Some(false));
+        assert_eq!(opts.bbtms.max_vars, Some(200));
+        assert_eq!(opts.model_search.iter_limit, Some(-1));
+        assert_eq!(opts.skip_technique, Some(crate::SkipTechnique::Never));
+        maxpre.set_options(
+            crate::Options::default()
+                .bve(crate::BveOptions::default().gate_extraction(true))
+                .skip_technique(crate::SkipTechnique::Sample(3)),
+        );
+        let opts = maxpre.options();
+        assert_eq!(opts.bve.gate_extraction, Some(true));
+        assert_eq!(opts.skip_technique, Some(crate::SkipTechnique::Sample(3)));
+        assert_eq!(opts.bbtms.max_vars, Some(200));
+    }
+
+    #[test]
+    fn set_option_by_name() {
+        let (cnf, softs) = random_instance(6);
+        let mut maxpre = keeping_maxpre(cnf, softs, false);
+        assert!(maxpre
+            .set_option_by_name("BVE_sizelimit", crate::OptionValue::Int(2))
+            .is_ok());
+        assert!(maxpre
+            .set_option_by_name("BBTMS_maxVars", 10.into())
+            .is_ok());
+        assert_eq!(maxpre.options().bbtms.max_vars, Some(10));
+        assert!(maxpre
+            .set_option_by_name("BBTMS_maxVars", true.into())
+            .is_err());
+        assert!(maxpre.set_option_by_name("notAnOption", 1.into()).is_err());
+        assert!(maxpre.reset().is_ok());
+        assert_eq!(maxpre.options().bbtms.max_vars, Some(10));
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+    }
+
+    #[test]
+    fn supported_techniques() {
+        let techniques = MaxPre::supported_techniques();
+        assert_eq!(techniques.len(), 27);
+        for tech in crate::Technique::ALL {
+            assert!(techniques
+                .iter()
+                .any(|info| info.technique == tech && info.character == tech.char()));
+        }
+    }
+
+    #[test]
+    fn version() {
+        let version = crate::Version::parse(
+            "MaxPRE 2.0.3 beta (maxpre-rs build, vendored@0a1b2c3d, Oct 14 2026 09:39:41)",
+        );
+        assert_eq!((version.major, version.minor, version.patch), (2, 0, 3));
+        assert_eq!(version.git_commit.as_deref(), Some("0a1b2c3d"));
+        assert_eq!(version.build_date.as_deref(), Some("Oct 14 2026 09:39:41"));
+        let version = crate::Version::parse("MaxPRE 2.1 (vendored, Oct 14 2026 09:39:41)");
+        assert_eq!((version.major, version.minor, version.patch), (2, 1, 0));
+        assert_eq!(version.git_commit, None);
+        assert_eq!(MaxPre::version().major, 2);
+        if let Some(commit) = MaxPre::commit() {
+            assert_eq!(MaxPre::version().git_commit.as_deref(), Some(commit));
+        }
+    }
+
+    #[test]
+    fn technique_presets() {
+        let (cnf, softs) = random_instance(7);
+        let mut maxpre = MaxPre::new(cnf.clone(), softs.clone(), false);
+        maxpre.preprocess_default();
+        let expected = maxpre.prepro_instance();
+        let mut maxpre = MaxPre::new(cnf.clone(), softs.clone(), false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        assert_eq!(maxpre.prepro_instance(), expected);
+        for techniques in [
+            crate::Techniques::WEIGHTED,
+            crate::Techniques::UNWEIGHTED,
+            crate::Techniques::LIGHT,
+            crate::Techniques::AGGRESSIVE,
+        ] {
+            assert!(techniques.chars().filter(|c| c.is_alphabetic()).all(|c| {
+                MaxPre::supported_techniques()
+                    .iter()
+                    .any(|info| info.character == c)
+            }));
+            let mut maxpre = quiet_maxpre(cnf.clone(), softs.clone(), false);
+            maxpre.preprocess(&techniques, 0, 1e9);
+        }
+    }
+
+    #[test]
+    fn technique_budgets() {
+        use core::time::Duration;
+
+        use crate::{TechniqueBudget, Techniques};
+
+        assert_eq!(
+            Techniques::LIGHT
+                .retain(|t| t != Technique::Bce)
+                .unwrap()
+                .as_str(),
+            "[u]#[us]"
+        );
+        assert!(Techniques::LIGHT.retain(|t| t == Technique::Bve).is_none());
+        let techniques = Techniques::new("[uvs]")
+            .budget(
+                Technique::Bve,
+                TechniqueBudget::default().max_applications(1),
+            )
+            .budget(
+                Technique::Se,
+                TechniqueBudget::default().max_time(Duration::ZERO),
+            );
+        assert_eq!(
+            techniques.budget_of(Technique::Bve).max_applications,
+            Some(1)
+        );
+        assert_eq!(
+            techniques.budget_of(Technique::Up),
+            TechniqueBudget::default()
+        );
+        let (cnf, softs) = random_instance(3);
+        let mut maxpre = keeping_maxpre(cnf, softs, true);
+        for _ in 0..3 {
+            maxpre.preprocess_budgeted(&techniques, 0, 1e9);
+        }
+        assert_eq!(maxpre.technique_runs(Technique::Up), 3);
+        assert_eq!(maxpre.technique_runs(Technique::Bve), 1);
+        assert_eq!(maxpre.technique_runs(Technique::Se), 0);
+        assert!(maxpre.reset().is_ok());
+        assert_eq!(maxpre.technique_runs(Technique::Up), 0);
+    }
+
+    #[test]
+    fn options_from_str() {
+        let opts = crate::Options::default()
+            .bve(
+                crate::BveOptions::default()
+                    .gate_extraction(true)
+                    .local_grow_limit(5),
+            )
+            .bbtms(crate::BbtmsOptions::default().max_vars(10))
+            .skip_technique(crate::SkipTechnique::Sample(20));
+        let flags = opts.to_string();
+        assert_eq!(
+            flags,
+            "-bvegate=1 -skiptechnique=20 -bvelocalgrow=5 -BBTMS_maxVars=10"
+        );
+        assert_eq!(flags.parse::<crate::Options>(), Ok(opts));
+        assert_eq!("".parse::<crate::Options>(), Ok(crate::Options::default()));
+        assert!("-bvegate=2".parse::<crate::Options>().is_err());
+        assert!("-unknown=1".parse::<crate::Options>().is_err());
+        let (cnf, softs) = random_instance(8);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.set_options(flags.parse().unwrap());
+        assert_eq!(maxpre.options().bve.local_grow_limit, Some(5));
+    }
+
+    #[test]
+    fn techniques_from_str() {
+        let techniques: crate::Techniques = "[bu]#[buvsrgc]".parse().unwrap();
+        assert_eq!(techniques, crate::Techniques::DEFAULT);
+        assert_eq!(
+            crate::Techniques::AGGRESSIVE
+                .to_string()
+                .parse::<crate::Techniques>(),
+            Ok(crate::Techniques::AGGRESSIVE)
+        );
+        assert!("[bu".parse::<crate::Techniques>().is_err());
+        assert!("bu]".parse::<crate::Techniques>().is_err());
+        assert!("[b#u]".parse::<crate::Techniques>().is_err());
+        assert!("bx".parse::<crate::Techniques>().is_err());
+    }
+
+    #[test]
+    fn builder() {
+        let (cnf, softs) = random_instance(9);
+        let mut expected = MaxPre::new(cnf.clone(), softs.clone(), false);
+        expected
+            .set_options(crate::Options::default().skip_technique(crate::SkipTechnique::Sample(5)));
+        expected.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let mut builder = MaxPreBuilder::new()
+            .reserve_clauses(cnf.len())
+            .hards(cnf)
+            .option(crate::Options::default().skip_technique(crate::SkipTechnique::Sample(5)))
+            .named_option("BBTMS_maxVars", 10.into());
+        for (obj, offset) in softs {
+            builder = builder.objective_with_offset(obj, offset);
+        }
+        let Ok(mut maxpre) = builder.build() else {
+            panic!()
+        };
+        assert_eq!(maxpre.options().bbtms.max_vars, Some(10));
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        assert_eq!(maxpre.prepro_instance(), expected.prepro_instance());
+        assert!(matches!(
+            MaxPreBuilder::new()
+                .objective([(clause![lit![0]], u64::MAX)])
+                .build(),
+            Err(crate::Error::WeightOverflow)
+        ));
+        assert!(matches!(
+            MaxPreBuilder::new()
+                .objective([(clause![lit![0]], 1), (clause![], 1)])
+                .build(),
+            Err(crate::Error::InvalidInput(InputError::EmptySoftClause {
+                obj_idx: 0,
+                cl_idx: 1
+            }))
+        ));
+        assert!(MaxPreBuilder::new()
+            .named_option("notAnOption", 1.into())
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn extend() {
+        let mut maxpre = MaxPreBuilder::new()
+            .inprocessing(true)
+            .objective([(clause![lit![0]], 10)])
+            .build()
+            .unwrap_or_else(|_| panic!());
+        maxpre.extend([clause![lit![3], lit![4]], clause![!lit![4], lit![2]]]);
+        maxpre.extend([(clause![lit![2]], 4)]);
+        maxpre.preprocess("s", 0, 1e9);
+        let (hards, softs) = maxpre.prepro_instance();
+        assert!(hards.len() >= 2);
+        assert!(softs[0].0.iter().any(|(_, w)| *w == 4));
+        let maxpre: MaxPre = [clause![lit![0], lit![1]]].into_iter().collect();
+        assert_eq!(maxpre.stats().n_orig_hard_clauses, 1);
+        let maxpre: MaxPre = [(clause![lit![0]], 2), (clause![lit![1]], 3)]
+            .into_iter()
+            .collect();
+        assert_eq!(maxpre.stats().orig_weight, vec![5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "inprocessing mode")]
+    fn extend_without_inprocessing() {
+        let mut maxpre: MaxPre = [clause![lit![0], lit![1]]].into_iter().collect();
+        maxpre.extend([clause![lit![2]]]);
+    }
+
+    #[test]
+    fn debug() {
+        let (cnf, softs) = random_instance(10);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        let debug = format!("{maxpre:?}");
+        assert!(debug.contains("preprocessed: false"));
+        assert!(debug.contains("n_prepro_clauses: 0"));
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let debug = format!("{maxpre:?}");
+        assert!(debug.contains("preprocessed: true"));
+        assert!(debug.contains(&format!("n_prepro_clauses: {}", maxpre.n_prepro_clauses())));
+        assert!(debug.contains("skip_technique: Some(Never)"));
+    }
+
+    #[test]
+    fn add_group() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let mut maxpre = MaxPre::new(cnf, vec![(vec![(clause![lit![2]], 5)], 0)], true);
+        assert!(maxpre
+            .add_group(1, [clause![!lit![0]], clause![!lit![1]]], 3)
+            .is_err());
+        let Ok(group) = maxpre.add_group(0, [clause![!lit![0]], clause![!lit![1]]], 3) else {
+            panic!("failed to add group");
+        };
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre.prepro_labels().contains(&!group.label()));
+        let (_, objs) = maxpre.prepro_instance();
+        assert!(objs[0].0.contains(&(clause![!group.label()], 3)));
+    }
+
+    #[test]
+    fn alter_weights() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre.alter_weights([(lit![0], 1), (lit![5], 1)]).is_err());
+        let (_, objs) = maxpre.prepro_instance();
+        assert!(objs[0].0.contains(&(clause![!lit![0]], 2)));
+        assert!(maxpre.alter_weights([(lit![0], 1), (lit![1], 2)]).is_ok());
+        // The preprocessed instance is only updated by preprocessing
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (_, objs) = maxpre.prepro_instance();
+        assert!(objs[0].0.contains(&(clause![!lit![0]], 1)));
+        assert!(objs[0].0.contains(&(clause![!lit![1]], 2)));
+    }
+
+    #[cfg(feature = "optimization")]
+    #[test]
+    fn label_map() {
+        use rustsat::instances::OptInstance;
+
+        let mut inst: OptInstance = OptInstance::new();
+        inst.constraints_mut().add_binary(lit![0], lit![1]);
+        inst.objective_mut().add_soft_lit(1, lit![0]);
+        inst.objective_mut()
+            .add_soft_clause(2, clause![!lit![0], !lit![1]]);
+        let maxpre = <MaxPre as crate::PreproOpt>::new(inst, false);
+        let map = maxpre.label_map();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&!lit![0]], (0, clause![!lit![0]]));
+        let (lbl, _) = map.iter().find(|(l, _)| l.var() != var![0]).unwrap();
+        assert!(lbl.var() > var![1]);
+        assert_eq!(map[lbl], (0, clause![!lit![0], !lit![1]]));
+    }
+
+    #[test]
+    fn close() {
+        let (cnf, softs) = random_instance(3);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]", 0, 1e9);
+        let max_var = maxpre.max_prepro_var().map_or(0, |var| var.idx32());
+        let sol: rustsat::types::Assignment = (0..=max_var)
+            .map(|var| rustsat::types::Var::new(var).pos_lit())
+            .collect();
+        let rec = maxpre.reconstruct(sol.clone());
+        let stats = maxpre.stats();
+        let reconstructor = maxpre.reconstructor();
+        let (closed, closed_rec) = maxpre.close_with_reconstructor();
+        assert!(closed == stats);
+        assert_eq!(closed_rec, reconstructor);
+        assert_eq!(closed_rec.reconstruct(sol.iter()), rec);
+        let (cnf, softs) = random_instance(3);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]", 0, 1e9);
+        let closed = maxpre.close();
+        assert_eq!(closed.n_prepro_hard_clauses, stats.n_prepro_hard_clauses);
+        assert_eq!(closed.removed_weight, stats.removed_weight);
+    }
+
+    #[cfg(feature = "optimization")]
+    #[test]
+    fn prepro_instance_with_vm() {
+        use rustsat::instances::{ManageVars, OptInstance, SatInstance};
+
+        let mut inst: OptInstance = OptInstance::new();
+        inst.constraints_mut().add_binary(lit![0], lit![1]);
+        inst.objective_mut()
+            .add_soft_clause(2, clause![!lit![0], !lit![1]]);
+        let (constrs, obj) = inst.decompose();
+        let (cnf, mut vm) = constrs.into_cnf();
+        // Reserve variables that do not occur in the instance
+        vm.increase_next_free(var![10]);
+        let inst: OptInstance = OptInstance::compose(SatInstance::from(cnf), obj);
+        let mut maxpre = <MaxPre as crate::PreproOpt>::new(inst, false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let prepro = crate::PreproOpt::prepro_instance_with_vm(&mut maxpre, vm);
+        assert_eq!(
+            prepro.constraints_ref().var_manager_ref().max_var(),
+            Some(var![9])
+        );
+        let prepro = crate::PreproOpt::prepro_instance(&mut maxpre);
+        assert_eq!(
+            prepro.constraints_ref().var_manager_ref().max_var(),
+            Some(var![2])
+        );
+    }
+
+    #[test]
+    fn sat_prepro_instance_with_vm() {
+        use rustsat::instances::{BasicVarManager, ManageVars, SatInstance};
+
+        let mut inst: SatInstance = SatInstance::new();
+        inst.add_binary(lit![0], lit![4]);
+        inst.add_binary(!lit![0], lit![1]);
+        let mut maxpre = <MaxPre as crate::PreproSat>::new(inst, false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let mut vm = BasicVarManager::default();
+        vm.increase_next_free(var![10]);
+        let prepro = crate::PreproSat::prepro_instance_with_vm(&mut maxpre, vm);
+        assert_eq!(prepro.var_manager_ref().max_var(), Some(var![9]));
+        let prepro = crate::PreproSat::prepro_instance(&mut maxpre);
+        assert_eq!(prepro.var_manager_ref().max_var(), Some(var![4]));
+    }
+
+    #[cfg(feature = "multiopt")]
+    #[test]
+    fn verify() {
+        use rustsat::instances::MultiOptInstance;
+
+        use crate::{PreproMultiOpt, VerifyError};
+
+        let mut inst: MultiOptInstance = MultiOptInstance::new(2);
+        inst.constraints_mut().add_binary(lit![0], lit![1]);
+        inst.constraints_mut().add_binary(!lit![1], lit![2]);
+        inst.objective_mut(0).add_soft_lit(2, !lit![0]);
+        inst.objective_mut(0).add_soft_lit(1, !lit![2]);
+        inst.objective_mut(1).add_soft_lit(1, lit![0]);
+        let mut maxpre = <MaxPre as PreproMultiOpt>::new(inst.clone(), false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let sol: Assignment = [!lit![0], lit![1], lit![2]].into_iter().collect();
+        assert_eq!(maxpre.verify(&inst, &sol), Ok(vec![2, 0]));
+        let sol: Assignment = [!lit![0], !lit![1], lit![2]].into_iter().collect();
+        assert_eq!(maxpre.verify(&inst, &sol), Err(VerifyError::PreproUnsat));
+    }
+
+    #[test]
+    fn label_am1s() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![0], lit![2]);
+        cnf.add_binary(lit![1], lit![2]);
+        cnf.add_ternary(!lit![0], !lit![1], !lit![2]);
+        cnf.add_ternary(!lit![3], !lit![4], !lit![5]);
+        let softs = (0..6).map(|v| (clause![rustsat::types::Var::new(v).pos_lit()], 1));
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let am1s = maxpre.prepro_label_am1s();
+        assert_eq!(am1s.len(), 1);
+        let mut am1 = am1s[0].clone();
+        am1.sort();
+        assert_eq!(am1, vec![lit![0], lit![1], lit![2]]);
+    }
+
+    #[cfg(feature = "proofs")]
+    #[test]
+    fn proof() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_unit(lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let path = std::env::temp_dir().join(format!("maxpre-rs-proof-{}.pbp", std::process::id()));
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        assert!(maxpre
+            .set_proof_file(&path, crate::ProofFormat::VeriPb)
+            .is_ok());
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        assert!(maxpre
+            .set_proof_file(&path, crate::ProofFormat::VeriPb)
+            .is_err());
+        drop(maxpre);
+        let proof = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(proof.starts_with("pseudo-Boolean proof version"));
+        assert!(proof.contains("output EQUIOPTIMAL IMPLICIT"));
+    }
+
+    #[test]
+    fn lower_bounds() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 3), (clause![!lit![1]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.lower_bounds(), vec![3]);
+    }
+
+    #[test]
+    fn preprocess_reconstruct() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let (hards, objs) = maxpre.prepro_instance();
+        assert_eq!(objs.len(), 1);
+        // Solve preprocessed instance by brute force
+        let vars: Vec<_> = (0..=maxpre.max_orig_var().idx32())
+            .chain(
+                hards
+                    .iter()
+                    .flat_map(|cl| cl.iter().map(|l| l.var().idx32())),
+            )
+            .chain(
+                objs[0]
+                    .0
+                    .iter()
+                    .flat_map(|(cl, _)| cl.iter().map(|l| l.var().idx32())),
+            )
+            .collect();
+        let max_var = *vars.iter().max().unwrap();
+        let (sol, cost) = (0..1u32 << (max_var + 1))
+            .map(|bits| -> Assignment {
+                (0..=max_var)
+                    .map(|v| rustsat::types::Var::new(v).lit(bits & (1 << v) == 0))
+                    .collect()
+            })
+            .filter(|sol| hards.iter().all(|cl| cl.evaluate(sol) == TernaryVal::True))
+            .map(|sol| {
+                let cost: usize = objs[0]
+                    .0
+                    .iter()
+                    .filter(|(cl, _)| cl.evaluate(&sol) != TernaryVal::True)
+                    .map(|(_, w)| w)
+                    .sum();
+                (sol, cost)
+            })
+            .min_by_key(|(_, cost)| *cost)
+            .unwrap();
+        assert_eq!(cost as isize + objs[0].1, 1);
+        let sol = maxpre.reconstruct(sol);
+        assert_eq!(sol.var_value(var![0]), TernaryVal::False);
+        assert_eq!(sol.var_value(var![2]), TernaryVal::True);
+    }
+
+    #[test]
+    fn reset() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![2]);
+        cnf.add_unit(lit![1]);
+        let mut maxpre = MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf.clone(), vec![], false);
+        assert!(maxpre.reset().is_err());
+        let mut maxpre = keeping_maxpre(cnf, vec![], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let first = maxpre.prepro_instance();
+        assert!(maxpre.reset().is_ok());
+        assert_eq!(maxpre.n_prepro_clauses(), 0);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.prepro_instance(), first);
+    }
+
+    #[test]
+    fn trace() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        assert!(maxpre.trace().is_empty());
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        // x1 is eliminated, after which x0 and x2 can be fixed to their
+        // optimal values
+        assert_eq!(
+            maxpre.trace(),
+            vec![
+                TraceOp::Bve {
+                    var: var![1],
+                    clauses: vec![clause![lit![2]]],
+                },
+                TraceOp::Fix { lit: !lit![0] },
+                TraceOp::Fix { lit: lit![2] },
+            ]
+        );
+    }
+
+    #[test]
+    fn equivalences() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], !lit![1]);
+        cnf.add_binary(!lit![0], lit![1]);
+        cnf.add_binary(lit![1], lit![2]);
+        cnf.add_binary(!lit![1], !lit![2]);
+        cnf.add_ternary(lit![0], lit![2], lit![3]);
+        let softs = vec![(clause![!lit![3]], 1), (clause![lit![4]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        assert!(maxpre.equivalences().is_empty());
+        maxpre.preprocess("[e]", 0, 1e9);
+        let classes = maxpre.equivalences();
+        assert_eq!(classes.len(), 1);
+        let class = &classes[0];
+        assert_eq!(class.len(), 3);
+        // x0 and x1 have the same value and x2 the opposite
+        let is_neg = |idx: u32| {
+            class
+                .iter()
+                .find(|l| l.var() == var![idx])
+                .unwrap()
+                .is_neg()
+        };
+        assert_eq!(is_neg(0), is_neg(1));
+        assert_ne!(is_neg(1), is_neg(2));
+    }
+
+    #[test]
+    fn provenance() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_binary(lit![3], lit![4]);
+        let softs = vec![
+            (clause![!lit![0]], 1),
+            (clause![!lit![2]], 1),
+            (clause![!lit![3]], 1),
+            (clause![!lit![4]], 1),
+        ];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], true);
+        assert!(maxpre.track_provenance().is_err());
+        let mut maxpre = keeping_maxpre(cnf, vec![(softs, 0)], true);
+        assert!(maxpre.provenance().is_none());
+        assert!(maxpre.track_provenance().is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        let Some(prov) = maxpre.provenance() else {
+            panic!("provenance is tracked");
+        };
+        assert_eq!(prov.hards, vec![ClauseOrigin::Original; 3]);
+        assert_eq!(prov.softs, vec![vec![ClauseOrigin::Original; 4]]);
+        // Eliminating x1 resolves the first two clauses
+        maxpre.preprocess("[v]", 0, 1e9);
+        assert!(maxpre.add_clause(clause![lit![3], !lit![4]]).is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (hards, _) = maxpre.prepro_instance();
+        let Some(prov) = maxpre.provenance() else {
+            panic!("provenance is tracked");
+        };
+        assert_eq!(prov.hards.len(), hards.len());
+        for (cl, origin) in hards.iter().zip(&prov.hards) {
+            let expected = if *cl == clause![lit![0], lit![2]] {
+                ClauseOrigin::Preprocessed {
+                    round: 1,
+                    techniques: vec![Technique::Bve],
+                }
+            } else if *cl == clause![lit![3], !lit![4]] {
+                ClauseOrigin::Added { round: 2 }
+            } else {
+                ClauseOrigin::Original
+            };
+            assert_eq!(*origin, expected);
+        }
+        assert!(matches!(
+            maxpre.track_provenance(),
+            Err(crate::Error::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn apply() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        let res = maxpre.apply(Technique::Bve, TechniqueBudget::default());
+        assert!(res.ran);
+        assert_eq!(res.removed_vars, 1);
+        assert_eq!(maxpre.technique_runs(Technique::Bve), 1);
+        assert_eq!(maxpre.technique_runs(Technique::Up), 0);
+        let res = maxpre.apply(
+            Technique::Bve,
+            TechniqueBudget::default().max_applications(1),
+        );
+        assert!(!res.ran);
+        assert_eq!(maxpre.technique_runs(Technique::Bve), 1);
+    }
+
+    #[test]
+    fn forbid_aux_vars() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(!lit![0], !lit![1]);
+        cnf.add_binary(!lit![0], !lit![2]);
+        cnf.add_binary(!lit![1], !lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 1),
+            (clause![!lit![1]], 1),
+            (clause![!lit![2]], 1),
+            (clause![lit![3], lit![4]], 1),
+        ];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        maxpre.preprocess("[m]", 0, 1e9);
+        assert_eq!(maxpre.technique_runs(Technique::Am1), 1);
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.forbid_aux_vars(true);
+        maxpre.preprocess("[m]", 0, 1e9);
+        assert_eq!(maxpre.technique_runs(Technique::Am1), 0);
+        // The label of the non-unit soft clause
+        assert_eq!(maxpre.n_aux_vars(), 1);
+    }
+
+    #[test]
+    fn preservation() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_binary(lit![3], !lit![4]);
+        cnf.add_binary(!lit![3], lit![4]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        for (preservation, bve, ee) in [
+            (Preservation::Satisfiability, 1, 1),
+            (Preservation::Models, 0, 1),
+            (Preservation::Equivalence, 0, 0),
+        ] {
+            let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+            maxpre.set_preservation(preservation);
+            maxpre.preprocess("[bve]", 0, 1e9);
+            assert_eq!(maxpre.technique_runs(Technique::Bce), bve);
+            assert_eq!(maxpre.technique_runs(Technique::Bve), bve);
+            assert_eq!(maxpre.technique_runs(Technique::Ee), ee);
+        }
+    }
+
+    #[test]
+    fn anonymized() {
+        let (cnf, softs) = random_instance(11);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let (hards, _, anon) = maxpre.prepro_instance_anonymized(Some(3));
+        assert_eq!(maxpre.prepro_instance_anonymized(Some(3)).0, hards);
+        let sol: rustsat::types::Assignment = (0..anon.permutation().len() as u32)
+            .map(|idx| rustsat::types::Var::new(idx).lit(idx % 2 == 0))
+            .collect();
+        let mapped = sol.iter().filter_map(|l| anon.prepro_lit(l)).collect();
+        assert_eq!(
+            maxpre.reconstruct_anonymized(sol, &anon),
+            maxpre.reconstruct(mapped)
+        );
+    }
+
+    #[test]
+    fn objective_names() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let objs = vec![
+            (vec![(clause![!lit![0]], 1)], 0, "cost"),
+            (vec![(clause![!lit![1]], 1)], 2, "time"),
+        ];
+        let (objs, names) = objs
+            .into_iter()
+            .map(|(softs, offset, name)| ((softs, offset), Some(String::from(name))))
+            .unzip();
+        // Resetting needs the original instance, which `new_named` does not keep
+        let mut maxpre = keeping_maxpre(cnf, objs, false);
+        maxpre.set_objective_names(names);
+        assert_eq!(maxpre.objective_names()[1].as_deref(), Some("time"));
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (_, objs) = maxpre.prepro_instance_named();
+        assert_eq!(objs[0].2.as_deref(), Some("cost"));
+        assert_eq!(objs[1].1, 2);
+        maxpre.set_objective_names(vec![None, Some(String::from("delay"))]);
+        assert_eq!(
+            maxpre.stats().obj_names,
+            vec![None, Some(String::from("delay"))]
+        );
+        let mut out = vec![];
+        assert!(maxpre.write_prepro_dimacs(&mut out).is_ok());
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("c objective 2: delay\n"));
+        assert!(maxpre.reset().is_ok());
+        assert_eq!(maxpre.objective_names()[1].as_deref(), Some("delay"));
+    }
+
+    #[test]
+    fn capabilities() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let maxpre = MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf.clone(), vec![], true);
+        let caps = maxpre.capabilities();
+        assert!(caps.inprocessing);
+        assert_eq!(caps.proofs, cfg!(feature = "proofs"));
+        assert_eq!(caps.techniques, Technique::ALL);
+        assert_eq!(caps.max_var_idx, core::ffi::c_int::MAX as u32 - 1);
+        let maxpre = MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf, vec![], false);
+        assert!(!maxpre.capabilities().inprocessing);
+    }
+
+    #[test]
+    fn try_new() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let try_new = |softs: Vec<(Clause, u64)>| MaxPre::try_new(cnf.clone(), [(softs, 0)], false);
+        assert!(matches!(
+            try_new(vec![(clause![lit![0]], 1), (clause![], 1)]),
+            Err(crate::Error::InvalidInput(InputError::EmptySoftClause {
+                obj_idx: 0,
+                cl_idx: 1
+            }))
+        ));
+        assert!(matches!(
+            try_new(vec![(clause![lit![2], !lit![2]], 1)]),
+            Err(crate::Error::InvalidInput(
+                InputError::TautologicalSoftClause { .. }
+            ))
+        ));
+        let var = rustsat::types::Var::new(core::ffi::c_int::MAX as u32);
+        assert!(matches!(
+            try_new(vec![(clause![var.pos_lit()], 1)]),
+            Err(crate::Error::InvalidInput(InputError::VarOutOfRange(v))) if v == var
+        ));
+        assert!(matches!(
+            try_new(vec![(clause![lit![0]], u64::MAX)]),
+            Err(crate::Error::WeightOverflow)
+        ));
+        let Ok(mut maxpre) = try_new(vec![(clause![!lit![0]], 0), (clause![!lit![1]], 2)]) else {
+            panic!("instance is valid");
+        };
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (_, objs) = maxpre.prepro_instance();
+        assert_eq!(objs[0].0.len(), 1);
+        assert_eq!(objs[0].0[0].1, 2);
+    }
+
+    #[test]
+    fn skipped_techniques() {
+        let (cnf, softs) = crate::testing::random_instance(&InstanceConfig::LARGE, 0);
+        let mut maxpre = MaxPre::new(cnf.clone(), softs.clone(), false);
+        let summary = maxpre.preprocess_summarized("[bu]#[buvsrgc]", 0, 1e9);
+        assert!(!maxpre.hit_time_limit());
+        assert!(summary.skipped_techniques.is_empty());
+        assert_eq!(summary.cutoff, None);
+        // Under a short time limit, techniques are run without getting to
+        // remove anything, which does not make them skipped
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        let summary = maxpre.preprocess_summarized("[bu]#[buvsrgc]", 0, 1e-3);
+        assert!(maxpre.hit_time_limit());
+        let stats = maxpre.technique_stats();
+        let unchanged: Vec<_> = stats
+            .iter()
+            .filter(|ts| ts.invocations > Some(0) && ts.removed_clauses == 0)
+            .collect();
+        assert!(!unchanged.is_empty());
+        for ts in unchanged {
+            assert!(!summary.skipped_techniques.contains(&ts.technique));
+        }
+        assert_eq!(stats.len(), 7);
+        assert!(summary.skipped_techniques.is_empty());
+        assert_eq!(summary.cutoff, None);
+        let ran = |t: Technique| t == Technique::Up || t == Technique::Bve;
+        assert_eq!(
+            crate::schedule_cutoff("[bu]#[buvsrgc]", ran),
+            (
+                vec![
+                    Technique::Se,
+                    Technique::Ssr,
+                    Technique::Gsle,
+                    Technique::Bcr
+                ],
+                Some(9)
+            )
+        );
+        assert_eq!(crate::schedule_cutoff("[bu]", |_| true), (vec![], None));
+    }
+
+    #[test]
+    fn preprocess_until_fixpoint() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(!lit![0], lit![1]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![(clause![!lit![2]], 1), (clause![!lit![3]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], true);
+        let rounds = maxpre.preprocess_until_fixpoint("[u]", 1e9, 0.01, 1e9);
+        assert_eq!(rounds.len(), 2);
+        assert_eq!(rounds[0].n_clauses, 3);
+        assert_eq!(rounds[0].reduction, 0.4);
+        assert_eq!(rounds[1].n_clauses, 3);
+        assert_eq!(rounds[1].reduction, 0.);
+        assert_eq!(maxpre.technique_runs(Technique::Up), 2);
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        assert_eq!(
+            maxpre
+                .preprocess_until_fixpoint("[u]", 1e9, 0.01, 1e9)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn preprocess_auto() {
+        let (cnf, softs) = random_instance(3);
+        let mut maxpre = quiet_maxpre(cnf, softs, true);
+        let plan = maxpre.preprocess_auto(0, 1e9);
+        assert_eq!(
+            plan.reasons,
+            vec!["small instance, model search is run once"]
+        );
+        assert_eq!(maxpre.technique_runs(Technique::Tms), 1);
+        maxpre.preprocess_auto(0, 1e9);
+        assert_eq!(maxpre.technique_runs(Technique::Tms), 1);
+        assert_eq!(maxpre.technique_runs(Technique::Up), 2);
+        let plan = AutoPlan::choose(AutoPlan::HUGE_CLAUSES + 1, 1, true, 10.);
+        assert_eq!(plan.techniques, Techniques::LIGHT);
+        let plan = AutoPlan::choose(AutoPlan::SMALL_CLAUSES + 1, 2, true, 10.);
+        assert_eq!(plan.techniques, Techniques::DEFAULT);
+        let plan = AutoPlan::choose(AutoPlan::SMALL_CLAUSES + 1, 1, false, 10.);
+        assert_eq!(plan.techniques, Techniques::UNWEIGHTED);
+    }
+
+    #[test]
+    fn technique_stats() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![2]);
+        cnf.add_unit(lit![1]);
+        let mut maxpre = keeping_maxpre(cnf, vec![], false);
+        assert!(maxpre.technique_stats().is_empty());
+        maxpre.preprocess("[u]", 0, 1e9);
+        let stats = maxpre.technique_stats();
+        let up = stats
+            .iter()
+            .find(|ts| ts.technique == Technique::Up)
+            .unwrap();
+        assert!(up.removed_clauses > 0);
+        assert_eq!(up.invocations, None);
+        assert!(!up.hit_limit);
+        // Invocations are only tracked with a time limit
+        assert!(maxpre.reset().is_ok());
+        maxpre.preprocess("[u]", 0, 10.);
+        let stats = maxpre.technique_stats();
+        let up = stats
+            .iter()
+            .find(|ts| ts.technique == Technique::Up)
+            .unwrap();
+        assert!(up.invocations.is_some());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn stats_json() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![2]);
+        cnf.add_unit(lit![1]);
+        let mut maxpre = MaxPre::new::<_, _, Vec<(Clause, u64)>>(cnf, vec![], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let json: serde_json::Value = serde_json::from_str(&maxpre.stats_json()).unwrap();
+        assert_eq!(json["stats"]["n_orig_hard_clauses"], 2);
+        assert_eq!(json["stats"]["max_orig_var"], 2);
+        assert_eq!(json["techniques"][0]["technique"], "Up");
+    }
+
+    #[test]
+    fn eval_objectives() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut pps: Vec<Box<dyn PreproClauses>> = vec![
+            Box::new(MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false)),
+            Box::new(crate::NoopPrepro::new(
+                cnf.clone(),
+                vec![(softs.clone(), 0)],
+                false,
+            )),
+            Box::new(crate::NativePrepro::new(
+                cnf.clone(),
+                vec![(softs.clone(), 0)],
+                false,
+            )),
+        ];
+        let sol: Assignment = (0..3)
+            .map(|idx| rustsat::types::Var::new(idx).pos_lit())
+            .collect();
+        for pp in &mut pps {
+            pp.preprocess("[u]", 0, 1e9);
+            assert_eq!(pp.eval_objectives(&sol, VarSpace::Original), vec![3]);
+            assert_eq!(pp.eval_objectives(&sol, VarSpace::Preprocessed), vec![3]);
+        }
+    }
+
+    #[test]
+    fn removed_weight() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_unit(!lit![3]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 1),
+            (clause![!lit![3]], 4),
+        ];
+        let mut pps: Vec<Box<dyn PreproClauses>> = vec![
+            Box::new(MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false)),
+            Box::new(crate::NativePrepro::new(
+                cnf.clone(),
+                vec![(softs.clone(), 0)],
+                false,
+            )),
+        ];
+        for pp in &mut pps {
+            pp.preprocess("[u]", 0, 1e9);
+            let removed = pp.removed_weight();
+            assert_eq!(removed.falsified, vec![2]);
+            assert_eq!(removed.hardened, vec![4]);
+            assert_eq!(removed.incurred(), vec![2]);
+        }
+    }
+
+    #[test]
+    fn reset_removed_weight_for() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        let objs = vec![
+            (vec![(clause![!lit![0]], 2)], 0),
+            (vec![(clause![!lit![0]], 3)], 0),
+        ];
+        let mut maxpre = MaxPre::new(cnf.clone(), objs.clone(), false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre.reset_removed_weight_for(0).is_err());
+        let mut maxpre = MaxPre::new(cnf, objs, true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.removed_weight().incurred(), vec![2, 3]);
+        assert!(maxpre.reset_removed_weight_for(0).is_ok());
+        assert_eq!(maxpre.removed_weight().incurred(), vec![0, 3]);
+        assert!(maxpre.reset_removed_weight_for(2).is_err());
+    }
+
+    #[test]
+    fn fixed_lits_since_last_call() {
+        let sorted = |mut lits: Vec<Lit>| {
+            lits.sort_unstable();
+            lits
+        };
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![(clause![!lit![1]], 1), (clause![!lit![2]], 1)];
+        let mut maxpre = keeping_maxpre(cnf, vec![(softs, 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.fixed_lits_since_last_call(), vec![lit![0]]);
+        assert!(maxpre.fixed_lits_since_last_call().is_empty());
+        assert!(maxpre.add_clause(clause![!lit![1]]).is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(
+            sorted(maxpre.fixed_lits_since_last_call()),
+            vec![!lit![1], lit![2]]
+        );
+        assert!(maxpre.reset().is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.fixed_lits_since_last_call(), vec![lit![0]]);
+    }
+
+    #[test]
+    fn status() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![(clause![!lit![1]], 1), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        assert_eq!(maxpre.status(), PreproStatus::Unknown);
+        cnf.add_unit(!lit![1]);
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let PreproStatus::OptimumFound(model, costs) = maxpre.status() else {
+            panic!("instance should be solved")
+        };
+        assert_eq!(costs, vec![1]);
+        assert_eq!(model.lit_value(lit![2]), TernaryVal::True);
+        cnf.add_unit(!lit![2]);
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.status(), PreproStatus::Unsat);
+    }
+
+    #[test]
+    fn objective_order() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let objs = vec![
+            (vec![(clause![!lit![0]], 1)], 0),
+            (vec![(clause![!lit![1]], 5), (clause![!lit![2]], 3)], 0),
+        ];
+        let mut maxpre = keeping_maxpre(cnf, objs, false);
+        assert_eq!(maxpre.objective_order(), vec![0, 1]);
+        assert!(maxpre.set_objective_order(&[0, 0]).is_err());
+        assert!(maxpre.set_objective_order(&[1, 0]).is_ok());
+        assert_eq!(maxpre.objective_order(), vec![1, 0]);
+        assert_eq!(maxpre.n_orig_soft_clauses(1), 2);
+        assert_eq!(maxpre.orig_weight(1), 8);
+        // The cost of the first objective by priority is tracked
+        let model: Assignment = [lit![0], lit![1], !lit![2]].into_iter().collect();
+        assert!(maxpre.set_initial_model(model).is_ok());
+        assert_eq!(maxpre.upper_bound(), 5);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(maxpre.removed_weight().incurred(), vec![1, 0]);
+        let (_, objs) = maxpre.prepro_instance();
+        assert_eq!(objs[0], (vec![], 1));
+        let weights: Vec<_> = objs[1].0.iter().map(|(_, w)| *w).collect();
+        assert_eq!(weights, vec![5, 3]);
+    }
+
+    #[test]
+    fn scalarize() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let objs = vec![
+            (vec![(clause![!lit![0]], 1)], 1),
+            (vec![(clause![!lit![1]], 3)], 0),
+        ];
+        let maxpre = keeping_maxpre(cnf, objs, false);
+        assert!(maxpre.scalarize(&[1]).is_err());
+        assert!(maxpre.scalarize(&[u64::MAX, 2]).is_err());
+        let mut scalarized = maxpre.scalarize(&[2, 1]).unwrap_or_else(|_| panic!());
+        assert_eq!(scalarized.weights(), &[2, 1]);
+        assert_eq!(scalarized.orig_weight(0), 5);
+        scalarized.preprocess("[u]", 0, 1e9);
+        let (_, objs) = scalarized.prepro_instance();
+        assert_eq!(objs.len(), 1);
+        assert_eq!(objs[0].1, 2);
+        let sol: Assignment = [lit![0], !lit![1]].into_iter().collect();
+        let rec = scalarized.reconstruct(sol);
+        assert_eq!(scalarized.objective_values(&rec), vec![1, 0]);
+    }
+
+    #[test]
+    fn strata() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 5),
+            (clause![!lit![2]], 2),
+        ];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let mut strata = maxpre.strata(0);
+        strata.iter_mut().for_each(|(_, lits)| lits.sort());
+        assert_eq!(
+            strata,
+            vec![(5, vec![!lit![1]]), (2, vec![!lit![0], !lit![2]])]
+        );
+        assert!(maxpre.strata(1).is_empty());
+    }
+
+    #[test]
+    fn stratified() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![
+            (clause![!lit![0]], 1),
+            (clause![!lit![1]], 5),
+            (clause![!lit![2]], 3),
+        ];
+        let mut stratified: Stratified<MaxPre> = Stratified::new(cnf, vec![(softs, 0)], 3);
+        assert_eq!(stratified.threshold(), 3);
+        assert_eq!(stratified.pending(), &[(clause![!lit![0]], 1)]);
+        assert_eq!(stratified.n_orig_soft_clauses(0), 2);
+        stratified.preprocess("[u]", 0, 1e9);
+        let (_, objs) = stratified.prepro_instance();
+        assert!(objs[0].0.iter().all(|(_, w)| *w >= 3));
+    }
+
+    #[test]
+    fn stratified_levels() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![
+            (clause![!lit![0]], 1),
+            (clause![!lit![1]], 5),
+            (clause![!lit![3]], 1),
+        ];
+        let mut stratified: Stratified<MaxPre> = Stratified::new(cnf, vec![(softs, 0)], 5);
+        assert!(stratified.preprocess_level(5, "[u]", 0, 1e9).is_ok());
+        assert_eq!(stratified.pending().len(), 2);
+        assert!(stratified.preprocess_level(1, "[u]", 0, 1e9).is_ok());
+        assert_eq!(stratified.threshold(), 1);
+        assert!(stratified.pending().is_empty());
+        let label_map = stratified.label_map();
+        assert_eq!(label_map.len(), 3);
+        assert!(label_map
+            .keys()
+            .all(|l| *l == !lit![1] || l.var() > var![3]));
+        let strata = stratified.strata(0);
+        assert_eq!(strata.len(), 2);
+        assert_eq!(strata[1].0, 1);
+        assert_eq!(strata[1].1.len(), 2);
+    }
+
+    #[test]
+    fn inprocessing_loop() {
+        use crate::{testing::optimum, PreproState};
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![2]], 1),
+            (clause![!lit![3]], 1),
+        ];
+        let mut hards = cnf.clone();
+        let mut maxpre = MaxPre::new(cnf, vec![(softs.clone(), 0)], true);
+        assert_eq!(maxpre.state(), PreproState::Loaded);
+        for _ in 0..3 {
+            maxpre.preprocess("[uvs]", 0, 1e9);
+            assert_eq!(maxpre.state(), PreproState::Preprocessed);
+            let (prepro_hards, prepro_objs) = maxpre.prepro_instance();
+            let Some((sol, cost)) = optimum(&prepro_hards, &prepro_objs) else {
+                assert!(optimum(&hards, &[]).is_none());
+                break;
+            };
+            // Block the solution of the preprocessed instance
+            let block: Clause = prepro_hards
+                .iter()
+                .chain(prepro_objs[0].0.iter().map(|(cl, _)| cl))
+                .flat_map(|cl| cl.iter().map(|l| l.var()))
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .map(|v| v.lit(sol.var_value(v) == TernaryVal::True))
+                .collect();
+            let rec = maxpre.reconstruct(sol);
+            assert!(hards.iter().all(|cl| cl.evaluate(&rec) == TernaryVal::True));
+            let rec_cost = maxpre.eval_objectives(&rec, VarSpace::Original)[0];
+            assert!(rec_cost as isize <= cost as isize + prepro_objs[0].1);
+            assert!(maxpre.add_clause(block.clone()).is_ok());
+            hards.add_clause(block);
+            assert_eq!(maxpre.state(), PreproState::Modified);
+            assert_eq!(maxpre.status(), PreproStatus::Unknown);
+        }
+    }
+
+    #[test]
+    fn redundant_clauses() {
+        use crate::Redundancy;
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_unit(lit![3]);
+        let softs = vec![(clause![!lit![0]], 1), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        assert!(maxpre
+            .add_clause_with_redundancy(clause![lit![0], lit![2]], Redundancy::Redundant)
+            .is_err());
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        assert!(maxpre
+            .add_clause_with_redundancy(clause![lit![0], lit![2]], Redundancy::Redundant)
+            .is_ok());
+        assert!(maxpre
+            .add_clause_with_redundancy(clause![lit![0], !lit![3]], Redundancy::Redundant)
+            .is_ok());
+        assert_eq!(maxpre.state(), crate::PreproState::Loaded);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (hards, _) = maxpre.prepro_instance();
+        // The redundant clauses are not part of the preprocessed instance
+        assert_eq!(hards.len(), 2);
+        assert_eq!(
+            maxpre.prepro_redundant_clauses(),
+            vec![clause![lit![0], lit![2]]]
+        );
+    }
+
+    #[test]
+    fn call_order_guards() {
+        use crate::{Error, Redundancy};
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        assert!(!maxpre.is_inprocessing());
+        assert!(matches!(
+            maxpre.try_prepro_instance(),
+            Err(Error::InvalidState)
+        ));
+        assert!(maxpre.try_preprocess("[bu]", 0, 1e9).is_ok());
+        assert!(maxpre.try_prepro_instance().is_ok());
+        assert!(matches!(
+            maxpre.try_preprocess("[bu]", 0, 1e9),
+            Err(Error::InvalidState)
+        ));
+        assert!(matches!(maxpre.add_var(), Err(Error::NotInprocessing)));
+        assert!(matches!(maxpre.add_vars(2), Err(Error::NotInprocessing)));
+        assert!(matches!(
+            maxpre.add_clause(clause![lit![0]]),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.add_clause_with_redundancy(clause![lit![0]], Redundancy::Redundant),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.add_label(lit![2], 1),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.add_group(0, [clause![lit![0]]], 1),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.alter_weight(lit![0], 1),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.alter_weights([(lit![0], 1)]),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.label_to_var(lit![0]),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            maxpre.reset_removed_weight(),
+            Err(Error::NotInprocessing)
+        ));
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        assert!(maxpre.is_inprocessing());
+        assert!(maxpre.try_preprocess("[bu]", 0, 1e9).is_ok());
+        assert!(maxpre.try_preprocess("[bu]", 0, 1e9).is_ok());
+        assert!(maxpre.add_clause(clause![lit![0], lit![1]]).is_ok());
+        assert!(maxpre.try_prepro_instance().is_ok());
+    }
+
+    #[test]
+    fn objective_bound() {
+        use rustsat::encodings::pb::GeneralizedTotalizer;
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 1),
+            (clause![!lit![1]], 3),
+            (clause![!lit![2]], 1),
+        ];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre
+            .add_objective_bound(0, 2, &mut GeneralizedTotalizer::default())
+            .is_err());
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre
+            .add_objective_bound(1, 2, &mut GeneralizedTotalizer::default())
+            .is_err());
+        assert!(maxpre
+            .add_objective_bound(0, 2, &mut GeneralizedTotalizer::default())
+            .is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        // only the solution setting variables 0 and 2 is within the bound
+        let (_, objs) = maxpre.prepro_instance();
+        assert_eq!(objs[0].1, 2);
+        let mut fixed = maxpre.prepro_fixed_lits();
+        fixed.sort_unstable();
+        assert_eq!(fixed, vec![lit![0], !lit![1], lit![2]]);
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], true);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert!(maxpre
+            .add_objective_bound(0, 1, &mut GeneralizedTotalizer::default())
+            .is_ok());
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (hards, objs) = maxpre.prepro_instance();
+        assert!(crate::testing::optimum(&hards, &objs).is_none());
+    }
+
+    #[test]
+    fn partial_reconstruction() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![2], lit![3]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        // the solver left x0 and x2 unassigned since the clauses are
+        // satisfied without them
+        let sol: Assignment = [lit![1], lit![3]].into_iter().collect();
+        let rec = maxpre.reconstruct_partial(sol.clone());
+        assert_eq!(rec.lit_value(lit![1]), TernaryVal::True);
+        assert_eq!(rec.lit_value(lit![3]), TernaryVal::True);
+        assert_eq!(rec.var_value(var![0]), TernaryVal::DontCare);
+        assert_eq!(rec.var_value(var![2]), TernaryVal::DontCare);
+        let rec = maxpre.reconstruct(sol);
+        assert_ne!(rec.var_value(var![0]), TernaryVal::DontCare);
+    }
+
+    #[test]
+    fn reconstruct_lits() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let model = [!lit![0], lit![2]];
+        let rec = maxpre.reconstruct_lits(model.iter().copied());
+        assert_eq!(rec, maxpre.reconstruct(model.into_iter().collect()));
+        assert_eq!(rec.lit_value(lit![1]), TernaryVal::True);
+    }
+
+    #[test]
+    fn write_prepro_dimacs() {
+        let lines = |maxpre: &MaxPre| {
+            let mut out = Vec::new();
+            assert!(maxpre.write_prepro_dimacs(&mut out).is_ok());
+            let mut lines: Vec<String> = String::from_utf8(out)
+                .unwrap_or_else(|_| panic!("output is not UTF-8"))
+                .lines()
+                .map(String::from)
+                .collect();
+            lines.sort_unstable();
+            lines
+        };
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_ternary(lit![1], lit![2], lit![3]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(
+            lines(&maxpre),
+            vec!["1 -2 0", "2 -1 0", "h 1 2 0", "h 2 3 4 0"]
+        );
+        let objs = vec![(softs, 0), (vec![(clause![!lit![2]], 3)], 0)];
+        let mut maxpre = MaxPre::new(cnf, objs, false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(
+            lines(&maxpre),
+            vec![
+                "h 1 2 0",
+                "h 2 3 4 0",
+                "o1 1 -2 0",
+                "o1 2 -1 0",
+                "o2 3 -3 0"
+            ]
+        );
+    }
+
+    #[test]
+    fn label_stats() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 3),
+            (clause![!lit![1]], 1),
+            (clause![!lit![2]], 2),
+        ];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.preprocess("[u]", 0, 1e9);
+        assert_eq!(
+            maxpre.label_stats(),
+            LabelStats {
+                n_orig_labels: vec![3],
+                orig_weight: vec![6],
+                n_prepro_labels: vec![2],
+                prepro_weight: vec![3],
+            }
+        );
+    }
+
+    #[test]
+    fn elapsed() {
+        let (cnf, softs) = crate::testing::random_instance(&InstanceConfig::LARGE, 0);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let first = maxpre.elapsed();
+        assert_eq!(first.total, first.last_call);
+        maxpre.preprocess("[bu]", 0, 1e9);
+        let second = maxpre.elapsed();
+        assert_eq!(second.total, first.total + second.last_call);
+        assert_eq!(second.total, maxpre.stats().prepro_time);
+        assert!(second
+            .techniques
+            .iter()
+            .all(|(_, time)| *time <= second.total));
+    }
+
+    #[test]
+    fn objective_offsets() {
+        use crate::NativePrepro;
+
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 3), (clause![!lit![1]], 1)];
+        let mut maxpre = MaxPre::new(cnf.clone(), vec![(softs.clone(), 5)], false);
+        assert_eq!(maxpre.orig_offset(0), 5);
+        maxpre.preprocess("[u]", 0, 1e9);
+        let (_, objs) = maxpre.prepro_instance();
+        assert_eq!(objs[0].1, 8);
+        assert_eq!(maxpre.removed_weight().incurred(), vec![3]);
+        let mut native = NativePrepro::new(cnf, vec![(softs, -2)], false);
+        native.preprocess("[u]", 0, 1e9);
+        assert_eq!(native.orig_offset(0), -2);
+        let (_, objs) = native.prepro_instance();
+        assert_eq!(objs[0].1, 1);
+    }
+
+    #[test]
+    fn assumption_vars() {
+        use crate::Assumption;
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_binary(lit![3], lit![4]);
+        cnf.add_unit(lit![4]);
+        let softs = vec![(clause![!lit![2]], 1), (clause![!lit![3]], 1)];
+        let mut maxpre = MaxPre::new(cnf, vec![(softs, 0)], false);
+        maxpre.declare_assumption_vars(&[var![0], var![1], var![4]]);
+        assert_eq!(
+            maxpre.map_assumption(lit![0]),
+            Some(Assumption::Lit(lit![0]))
+        );
+        maxpre.preprocess("[bu]#[bvu]", 0, 1e9);
+        // Elimination of the assumption variables is skipped
+        let (hards, _) = maxpre.prepro_instance();
+        assert_eq!(hards.len(), 2);
+        let Some(Assumption::Lit(lit)) = maxpre.map_assumption(!lit![1]) else {
+            panic!("assumption variable was removed");
+        };
+        assert!(lit.is_neg());
+        assert_eq!(maxpre.map_assumption(lit![4]), Some(Assumption::Satisfied));
+        assert_eq!(maxpre.map_assumption(!lit![4]), Some(Assumption::Falsified));
+        assert_eq!(maxpre.map_assumption(lit![2]), None);
+    }
+
+    #[test]
+    fn dyn_prepro() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![2]], 1)];
+        let mut pps: Vec<Box<dyn PreproClauses>> = vec![
+            Box::new(MaxPre::new(cnf.clone(), vec![(softs.clone(), 0)], false)),
+            Box::new(crate::NoopPrepro::new(
+                cnf.clone(),
+                vec![(softs.clone(), 0)],
+                false,
+            )),
+            Box::new(crate::NativePrepro::new(
+                cnf.clone(),
+                vec![(softs.clone(), 0)],
+                false,
+            )),
+        ];
+        for pp in &mut pps {
+            pp.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+            let (hards, _) = pp.prepro_instance();
+            let sol: Assignment = (0..3)
+                .map(|idx| rustsat::types::Var::new(idx).pos_lit())
+                .collect();
+            assert!(hards.iter().all(|cl| cl.evaluate(&sol) == TernaryVal::True));
+            let rec = pp.reconstruct(sol);
+            assert!(cnf.iter().all(|cl| cl.evaluate(&rec) == TernaryVal::True));
+        }
     }
 }
@@ -0,0 +1,615 @@
+//! # C API
+//!
+//! A C interface to [`MaxPre`] for solvers not written in Rust, with error
+//! codes instead of aborts, structured statistics and solution
+//! reconstruction. The declarations are in `include/maxpre_rs.h`, which is
+//! generated from this module with `cbindgen --config cbindgen.toml --output
+//! include/maxpre_rs.h`. To link against the crate, build it as a static
+//! library with `cargo rustc --release --features capi --crate-type
+//! staticlib`.
+//!
+//! Literals are passed in IPASIR form as non-zero integers. All functions
+//! taking a pointer to a preprocessor or builder return
+//! [`MaxPreRsStatus::NullPointer`] (or null, or zero) if it is null. Panics
+//! do not unwind into the caller, they are reported as
+//! [`MaxPreRsStatus::Panicked`] (or null) and the preprocessor or builder
+//! should not be used afterwards, other than releasing it.
+
+use core::ffi::{c_char, c_double, c_int, c_void, CStr};
+
+use rustsat::types::{Assignment, Clause, Lit, TernaryVal, Var};
+
+use crate::{Error, MaxPre, MaxPreBuilder, PreproClauses, PreproInit};
+
+/// Status codes returned by the C API
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxPreRsStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A pointer argument was null
+    NullPointer = 1,
+    /// A literal was zero or out of range
+    InvalidLit = 2,
+    /// A string argument was not valid UTF-8
+    InvalidString = 3,
+    /// An output buffer was too small
+    BufferTooSmall = 4,
+    /// MaxPre rejected the call
+    Rejected = 5,
+    /// The call is not valid in the current state of the preprocessor
+    InvalidState = 6,
+    /// The call requires the preprocessor to be in inprocessing mode
+    NotInprocessing = 7,
+    /// The call panicked
+    Panicked = 8,
+}
+
+impl From<Error> for MaxPreRsStatus {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Generic => MaxPreRsStatus::Rejected,
+            Error::InvalidState => MaxPreRsStatus::InvalidState,
+            Error::NotInprocessing => MaxPreRsStatus::NotInprocessing,
+            Error::WeightOverflow | Error::InvalidInput(_) => MaxPreRsStatus::Rejected,
+        }
+    }
+}
+
+/// Builder collecting an instance before the preprocessor is created
+pub struct MaxPreRsBuilder {
+    inprocessing: bool,
+    hards: Vec<Clause>,
+    objs: Vec<(Vec<(Clause, u64)>, isize)>,
+}
+
+/// Statistics of a preprocessor that do not depend on an objective. Variables
+/// are given as their IPASIR index, zero if there is none.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MaxPreRsStats {
+    pub n_objs: usize,
+    pub n_orig_hard_clauses: usize,
+    pub max_orig_var: c_int,
+    pub n_prepro_hard_clauses: usize,
+    pub max_prepro_var: c_int,
+    /// Preprocessing time in seconds
+    pub prepro_time: c_double,
+    /// Reconstruction time in seconds
+    pub reconst_time: c_double,
+}
+
+/// Statistics of a preprocessor for one objective
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaxPreRsObjStats {
+    pub n_orig_soft_clauses: usize,
+    pub orig_weight: u64,
+    pub n_prepro_soft_clauses: usize,
+    pub removed_weight: u64,
+}
+
+/// Callback receiving a clause as `len` literals, and the weight of soft
+/// clauses (zero for hard clauses)
+pub type MaxPreRsClauseCallback =
+    Option<unsafe extern "C" fn(data: *mut c_void, lits: *const c_int, len: usize, weight: u64)>;
+
+/// Converts a clause from the C API
+///
+/// # Safety
+///
+/// `lits` must point to `len` readable integers, or `len` must be zero
+unsafe fn read_clause(lits: *const c_int, len: usize) -> Result<Clause, MaxPreRsStatus> {
+    if len == 0 {
+        return Ok(Clause::new());
+    }
+    if lits.is_null() {
+        return Err(MaxPreRsStatus::NullPointer);
+    }
+    core::slice::from_raw_parts(lits, len)
+        .iter()
+        .map(|&l| Lit::from_ipasir(l).map_err(|_| MaxPreRsStatus::InvalidLit))
+        .collect()
+}
+
+/// Passes clauses to a callback
+///
+/// # Safety
+///
+/// `callback` must be safe to call with `data`
+unsafe fn write_clauses<'a, I: IntoIterator<Item = (&'a Clause, u64)>>(
+    clauses: I,
+    callback: MaxPreRsClauseCallback,
+    data: *mut c_void,
+) -> MaxPreRsStatus {
+    let Some(callback) = callback else {
+        return MaxPreRsStatus::NullPointer;
+    };
+    let mut buf = Vec::new();
+    for (cl, w) in clauses {
+        buf.clear();
+        buf.extend(cl.iter().map(|l| l.to_ipasir()));
+        callback(data, buf.as_ptr(), buf.len(), w);
+    }
+    MaxPreRsStatus::Ok
+}
+
+/// Runs the body of a C API function, returning `on_panic` instead of
+/// unwinding into the caller if it panics
+fn catch<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+fn ipasir_var(var: Option<Var>) -> c_int {
+    var.map_or(0, Var::to_ipasir)
+}
+
+/// Returns the signature of the preprocessor as a null-terminated string
+#[no_mangle]
+pub extern "C" fn maxpre_rs_signature() -> *const c_char {
+    catch(core::ptr::null(), || {
+        static SIGNATURE: std::sync::OnceLock<std::ffi::CString> = std::sync::OnceLock::new();
+        SIGNATURE
+            .get_or_init(|| {
+                std::ffi::CString::new(<MaxPre as PreproInit>::signature()).unwrap_or_default()
+            })
+            .as_ptr()
+    })
+}
+
+/// Creates a builder for an empty instance without objectives. The builder
+/// must be released with [`maxpre_rs_builder_build`] or
+/// [`maxpre_rs_builder_free`].
+#[no_mangle]
+pub extern "C" fn maxpre_rs_builder_new(inprocessing: bool) -> *mut MaxPreRsBuilder {
+    catch(core::ptr::null_mut(), || {
+        Box::into_raw(Box::new(MaxPreRsBuilder {
+            inprocessing,
+            hards: Vec::new(),
+            objs: Vec::new(),
+        }))
+    })
+}
+
+/// Releases a builder without creating a preprocessor
+///
+/// # Safety
+///
+/// `builder` must be null or returned by [`maxpre_rs_builder_new`] and not
+/// released yet
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_builder_free(builder: *mut MaxPreRsBuilder) {
+    catch((), || {
+        if !builder.is_null() {
+            drop(Box::from_raw(builder));
+        }
+    })
+}
+
+/// Adds a hard clause of `len` literals
+///
+/// # Safety
+///
+/// `builder` must be a valid builder and `lits` must point to `len` integers
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_builder_add_hard(
+    builder: *mut MaxPreRsBuilder,
+    lits: *const c_int,
+    len: usize,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(builder) = builder.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        match read_clause(lits, len) {
+            Ok(cl) => {
+                builder.hards.push(cl);
+                MaxPreRsStatus::Ok
+            }
+            Err(status) => status,
+        }
+    })
+}
+
+/// Adds a soft clause of `len` literals to objective `obj_idx`. Objectives up
+/// to `obj_idx` that do not exist yet are created.
+///
+/// # Safety
+///
+/// `builder` must be a valid builder and `lits` must point to `len` integers
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_builder_add_soft(
+    builder: *mut MaxPreRsBuilder,
+    obj_idx: usize,
+    lits: *const c_int,
+    len: usize,
+    weight: u64,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(builder) = builder.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let cl = match read_clause(lits, len) {
+            Ok(cl) => cl,
+            Err(status) => return status,
+        };
+        if builder.objs.len() <= obj_idx {
+            builder.objs.resize_with(obj_idx + 1, Default::default);
+        }
+        builder.objs[obj_idx].0.push((cl, weight));
+        MaxPreRsStatus::Ok
+    })
+}
+
+/// Sets the offset of objective `obj_idx`, creating objectives up to it that
+/// do not exist yet
+///
+/// # Safety
+///
+/// `builder` must be a valid builder
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_builder_set_offset(
+    builder: *mut MaxPreRsBuilder,
+    obj_idx: usize,
+    offset: isize,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(builder) = builder.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        if builder.objs.len() <= obj_idx {
+            builder.objs.resize_with(obj_idx + 1, Default::default);
+        }
+        builder.objs[obj_idx].1 = offset;
+        MaxPreRsStatus::Ok
+    })
+}
+
+/// Creates the preprocessor and releases the builder. Returns null if the
+/// instance is rejected, e.g., if the weights or variables exceed the range
+/// of MaxPre. The status is written to `status`, if it is not null. The
+/// preprocessor must be released with [`maxpre_rs_free`].
+///
+/// # Safety
+///
+/// `builder` must be null or a valid builder, which is invalid afterwards,
+/// and `status` must be null or writable
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_builder_build(
+    builder: *mut MaxPreRsBuilder,
+    status: *mut MaxPreRsStatus,
+) -> *mut MaxPre {
+    let (pp, st) = catch((core::ptr::null_mut(), MaxPreRsStatus::Panicked), || {
+        if builder.is_null() {
+            return (core::ptr::null_mut(), MaxPreRsStatus::NullPointer);
+        }
+        let builder = Box::from_raw(builder);
+        let mut pp = MaxPreBuilder::new()
+            .inprocessing(builder.inprocessing)
+            .hards(builder.hards);
+        for (softs, offset) in builder.objs {
+            pp = pp.objective_with_offset(softs, offset);
+        }
+        match pp.build() {
+            Ok(pp) => (Box::into_raw(Box::new(pp)), MaxPreRsStatus::Ok),
+            Err(err) => (core::ptr::null_mut(), err.into()),
+        }
+    });
+    if !status.is_null() {
+        status.write(st);
+    }
+    pp
+}
+
+/// Releases a preprocessor
+///
+/// # Safety
+///
+/// `pp` must be null or returned by [`maxpre_rs_builder_build`] and not
+/// released yet
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_free(pp: *mut MaxPre) {
+    catch((), || {
+        if !pp.is_null() {
+            drop(Box::from_raw(pp));
+        }
+    })
+}
+
+/// Preprocesses with the techniques given as a null-terminated string, which
+/// are checked before they are passed to MaxPre. Without inprocessing, an
+/// instance can only be preprocessed once.
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor and `techniques` a null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_preprocess(
+    pp: *mut MaxPre,
+    techniques: *const c_char,
+    log_level: c_int,
+    time_limit: c_double,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(pp) = pp.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        if techniques.is_null() {
+            return MaxPreRsStatus::NullPointer;
+        }
+        let Ok(techniques) = CStr::from_ptr(techniques).to_str() else {
+            return MaxPreRsStatus::InvalidString;
+        };
+        let Ok(techniques) = techniques.parse::<crate::Techniques>() else {
+            return MaxPreRsStatus::Rejected;
+        };
+        match pp.try_preprocess(&techniques, log_level, time_limit) {
+            Ok(()) => MaxPreRsStatus::Ok,
+            Err(err) => err.into(),
+        }
+    })
+}
+
+/// Writes the statistics of the preprocessor to `stats`
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor and `stats` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_stats(
+    pp: *const MaxPre,
+    stats: *mut MaxPreRsStats,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let (Some(pp), false) = (pp.as_ref(), stats.is_null()) else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let st = pp.stats();
+        stats.write(MaxPreRsStats {
+            n_objs: st.n_objs,
+            n_orig_hard_clauses: st.n_orig_hard_clauses,
+            max_orig_var: ipasir_var(st.max_orig_var),
+            n_prepro_hard_clauses: st.n_prepro_hard_clauses,
+            max_prepro_var: ipasir_var(st.max_prepro_var),
+            prepro_time: st.prepro_time.as_secs_f64(),
+            reconst_time: st.reconst_time.as_secs_f64(),
+        });
+        MaxPreRsStatus::Ok
+    })
+}
+
+/// Writes the statistics of objective `obj_idx` to `stats`
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor and `stats` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_obj_stats(
+    pp: *const MaxPre,
+    obj_idx: usize,
+    stats: *mut MaxPreRsObjStats,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let (Some(pp), false) = (pp.as_ref(), stats.is_null()) else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let st = pp.stats();
+        if obj_idx >= st.n_objs {
+            return MaxPreRsStatus::Rejected;
+        }
+        let get = |v: &[usize]| v.get(obj_idx).copied().unwrap_or(0);
+        stats.write(MaxPreRsObjStats {
+            n_orig_soft_clauses: get(&st.n_orig_soft_clauses),
+            orig_weight: get(&st.orig_weight) as u64,
+            n_prepro_soft_clauses: get(&st.n_prepro_soft_clauses),
+            removed_weight: get(&st.removed_weight) as u64,
+        });
+        MaxPreRsStatus::Ok
+    })
+}
+
+/// Passes the preprocessed hard clauses to `callback`. The instance must be
+/// preprocessed first.
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor and `callback` must be safe to call with
+/// `data`
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_prepro_hards(
+    pp: *mut MaxPre,
+    callback: MaxPreRsClauseCallback,
+    data: *mut c_void,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(pp) = pp.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let (hards, _) = match pp.try_prepro_instance() {
+            Ok(inst) => inst,
+            Err(err) => return err.into(),
+        };
+        write_clauses(hards.iter().map(|cl| (cl, 0)), callback, data)
+    })
+}
+
+/// Passes the preprocessed soft clauses of objective `obj_idx` to `callback`
+/// and writes the offset to `offset`, if it is not null
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor, `offset` must be null or writable and
+/// `callback` must be safe to call with `data`
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_prepro_softs(
+    pp: *mut MaxPre,
+    obj_idx: usize,
+    callback: MaxPreRsClauseCallback,
+    data: *mut c_void,
+    offset: *mut isize,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(pp) = pp.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let (_, objs) = match pp.try_prepro_instance() {
+            Ok(inst) => inst,
+            Err(err) => return err.into(),
+        };
+        let Some((softs, ofs)) = objs.get(obj_idx) else {
+            return MaxPreRsStatus::Rejected;
+        };
+        if !offset.is_null() {
+            offset.write(*ofs);
+        }
+        write_clauses(softs.iter().map(|(cl, w)| (cl, *w as u64)), callback, data)
+    })
+}
+
+/// Reconstructs a solution to the original instance from the `len` literals
+/// of a solution to the preprocessed instance. The reconstructed solution is
+/// written to `out` as one literal per original variable, so `out_len` must
+/// be at least the maximum original variable.
+///
+/// # Safety
+///
+/// `pp` must be a valid preprocessor, `sol` must point to `len` integers and
+/// `out` to `out_len` writable integers
+#[no_mangle]
+pub unsafe extern "C" fn maxpre_rs_reconstruct(
+    pp: *mut MaxPre,
+    sol: *const c_int,
+    len: usize,
+    out: *mut c_int,
+    out_len: usize,
+) -> MaxPreRsStatus {
+    catch(MaxPreRsStatus::Panicked, || {
+        let Some(pp) = pp.as_mut() else {
+            return MaxPreRsStatus::NullPointer;
+        };
+        let sol: Assignment = match read_clause(sol, len) {
+            Ok(cl) => cl.into_iter().collect(),
+            Err(status) => return status,
+        };
+        let n_vars = pp.max_orig_var().idx() + 1;
+        if out_len < n_vars {
+            return MaxPreRsStatus::BufferTooSmall;
+        }
+        if out.is_null() {
+            return MaxPreRsStatus::NullPointer;
+        }
+        let sol = pp.reconstruct(sol);
+        let out = core::slice::from_raw_parts_mut(out, n_vars);
+        for (idx, o) in out.iter_mut().enumerate() {
+            let var = Var::new(idx as u32);
+            *o = var.lit(sol.var_value(var) == TernaryVal::False).to_ipasir();
+        }
+        MaxPreRsStatus::Ok
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::{c_int, c_void};
+
+    use super::{
+        catch, maxpre_rs_builder_add_hard, maxpre_rs_builder_add_soft, maxpre_rs_builder_build,
+        maxpre_rs_builder_new, maxpre_rs_free, maxpre_rs_obj_stats, maxpre_rs_prepro_softs,
+        maxpre_rs_preprocess, maxpre_rs_reconstruct, maxpre_rs_stats, MaxPreRsObjStats,
+        MaxPreRsStats, MaxPreRsStatus,
+    };
+
+    unsafe extern "C" fn count(data: *mut c_void, _: *const c_int, _: usize, weight: u64) {
+        *data.cast::<u64>() += weight;
+    }
+
+    #[test]
+    fn c_api() {
+        unsafe {
+            let builder = maxpre_rs_builder_new(false);
+            assert_eq!(
+                maxpre_rs_builder_add_hard(builder, [1, 2].as_ptr(), 2),
+                MaxPreRsStatus::Ok
+            );
+            assert_eq!(
+                maxpre_rs_builder_add_hard(builder, [1, 0].as_ptr(), 2),
+                MaxPreRsStatus::InvalidLit
+            );
+            for (l, w) in [(-1, 2), (-2, 1)] {
+                assert_eq!(
+                    maxpre_rs_builder_add_soft(builder, 0, [l].as_ptr(), 1, w),
+                    MaxPreRsStatus::Ok
+                );
+            }
+            let mut status = MaxPreRsStatus::Panicked;
+            let pp = maxpre_rs_builder_build(builder, &mut status);
+            assert!(!pp.is_null());
+            assert_eq!(status, MaxPreRsStatus::Ok);
+            assert_eq!(
+                maxpre_rs_preprocess(pp, c"[bx]".as_ptr(), 0, 1e9),
+                MaxPreRsStatus::Rejected
+            );
+            assert_eq!(
+                maxpre_rs_preprocess(pp, c"[bu]".as_ptr(), 0, 1e9),
+                MaxPreRsStatus::Ok
+            );
+            let mut stats = MaxPreRsStats::default();
+            assert_eq!(maxpre_rs_stats(pp, &mut stats), MaxPreRsStatus::Ok);
+            assert_eq!(stats.n_objs, 1);
+            assert_eq!(stats.max_orig_var, 2);
+            let mut obj_stats = MaxPreRsObjStats::default();
+            assert_eq!(
+                maxpre_rs_obj_stats(pp, 0, &mut obj_stats),
+                MaxPreRsStatus::Ok
+            );
+            assert_eq!(obj_stats.orig_weight, 3);
+            assert_eq!(
+                maxpre_rs_obj_stats(pp, 1, &mut obj_stats),
+                MaxPreRsStatus::Rejected
+            );
+            let mut weight = 0u64;
+            let mut offset = 0;
+            assert_eq!(
+                maxpre_rs_prepro_softs(pp, 0, Some(count), (&raw mut weight).cast(), &mut offset),
+                MaxPreRsStatus::Ok
+            );
+            assert_eq!(weight + offset as u64, 3);
+            let mut out = [0; 2];
+            assert_eq!(
+                maxpre_rs_reconstruct(pp, core::ptr::null(), 0, out.as_mut_ptr(), 1),
+                MaxPreRsStatus::BufferTooSmall
+            );
+            maxpre_rs_free(pp);
+        }
+    }
+
+    #[test]
+    fn build_status() {
+        unsafe {
+            let mut status = MaxPreRsStatus::Ok;
+            let pp = maxpre_rs_builder_build(core::ptr::null_mut(), &mut status);
+            assert!(pp.is_null());
+            assert_eq!(status, MaxPreRsStatus::NullPointer);
+            let builder = maxpre_rs_builder_new(false);
+            assert_eq!(
+                maxpre_rs_builder_add_soft(builder, 0, core::ptr::null(), 0, 1),
+                MaxPreRsStatus::Ok
+            );
+            let pp = maxpre_rs_builder_build(builder, &mut status);
+            assert!(pp.is_null());
+            assert_eq!(status, MaxPreRsStatus::Rejected);
+        }
+    }
+
+    #[test]
+    fn catch_panics() {
+        assert_eq!(
+            catch(MaxPreRsStatus::Panicked, || panic!("caught")),
+            MaxPreRsStatus::Panicked
+        );
+        assert_eq!(
+            catch(MaxPreRsStatus::Panicked, || MaxPreRsStatus::Ok),
+            MaxPreRsStatus::Ok
+        );
+    }
+}
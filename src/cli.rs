@@ -0,0 +1,95 @@
+//! # Command Line Arguments
+//!
+//! [`clap`] arguments for configuring preprocessing, so that binaries using
+//! this crate share a common set of preprocessing flags.
+
+use core::ffi::c_int;
+
+use crate::{Options, PreproClauses, SkipTechnique, Techniques};
+
+/// Arguments for configuring preprocessing. Flatten this into the arguments of
+/// a binary with `#[command(flatten)]`.
+#[derive(Clone, Debug, PartialEq, clap::Args)]
+pub struct PreproArgs {
+    /// The techniques to preprocess with
+    #[arg(long, default_value_t = Techniques::DEFAULT)]
+    pub techniques: Techniques,
+    /// The log level of MaxPre
+    #[arg(long = "verb", value_name = "LEVEL", default_value_t = 0)]
+    pub log_level: c_int,
+    /// The time limit for preprocessing in seconds
+    #[arg(long = "timelimit", value_name = "SECONDS", default_value_t = 1e9)]
+    pub time_limit: f64,
+    #[command(flatten)]
+    pub options: Options,
+}
+
+impl Default for PreproArgs {
+    fn default() -> Self {
+        PreproArgs {
+            techniques: Techniques::DEFAULT,
+            log_level: 0,
+            time_limit: 1e9,
+            options: Options::default(),
+        }
+    }
+}
+
+impl PreproArgs {
+    /// Sets the options on a preprocessor and preprocesses with the
+    /// techniques and limits
+    pub fn preprocess<PP: PreproClauses + ?Sized>(&self, prepro: &mut PP) {
+        prepro.set_options(self.options.clone());
+        prepro.preprocess(&self.techniques, self.log_level, self.time_limit);
+    }
+}
+
+/// Parses the argument of `--skiptechnique`
+pub(crate) fn parse_skip_technique(arg: &str) -> Result<SkipTechnique, String> {
+    let samples: u32 = arg.parse().map_err(|err| format!("{err}"))?;
+    Ok(if samples == 0 {
+        SkipTechnique::Never
+    } else {
+        SkipTechnique::Sample(samples)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::PreproArgs;
+    use crate::{SkipTechnique, Techniques};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        prepro: PreproArgs,
+    }
+
+    #[test]
+    fn parse_args() {
+        let cli = Cli::parse_from(["test"]);
+        assert_eq!(cli.prepro, PreproArgs::default());
+        let cli = Cli::parse_from([
+            "test",
+            "--techniques",
+            "[bu]#[bus]",
+            "--timelimit",
+            "10",
+            "--bvegate=1",
+            "--skiptechnique=20",
+            "--BBTMS_maxVars",
+            "10",
+        ]);
+        assert_eq!(cli.prepro.techniques, Techniques::LIGHT);
+        assert_eq!(cli.prepro.time_limit, 10.);
+        assert_eq!(cli.prepro.options.bve.gate_extraction, Some(true));
+        assert_eq!(
+            cli.prepro.options.skip_technique,
+            Some(SkipTechnique::Sample(20))
+        );
+        assert_eq!(cli.prepro.options.bbtms.max_vars, Some(10));
+        assert!(Cli::try_parse_from(["test", "--techniques", "bx"]).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! # Preprocessing Variable-Disjoint Components Separately
+//!
+//! Splits an instance into components that do not share any variables and
+//! preprocesses each of them with its own preprocessor.
+
+use core::ffi::c_int;
+
+use rustsat::{
+    instances::Cnf,
+    types::{Assignment, Clause, Lit, Var},
+};
+
+use crate::{PreproInit, SoftClauses};
+
+/// The preprocessor used for the components if none is specified
+#[cfg(feature = "cxx")]
+type DefaultPrepro = crate::MaxPre;
+/// The preprocessor used for the components if none is specified
+#[cfg(not(feature = "cxx"))]
+type DefaultPrepro = crate::NativePrepro;
+
+/// A preprocessor that preprocesses each variable-disjoint component of an
+/// instance in its own preprocessor of type `PP`. The preprocessed
+/// components are merged into a single preprocessed instance and solutions of
+/// it are reconstructed component by component.
+///
+/// Each component is preprocessed over its own dense variable range, the
+/// preprocessed instance places the components in consecutive variable
+/// ranges. Reconstruction therefore relies on the variable ranges determined
+/// by the last call to [`ComponentPrepro::prepro_instance`].
+pub struct ComponentPrepro<PP = DefaultPrepro> {
+    /// The components and their preprocessors
+    comps: Vec<Component<PP>>,
+    /// Offsets of the objectives
+    offsets: Vec<isize>,
+    /// For each component, the first variable of its range in the
+    /// preprocessed instance
+    prepro_ranges: Vec<u32>,
+}
+
+/// A single component
+struct Component<PP> {
+    /// The preprocessor for the component
+    pp: PP,
+    /// The original variable of each variable in the component
+    vars: Vec<Var>,
+}
+
+impl<PP: PreproInit> ComponentPrepro<PP> {
+    /// Initializes the preprocessors for all components of an instance with
+    /// hard clauses and optional multiple sets of soft clauses. Clauses
+    /// without variables are put into the first component.
+    pub fn new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let (hards, softs) = crate::collect_instance(hards, softs);
+        let offsets = softs.iter().map(|(_, ofs)| *ofs).collect();
+        // Union-find over the variables
+        let max_var = hards
+            .iter()
+            .chain(softs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+            .flat_map(|cl| cl.iter().map(|l| l.var().idx()))
+            .max();
+        let mut parents: Vec<usize> = (0..max_var.map_or(0, |v| v + 1)).collect();
+        let mut used = vec![false; parents.len()];
+        for cl in hards
+            .iter()
+            .chain(softs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+        {
+            let mut lits = cl.iter();
+            let Some(first) = lits.next() else {
+                continue;
+            };
+            used[first.var().idx()] = true;
+            let root = find(&mut parents, first.var().idx());
+            for l in lits {
+                used[l.var().idx()] = true;
+                let other = find(&mut parents, l.var().idx());
+                parents[other] = root;
+            }
+        }
+        // Assign components and local variable indices
+        let mut comp_idx = vec![usize::MAX; parents.len()];
+        let mut local = vec![0; parents.len()];
+        let mut comp_vars: Vec<Vec<Var>> = vec![];
+        for idx in 0..parents.len() {
+            if !used[idx] {
+                continue;
+            }
+            let root = find(&mut parents, idx);
+            if comp_idx[root] == usize::MAX {
+                comp_idx[root] = comp_vars.len();
+                comp_vars.push(vec![]);
+            }
+            comp_idx[idx] = comp_idx[root];
+            local[idx] = comp_vars[comp_idx[idx]].len() as u32;
+            comp_vars[comp_idx[idx]].push(Var::new(idx as u32));
+        }
+        if comp_vars.is_empty() {
+            comp_vars.push(vec![]);
+        }
+        // Split the clauses
+        let to_local = |cl: &Clause| -> Clause {
+            cl.iter()
+                .map(|l| Lit::new(local[l.var().idx()], l.is_neg()))
+                .collect()
+        };
+        let comp_of = |cl: &Clause| cl.iter().next().map_or(0, |l| comp_idx[l.var().idx()]);
+        let mut comp_hards = vec![Cnf::new(); comp_vars.len()];
+        for cl in hards.iter() {
+            comp_hards[comp_of(cl)].add_clause(to_local(cl));
+        }
+        let mut comp_softs: Vec<Vec<(SoftClauses, isize)>> =
+            vec![vec![(vec![], 0); softs.len()]; comp_vars.len()];
+        for (obj_idx, (softs, _)) in softs.iter().enumerate() {
+            for (cl, w) in softs {
+                comp_softs[comp_of(cl)][obj_idx].0.push((to_local(cl), *w));
+            }
+        }
+        let comps = comp_vars
+            .into_iter()
+            .zip(comp_hards.into_iter().zip(comp_softs))
+            .map(|(vars, (hards, softs))| Component {
+                pp: PP::new(hards, crate::weighted(softs), inprocessing),
+                vars,
+            })
+            .collect();
+        Self {
+            comps,
+            offsets,
+            prepro_ranges: vec![],
+        }
+    }
+
+    /// Gets the number of components
+    pub fn n_components(&self) -> usize {
+        self.comps.len()
+    }
+
+    /// Gets the preprocessors of the components
+    pub fn components(&self) -> impl Iterator<Item = &PP> {
+        self.comps.iter().map(|c| &c.pp)
+    }
+
+    /// Preprocesses all components one after another. The techniques and
+    /// time limit apply to each component separately.
+    pub fn preprocess(&mut self, techniques: &str, log_level: c_int, time_limit: f64) {
+        self.comps
+            .iter_mut()
+            .for_each(|c| c.pp.preprocess(techniques, log_level, time_limit));
+    }
+
+    /// Gets the merged preprocessed instance of all components
+    pub fn prepro_instance(&mut self) -> (Cnf, Vec<(SoftClauses, isize)>) {
+        let mut hards = Cnf::new();
+        let mut objs: Vec<(SoftClauses, isize)> =
+            self.offsets.iter().map(|ofs| (vec![], *ofs)).collect();
+        self.prepro_ranges.clear();
+        let mut next = 0;
+        for comp in &mut self.comps {
+            self.prepro_ranges.push(next);
+            let (comp_hards, comp_objs) = comp.pp.prepro_instance();
+            let shift = |cl: &Clause| -> Clause { cl.iter().map(|l| *l + next).collect() };
+            let mut max_var = None;
+            for cl in comp_hards.iter() {
+                max_var = max_var.max(cl.iter().map(|l| l.var()).max());
+                hards.add_clause(shift(cl));
+            }
+            for (obj_idx, (softs, ofs)) in comp_objs.into_iter().enumerate() {
+                objs[obj_idx].1 += ofs;
+                for (cl, w) in softs {
+                    max_var = max_var.max(cl.iter().map(|l| l.var()).max());
+                    objs[obj_idx].0.push((shift(&cl), w));
+                }
+            }
+            next += max_var.map_or(0, |v| v.idx32() + 1);
+        }
+        (hards, objs)
+    }
+
+    /// Reconstructs a solution of the preprocessed instance returned by the
+    /// last call to [`ComponentPrepro::prepro_instance`]
+    pub fn reconstruct(&mut self, sol: Assignment) -> Assignment {
+        debug_assert_eq!(self.prepro_ranges.len(), self.comps.len());
+        let mut comp_sols: Vec<Vec<Lit>> = vec![vec![]; self.comps.len()];
+        for lit in sol {
+            let comp = self
+                .prepro_ranges
+                .partition_point(|&start| start <= lit.var().idx32())
+                .saturating_sub(1);
+            comp_sols[comp].push(Lit::new(
+                lit.var().idx32() - self.prepro_ranges[comp],
+                lit.is_neg(),
+            ));
+        }
+        let mut rec = vec![];
+        for (comp, sol) in self.comps.iter_mut().zip(comp_sols) {
+            let comp_rec = comp.pp.reconstruct(sol.into_iter().collect());
+            rec.extend(comp_rec.into_iter().filter_map(|l| {
+                comp.vars
+                    .get(l.var().idx())
+                    .map(|v| Lit::new(v.idx32(), l.is_neg()))
+            }));
+        }
+        rec.into_iter().collect()
+    }
+}
+
+/// Finds the root of an element in a union-find forest, halving the path
+fn find(parents: &mut [usize], mut idx: usize) -> usize {
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+    idx
+}
+
+#[cfg(feature = "rayon")]
+impl<PP: PreproInit + Send> ComponentPrepro<PP> {
+    /// Preprocesses all components in parallel, see
+    /// [`ComponentPrepro::preprocess`]
+    pub fn preprocess_parallel(&mut self, techniques: &str, log_level: c_int, time_limit: f64) {
+        use rayon::prelude::*;
+
+        self.comps
+            .par_iter_mut()
+            .for_each(|c| c.pp.preprocess(techniques, log_level, time_limit));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::Cnf,
+        lit,
+        types::{Assignment, Clause, TernaryVal},
+    };
+
+    use super::ComponentPrepro;
+
+    /// Soft clauses as passed to [`ComponentPrepro::new`]
+    type Softs = Vec<(Clause, u64)>;
+
+    fn instance() -> (Cnf, Vec<(Softs, isize)>) {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![5], lit![7]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![1]], 1),
+            (clause![!lit![5]], 1),
+            (clause![!lit![7]], 3),
+        ];
+        (cnf, vec![(softs, 0)])
+    }
+
+    #[test]
+    fn components() {
+        let (cnf, softs) = instance();
+        let mut pp: ComponentPrepro = ComponentPrepro::new(cnf.clone(), softs.clone(), false);
+        assert_eq!(pp.n_components(), 2);
+        pp.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let (hards, objs) = pp.prepro_instance();
+        // Solve preprocessed instance by brute force
+        let max_var = hards
+            .iter()
+            .chain(objs[0].0.iter().map(|(cl, _)| cl))
+            .flat_map(|cl| cl.iter().map(|l| l.var().idx32()))
+            .max()
+            .unwrap_or(0);
+        let sol = (0..1u32 << (max_var + 1))
+            .map(|bits| -> Assignment {
+                (0..=max_var)
+                    .map(|v| rustsat::types::Var::new(v).lit(bits & (1 << v) == 0))
+                    .collect()
+            })
+            .filter(|sol| hards.iter().all(|cl| cl.evaluate(sol) == TernaryVal::True))
+            .min_by_key(|sol| {
+                objs[0]
+                    .0
+                    .iter()
+                    .filter(|(cl, _)| cl.evaluate(sol) != TernaryVal::True)
+                    .map(|(_, w)| w)
+                    .sum::<usize>()
+            })
+            .unwrap();
+        let rec = pp.reconstruct(sol);
+        assert!(cnf.iter().all(|cl| cl.evaluate(&rec) == TernaryVal::True));
+        let cost: u64 = softs[0]
+            .0
+            .iter()
+            .filter(|(cl, _)| cl.evaluate(&rec) != TernaryVal::True)
+            .map(|(_, w)| w)
+            .sum();
+        assert_eq!(cost, 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        let (cnf, softs) = instance();
+        let mut seq: ComponentPrepro = ComponentPrepro::new(cnf.clone(), softs.clone(), false);
+        seq.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let mut par: ComponentPrepro = ComponentPrepro::new(cnf, softs, false);
+        par.preprocess_parallel("[bu]#[buvsrgc]", 0, 1e9);
+        let (seq_hards, seq_objs) = seq.prepro_instance();
+        let (par_hards, par_objs) = par.prepro_instance();
+        assert_eq!(seq_hards.len(), par_hards.len());
+        assert!(seq_hards.iter().zip(par_hards.iter()).all(|(a, b)| a == b));
+        assert_eq!(seq_objs, par_objs);
+    }
+}
@@ -0,0 +1,180 @@
+//! # Instance Features
+//!
+//! Standard features of MaxSAT instances, as used by portfolio solvers and
+//! algorithm selection, computed on the preprocessed instance with
+//! [`PreproClauses::features`](crate::PreproClauses::features).
+
+use rustsat::{
+    instances::Cnf,
+    types::{Clause, Var},
+};
+
+use crate::SoftClauses;
+
+/// Summary statistics of a set of values
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FeatureSummary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl FeatureSummary {
+    /// Summarizes values, all statistics are zero if there are none
+    fn of<I: IntoIterator<Item = f64>>(vals: I) -> Self {
+        let vals: Vec<f64> = vals.into_iter().collect();
+        if vals.is_empty() {
+            return FeatureSummary::default();
+        }
+        let n = vals.len() as f64;
+        let mean = vals.iter().sum::<f64>() / n;
+        let var = vals.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n;
+        FeatureSummary {
+            min: vals.iter().copied().fold(f64::INFINITY, f64::min),
+            max: vals.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            std_dev: var.sqrt(),
+        }
+    }
+}
+
+/// Features of a MaxSAT instance. Ratios and fractions of an empty instance
+/// are zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Features {
+    pub n_objs: usize,
+    /// The number of distinct variables in hard or soft clauses
+    pub n_vars: usize,
+    pub n_hard_clauses: usize,
+    /// The number of soft clauses, over all objectives
+    pub n_soft_clauses: usize,
+    /// Clauses per variable
+    pub clause_var_ratio: f64,
+    /// The fraction of clauses that are soft
+    pub soft_fraction: f64,
+    /// The fraction of variables that occur in soft clauses
+    pub label_density: f64,
+    /// The fraction of clauses with a single literal
+    pub unit_fraction: f64,
+    /// The fraction of clauses with two literals
+    pub binary_fraction: f64,
+    /// The fraction of clauses with at most one positive literal
+    pub horn_fraction: f64,
+    /// The lengths of all clauses
+    pub clause_len: FeatureSummary,
+    /// The number of clauses that each variable occurs in
+    pub var_degree: FeatureSummary,
+    /// The weights of the soft clauses, over all objectives
+    pub weight: FeatureSummary,
+    /// The number of distinct weights, over all objectives
+    pub n_distinct_weights: usize,
+}
+
+impl Features {
+    /// Computes the features of an instance
+    pub(crate) fn of(hards: &Cnf, softs: &[(SoftClauses, isize)]) -> Self {
+        let clauses: Vec<&Clause> = hards
+            .iter()
+            .chain(softs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+            .collect();
+        let mut degrees: Vec<usize> = vec![];
+        let mut in_soft: Vec<bool> = vec![];
+        let mut count = |var: Var, soft: bool| {
+            if degrees.len() <= var.idx() {
+                degrees.resize(var.idx() + 1, 0);
+                in_soft.resize(var.idx() + 1, false);
+            }
+            degrees[var.idx()] += 1;
+            in_soft[var.idx()] |= soft;
+        };
+        for (idx, cl) in clauses.iter().enumerate() {
+            let mut vars: Vec<Var> = cl.iter().map(|l| l.var()).collect();
+            vars.sort_unstable();
+            vars.dedup();
+            for var in vars {
+                count(var, idx >= hards.len());
+            }
+        }
+        let n_vars = degrees.iter().filter(|&&d| d > 0).count();
+        let n_clauses = clauses.len();
+        let fraction = |n: usize, of: usize| if of == 0 { 0. } else { n as f64 / of as f64 };
+        let share = |pred: &dyn Fn(&Clause) -> bool| {
+            fraction(clauses.iter().filter(|cl| pred(cl)).count(), n_clauses)
+        };
+        let mut weights: Vec<usize> = softs
+            .iter()
+            .flat_map(|(s, _)| s.iter().map(|(_, w)| *w))
+            .collect();
+        let weight = FeatureSummary::of(weights.iter().map(|&w| w as f64));
+        weights.sort_unstable();
+        weights.dedup();
+        Features {
+            n_objs: softs.len(),
+            n_vars,
+            n_hard_clauses: hards.len(),
+            n_soft_clauses: n_clauses - hards.len(),
+            clause_var_ratio: fraction(n_clauses, n_vars),
+            soft_fraction: fraction(n_clauses - hards.len(), n_clauses),
+            label_density: fraction(in_soft.iter().filter(|&&s| s).count(), n_vars),
+            unit_fraction: share(&|cl| cl.len() == 1),
+            binary_fraction: share(&|cl| cl.len() == 2),
+            horn_fraction: share(&|cl| cl.iter().filter(|l| l.is_pos()).count() <= 1),
+            clause_len: FeatureSummary::of(clauses.iter().map(|cl| cl.len() as f64)),
+            var_degree: FeatureSummary::of(degrees.iter().filter(|&&d| d > 0).map(|&d| d as f64)),
+            weight,
+            n_distinct_weights: weights.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{clause, instances::Cnf, lit};
+
+    use super::{FeatureSummary, Features};
+
+    #[test]
+    fn summary() {
+        assert_eq!(FeatureSummary::of([]), FeatureSummary::default());
+        let summary = FeatureSummary::of([1., 3.]);
+        assert_eq!(
+            summary,
+            FeatureSummary {
+                min: 1.,
+                max: 3.,
+                mean: 2.,
+                std_dev: 1.,
+            }
+        );
+    }
+
+    #[test]
+    fn features() {
+        assert_eq!(Features::of(&Cnf::new(), &[]), Features::default());
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_ternary(!lit![0], !lit![1], lit![2]);
+        let softs = vec![(clause![!lit![2]], 2), (clause![!lit![3]], 2)];
+        let features = Features::of(&cnf, &[(softs, 0)]);
+        assert_eq!(features.n_objs, 1);
+        assert_eq!(features.n_vars, 4);
+        assert_eq!(features.n_hard_clauses, 2);
+        assert_eq!(features.n_soft_clauses, 2);
+        assert_eq!(features.clause_var_ratio, 1.);
+        assert_eq!(features.soft_fraction, 0.5);
+        assert_eq!(features.label_density, 0.5);
+        assert_eq!(features.unit_fraction, 0.5);
+        assert_eq!(features.binary_fraction, 0.25);
+        assert_eq!(features.horn_fraction, 0.75);
+        assert_eq!(features.clause_len.max, 3.);
+        assert_eq!(features.clause_len.mean, 1.75);
+        assert_eq!(features.var_degree.min, 1.);
+        assert_eq!(features.var_degree.max, 2.);
+        assert_eq!(features.weight.mean, 2.);
+        assert_eq!(features.weight.std_dev, 0.);
+        assert_eq!(features.n_distinct_weights, 1);
+    }
+}
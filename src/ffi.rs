@@ -1,4 +1,9 @@
 //! Reproduction of `cpreprocessorinterface.h`
+//!
+//! This module is only public with the `raw-ffi` feature. It is intended for
+//! calling functions of the C API that are not (yet) covered by this crate,
+//! together with [`MaxPre::into_raw`](crate::MaxPre::into_raw) and
+//! [`MaxPre::from_raw`](crate::MaxPre::from_raw).
 
 #![allow(dead_code)]
 
@@ -7,6 +12,9 @@ use core::ffi::{c_char, c_int, c_uint};
 pub const TRUE: c_char = 1;
 pub const FALSE: c_char = 0;
 
+/// The instance and solution format MaxPre uses by default (`global.hpp`)
+pub const INPUT_FORMAT_WPMS22: c_int = 4;
+
 #[inline]
 pub fn map_bool(b: bool) -> c_char {
     if b {
@@ -22,7 +30,8 @@ pub struct CMaxPre {
     _unused: [u8; 0],
 }
 
-#[link(name = "maxpre", kind = "static")]
+// Linking is set up by the build script, since the library might be built
+// from the vendored or from external sources
 extern "C" {
     pub fn cmaxpre_signature() -> *const c_char;
     pub fn cmaxpre_init_start(top_weight: u64, inprocess_mode: c_char) -> *mut CMaxPre;
@@ -68,10 +77,173 @@ extern "C" {
     pub fn cmaxpre_set_model_search_iter_limit(arg1: *mut CMaxPre, limit: c_int);
     pub fn cmaxpre_get_original_variables(arg1: *mut CMaxPre) -> c_int;
     pub fn cmaxpre_get_upper_bound(arg1: *mut CMaxPre) -> u64;
-    pub fn cmaxpre_print_instance_stdout(arg1: *mut CMaxPre);
-    pub fn cmaxpre_print_solution_stdout(arg1: *mut CMaxPre, weight: u64);
+    pub fn cmaxpre_print_instance_stdout(arg1: *mut CMaxPre, instance_format: c_int);
+    pub fn cmaxpre_print_solution_stdout(arg1: *mut CMaxPre, weight: u64, sol_format: c_int);
     pub fn cmaxpre_print_map_stdout(arg1: *mut CMaxPre);
     pub fn cmaxpre_print_technique_log_stdout(arg1: *mut CMaxPre);
     pub fn cmaxpre_print_info_log_stdout(arg1: *mut CMaxPre);
     pub fn cmaxpre_print_preprocessor_stats_stdout(arg1: *mut CMaxPre);
 }
+
+/// Reproduction of `CMaxPreTechniqueStats` from `cppext/cmaxpre_ext.h`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CMaxPreTechniqueStats {
+    pub time: f64,
+    pub time_limit: f64,
+    pub invocations: c_uint,
+    pub removed_clauses: c_int,
+    pub removed_variables: c_int,
+    pub removed_literals: c_int,
+    pub removed_labels: c_int,
+}
+
+/// Reproduction of `CMaxPreOptions` from `cppext/cmaxpre_ext.h`
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CMaxPreOptions {
+    pub bve_gate_extraction: c_char,
+    pub label_matching: c_char,
+    pub skip_technique: c_int,
+    pub bve_sort_max_first: c_char,
+    pub bve_local_grow_limit: c_int,
+    pub bve_global_grow_limit: c_int,
+    pub max_bbtms_vars: c_int,
+    pub harden_in_model_search: c_char,
+    pub model_search_iter_limit: c_int,
+}
+
+// Extensions of the C API implemented in `cppext/` of this crate
+extern "C" {
+    pub fn cmaxpre_ext_get_techniques() -> *const c_char;
+    pub fn cmaxpre_ext_get_options(arg1: *mut CMaxPre, opts: *mut CMaxPreOptions);
+    pub fn cmaxpre_ext_is_inprocessing(arg1: *mut CMaxPre) -> c_char;
+    pub fn cmaxpre_ext_is_preprocessed(arg1: *mut CMaxPre) -> c_char;
+    pub fn cmaxpre_ext_time_limit_hit(arg1: *mut CMaxPre) -> c_char;
+    pub fn cmaxpre_ext_reset_removed_weight_for(arg1: *mut CMaxPre, obj_idx: c_uint) -> c_char;
+    pub fn cmaxpre_ext_set_int_option(
+        arg1: *mut CMaxPre,
+        name: *const c_char,
+        value: c_int,
+    ) -> c_char;
+    pub fn cmaxpre_ext_set_bool_option(
+        arg1: *mut CMaxPre,
+        name: *const c_char,
+        value: c_char,
+    ) -> c_char;
+    pub fn cmaxpre_ext_set_double_option(
+        arg1: *mut CMaxPre,
+        name: *const c_char,
+        value: f64,
+    ) -> c_char;
+    pub fn cmaxpre_ext_set_uint64_option(
+        arg1: *mut CMaxPre,
+        name: *const c_char,
+        value: u64,
+    ) -> c_char;
+    pub fn cmaxpre_ext_get_technique_stats(
+        arg1: *mut CMaxPre,
+        technique: c_char,
+        stats: *mut CMaxPreTechniqueStats,
+    ) -> c_char;
+    pub fn cmaxpre_ext_reconstruct_best_model(arg1: *mut CMaxPre) -> c_char;
+    pub fn cmaxpre_ext_best_model_phases(arg1: *mut CMaxPre, lits: *mut c_int, n: c_uint);
+    pub fn cmaxpre_ext_local_search(arg1: *mut CMaxPre, time_limit: f64) -> c_char;
+    pub fn cmaxpre_ext_set_initial_model(arg1: *mut CMaxPre, cost: u64) -> c_char;
+    pub fn cmaxpre_ext_log_proof(
+        arg1: *mut CMaxPre,
+        path: *const c_char,
+        debug_level: c_int,
+        output_file: c_char,
+    ) -> c_char;
+    pub fn cmaxpre_ext_preprocess(
+        arg1: *mut CMaxPre,
+        techniques: *const c_char,
+        log_level: c_int,
+        time_limit: f64,
+        add_removed_weight: c_char,
+        sort_labels_frequency: c_char,
+    );
+    pub fn cmaxpre_ext_alter_weights(
+        arg1: *mut CMaxPre,
+        labels: *const c_int,
+        weights: *const u64,
+        n: c_uint,
+    ) -> c_char;
+    pub fn cmaxpre_ext_add_vars(arg1: *mut CMaxPre, n: c_uint) -> c_int;
+    pub fn cmaxpre_ext_init_reserve(arg1: *mut CMaxPre, n: u64);
+    pub fn cmaxpre_ext_reserve_vars(arg1: *mut CMaxPre, n: c_uint);
+    pub fn cmaxpre_ext_prepro_lit(arg1: *mut CMaxPre, lit: c_int) -> c_int;
+    pub fn cmaxpre_ext_get_n_trace_ops(arg1: *mut CMaxPre) -> c_uint;
+    pub fn cmaxpre_ext_get_trace_op(arg1: *mut CMaxPre, op_idx: c_uint) -> c_int;
+    pub fn cmaxpre_ext_get_trace_data_len(arg1: *mut CMaxPre, op_idx: c_uint) -> c_uint;
+    pub fn cmaxpre_ext_get_trace_data(
+        arg1: *mut CMaxPre,
+        op_idx: c_uint,
+        data_idx: c_uint,
+    ) -> c_int;
+    pub fn cmaxpre_ext_get_n_internal_vars(arg1: *mut CMaxPre) -> c_int;
+    pub fn cmaxpre_ext_get_n_orig_vars(arg1: *mut CMaxPre) -> c_int;
+    pub fn cmaxpre_ext_get_n_aux_vars(arg1: *mut CMaxPre) -> c_int;
+    pub fn cmaxpre_ext_get_n_solver_vars(arg1: *mut CMaxPre) -> c_uint;
+    pub fn cmaxpre_ext_internal_lit(arg1: *mut CMaxPre, lit: c_int) -> c_int;
+    pub fn cmaxpre_ext_quiet_begin();
+    pub fn cmaxpre_ext_quiet_end();
+    pub fn cmaxpre_ext_get_n_prepro_clauses(arg1: *mut CMaxPre) -> u64;
+    pub fn cmaxpre_ext_get_prepro_weight(arg1: *mut CMaxPre, cl_idx: u64, obj_idx: c_uint) -> u64;
+    pub fn cmaxpre_ext_get_prepro_lit(arg1: *mut CMaxPre, cl_idx: u64, lit_idx: u64) -> c_int;
+    pub fn cmaxpre_ext_get_n_prepro_labels(arg1: *mut CMaxPre) -> u64;
+    pub fn cmaxpre_ext_get_prepro_label(arg1: *mut CMaxPre, lbl_idx: u64) -> c_int;
+    pub fn cmaxpre_ext_get_n_prepro_fixed(arg1: *mut CMaxPre) -> u64;
+    pub fn cmaxpre_ext_get_prepro_fixed_lit(arg1: *mut CMaxPre, lit_idx: u64) -> c_int;
+}
+
+#[cfg(all(test, feature = "bindgen"))]
+mod tests {
+    mod generated {
+        #![allow(non_camel_case_types, non_upper_case_globals, dead_code)]
+        include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+    }
+
+    /// Start of a function declaration. Split up so that this line is not
+    /// picked up when parsing this file.
+    const DECL_START: &str = concat!("pub fn ", "cmaxpre_");
+
+    /// Normalizes a type, ignoring the path that C types are referenced by
+    fn normalize_type(typ: &str) -> String {
+        let typ = typ
+            .replace("::core::ffi::", "")
+            .replace("core::ffi::", "")
+            .replace("uint64_t", "u64");
+        typ.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Extracts all declarations of `cmaxpre_*` functions from Rust source
+    /// code, ignoring argument names
+    fn declarations(src: &str) -> Vec<String> {
+        let mut decls: Vec<String> = src
+            .match_indices(DECL_START)
+            .map(|(idx, _)| {
+                let decl = &src[idx + DECL_START.len() - "cmaxpre_".len()..];
+                let decl = &decl[..decl.find(';').unwrap()];
+                let (name, rest) = decl.split_once('(').unwrap();
+                let (args, ret) = rest.rsplit_once(')').unwrap();
+                let args: Vec<String> = args
+                    .split(',')
+                    .filter(|arg| !arg.trim().is_empty())
+                    .map(|arg| normalize_type(arg.split_once(':').unwrap().1))
+                    .collect();
+                format!("{}({}){}", name, args.join(", "), normalize_type(ret))
+            })
+            .collect();
+        decls.sort();
+        decls
+    }
+
+    #[test]
+    fn matches_generated() {
+        let generated = declarations(include_str!(concat!(env!("OUT_DIR"), "/bindings.rs")));
+        let handwritten = declarations(include_str!("ffi.rs"));
+        assert_eq!(handwritten, generated);
+    }
+}
@@ -0,0 +1,91 @@
+//! # Instance Graphs
+//!
+//! Graphs of the structure of the preprocessed instance as adjacency lists,
+//! see [`PreproClauses::interaction_graph`](crate::PreproClauses::interaction_graph)
+//! and [`PreproClauses::incidence_graph`](crate::PreproClauses::incidence_graph).
+
+use rustsat::{
+    instances::Cnf,
+    types::{Clause, Var},
+};
+
+use crate::SoftClauses;
+
+/// The clauses of an instance, hard clauses first and then the soft clauses
+/// of each objective
+fn clauses<'a>(
+    hards: &'a Cnf,
+    softs: &'a [(SoftClauses, isize)],
+) -> impl Iterator<Item = &'a Clause> {
+    hards
+        .iter()
+        .chain(softs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+}
+
+/// The distinct variables of each clause, sorted
+pub(crate) fn incidence(hards: &Cnf, softs: &[(SoftClauses, isize)]) -> Vec<Vec<Var>> {
+    clauses(hards, softs)
+        .map(|cl| {
+            let mut vars: Vec<Var> = cl.iter().map(|l| l.var()).collect();
+            vars.sort_unstable();
+            vars.dedup();
+            vars
+        })
+        .collect()
+}
+
+/// The neighbours of each variable up to the maximum variable, sorted.
+/// Variables are neighbours if they occur in a common clause.
+pub(crate) fn interaction(hards: &Cnf, softs: &[(SoftClauses, isize)]) -> Vec<Vec<Var>> {
+    let incidence = incidence(hards, softs);
+    let n_vars = incidence
+        .iter()
+        .filter_map(|vars| vars.last())
+        .map(|var| var.idx() + 1)
+        .max()
+        .unwrap_or(0);
+    let mut adj: Vec<Vec<Var>> = vec![vec![]; n_vars];
+    for vars in &incidence {
+        for &var in vars {
+            adj[var.idx()].extend(vars.iter().filter(|&&other| other != var));
+        }
+    }
+    for neighbours in &mut adj {
+        neighbours.sort_unstable();
+        neighbours.dedup();
+    }
+    adj
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{clause, instances::Cnf, lit, var};
+
+    use super::{incidence, interaction};
+
+    #[test]
+    fn graphs() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], !lit![2]);
+        cnf.add_ternary(lit![2], lit![3], !lit![3]);
+        let softs = vec![(vec![(clause![lit![0], lit![3]], 1)], 0)];
+        assert_eq!(
+            incidence(&cnf, &softs),
+            vec![
+                vec![var![0], var![2]],
+                vec![var![2], var![3]],
+                vec![var![0], var![3]],
+            ]
+        );
+        assert_eq!(
+            interaction(&cnf, &softs),
+            vec![
+                vec![var![2], var![3]],
+                vec![],
+                vec![var![0], var![3]],
+                vec![var![0], var![2]],
+            ]
+        );
+        assert!(interaction(&Cnf::new(), &[]).is_empty());
+    }
+}
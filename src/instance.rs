@@ -0,0 +1,278 @@
+//! # Generic Interface over Instance Kinds
+//!
+//! The kinds of instances of [`rustsat`] that preprocessors can be created
+//! from share how they are converted to clauses and built back from the
+//! preprocessed instance. [`InstanceKind`] captures this for
+//! [`SatInstance`], [`OptInstance`](rustsat::instances::OptInstance) and
+//! [`MultiOptInstance`](rustsat::instances::MultiOptInstance), and
+//! [`Prepro`] provides the constructors and extraction for all of them.
+
+use rustsat::{
+    encodings::{card, pb},
+    instances::{BasicVarManager, Cnf, ManageVars, SatInstance},
+    types::{
+        constraints::{CardConstraint, PBConstraint},
+        Clause, Lit, RsHashMap, Var,
+    },
+};
+
+use crate::{PreproClauses, PreproInit, SoftClauses};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A kind of instance that preprocessors can be created from. The trait is
+/// sealed, it is implemented for the instance types of [`rustsat`].
+pub trait InstanceKind: sealed::Sealed + Sized {
+    /// The same kind of instance over another variable manager
+    type WithVm<VM: ManageVars>: InstanceKind;
+    /// Converts the instance to hard clauses and objectives, encoding
+    /// constraints with the given encoders. Non-unit soft clauses are
+    /// relaxed with a fresh variable from the instance's variable manager and
+    /// the labels of all soft clauses are recorded in `label_map`.
+    #[allow(clippy::type_complexity)]
+    fn into_clauses<CardEnc, PBEnc>(
+        self,
+        card_encoder: CardEnc,
+        pb_encoder: PBEnc,
+        label_map: &mut RsHashMap<Lit, (usize, Clause)>,
+    ) -> (Cnf, Vec<(Vec<(Clause, u64)>, isize)>)
+    where
+        CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
+        PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars);
+    /// Builds an instance over a variable manager from a preprocessed
+    /// instance. All variables up to `max_var` are marked as used in the
+    /// manager.
+    fn from_clauses<VM: ManageVars>(
+        hards: Cnf,
+        objs: Vec<(SoftClauses, isize)>,
+        vm: VM,
+        max_var: Var,
+    ) -> Self::WithVm<VM>;
+}
+
+/// Builds the constraints of a preprocessed instance over a variable manager
+fn constraints<VM: ManageVars>(hards: Cnf, mut vm: VM, max_var: Var) -> SatInstance<VM> {
+    vm.mark_used(max_var);
+    let mut constrs = SatInstance::new_with_manager(vm);
+    hards.into_iter().for_each(|cl| constrs.add_clause(cl));
+    constrs
+}
+
+impl<VM: ManageVars> sealed::Sealed for SatInstance<VM> {}
+
+impl<IVM: ManageVars> InstanceKind for SatInstance<IVM> {
+    type WithVm<VM: ManageVars> = SatInstance<VM>;
+
+    fn into_clauses<CardEnc, PBEnc>(
+        self,
+        card_encoder: CardEnc,
+        pb_encoder: PBEnc,
+        _label_map: &mut RsHashMap<Lit, (usize, Clause)>,
+    ) -> (Cnf, Vec<(Vec<(Clause, u64)>, isize)>)
+    where
+        CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
+        PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
+    {
+        let (cnf, _) = self.into_cnf_with_encoders(card_encoder, pb_encoder);
+        (cnf, vec![])
+    }
+
+    fn from_clauses<VM: ManageVars>(
+        hards: Cnf,
+        objs: Vec<(SoftClauses, isize)>,
+        vm: VM,
+        max_var: Var,
+    ) -> SatInstance<VM> {
+        debug_assert!(objs.is_empty());
+        constraints(hards, vm, max_var)
+    }
+}
+
+#[cfg(feature = "optimization")]
+impl<VM: ManageVars> sealed::Sealed for rustsat::instances::OptInstance<VM> {}
+
+#[cfg(feature = "optimization")]
+impl<IVM: ManageVars> InstanceKind for rustsat::instances::OptInstance<IVM> {
+    type WithVm<VM: ManageVars> = rustsat::instances::OptInstance<VM>;
+
+    fn into_clauses<CardEnc, PBEnc>(
+        self,
+        card_encoder: CardEnc,
+        pb_encoder: PBEnc,
+        label_map: &mut RsHashMap<Lit, (usize, Clause)>,
+    ) -> (Cnf, Vec<(Vec<(Clause, u64)>, isize)>)
+    where
+        CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
+        PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
+    {
+        let (constrs, obj) = self.decompose();
+        let (mut cnf, mut vm) = constrs.into_cnf_with_encoders(card_encoder, pb_encoder);
+        let softs = crate::opt::relax_softs(obj.into_soft_cls(), 0, &mut cnf, &mut vm, label_map);
+        (cnf, vec![softs])
+    }
+
+    fn from_clauses<VM: ManageVars>(
+        hards: Cnf,
+        objs: Vec<(SoftClauses, isize)>,
+        vm: VM,
+        max_var: Var,
+    ) -> rustsat::instances::OptInstance<VM> {
+        debug_assert_eq!(objs.len(), 1);
+        let Some((softs, offset)) = objs.into_iter().last() else {
+            panic!("preprocessed instance has no objective")
+        };
+        let mut obj = rustsat::instances::Objective::from_iter(softs);
+        obj.set_offset(offset);
+        rustsat::instances::OptInstance::compose(constraints(hards, vm, max_var), obj)
+    }
+}
+
+#[cfg(feature = "multiopt")]
+impl<VM: ManageVars> sealed::Sealed for rustsat::instances::MultiOptInstance<VM> {}
+
+#[cfg(feature = "multiopt")]
+impl<IVM: ManageVars> InstanceKind for rustsat::instances::MultiOptInstance<IVM> {
+    type WithVm<VM: ManageVars> = rustsat::instances::MultiOptInstance<VM>;
+
+    fn into_clauses<CardEnc, PBEnc>(
+        self,
+        card_encoder: CardEnc,
+        pb_encoder: PBEnc,
+        label_map: &mut RsHashMap<Lit, (usize, Clause)>,
+    ) -> (Cnf, Vec<(Vec<(Clause, u64)>, isize)>)
+    where
+        CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
+        PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
+    {
+        let (constrs, objs) = self.decompose();
+        let (mut cnf, mut vm) = constrs.into_cnf_with_encoders(card_encoder, pb_encoder);
+        let softs = objs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, o)| {
+                crate::opt::relax_softs(o.into_soft_cls(), idx, &mut cnf, &mut vm, label_map)
+            })
+            .collect();
+        (cnf, softs)
+    }
+
+    fn from_clauses<VM: ManageVars>(
+        hards: Cnf,
+        objs: Vec<(SoftClauses, isize)>,
+        vm: VM,
+        max_var: Var,
+    ) -> rustsat::instances::MultiOptInstance<VM> {
+        let objs = objs
+            .into_iter()
+            .map(|(softs, offset)| {
+                let mut obj = rustsat::instances::Objective::from_iter(softs);
+                obj.set_offset(offset);
+                obj
+            })
+            .collect();
+        rustsat::instances::MultiOptInstance::compose(constraints(hards, vm, max_var), objs)
+    }
+}
+
+/// Encodes a cardinality constraint with the default encoding of [`rustsat`]
+pub(crate) fn default_card_encoder(constr: CardConstraint, cnf: &mut Cnf, vm: &mut dyn ManageVars) {
+    card::default_encode_cardinality_constraint(constr, cnf, vm)
+        .expect("cardinality encoding ran out of memory");
+}
+
+/// Encodes a pseudo-boolean constraint with the default encoding of
+/// [`rustsat`]
+pub(crate) fn default_pb_encoder(constr: PBConstraint, cnf: &mut Cnf, vm: &mut dyn ManageVars) {
+    pb::default_encode_pb_constraint(constr, cnf, vm).expect("pb encoding ran out of memory");
+}
+
+/// Creating preprocessors from and extracting preprocessed instances as any
+/// [kind of instance](InstanceKind). This is implemented for all
+/// preprocessors, [`PreproSat`](crate::PreproSat),
+/// [`PreproOpt`](crate::PreproOpt) and
+/// [`PreproMultiOpt`](crate::PreproMultiOpt) are built on it.
+pub trait Prepro<I: InstanceKind>: PreproClauses {
+    /// Initializes a new preprocessor from an instance where the instance is
+    /// converted to CNF with the given encoders. Non-unit soft clauses are
+    /// relaxed with a fresh variable from the instance's variable manager, so
+    /// that the [label map](PreproClauses::label_map) covers all soft
+    /// clauses.
+    ///
+    /// MaxPre's techniques only operate on clauses. Its cardinality
+    /// constraint code is a CNF encoder for its input reader and is not a
+    /// native constraint representation, so constraints are always encoded
+    /// before being passed to MaxPre.
+    fn load_with_encoders<CardEnc, PBEnc>(
+        inst: I,
+        card_encoder: CardEnc,
+        pb_encoder: PBEnc,
+        inprocessing: bool,
+    ) -> Self
+    where
+        CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
+        PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
+        Self: PreproInit,
+    {
+        let mut label_map = RsHashMap::default();
+        let (cnf, softs) = inst.into_clauses(card_encoder, pb_encoder, &mut label_map);
+        let mut pp = <Self as PreproInit>::new(cnf, softs, inprocessing);
+        if !label_map.is_empty() {
+            pp.set_label_map(label_map);
+        }
+        pp
+    }
+    /// Initializes a new preprocessor from an instance, encoding constraints
+    /// with the default encodings
+    fn load(inst: I, inprocessing: bool) -> Self
+    where
+        Self: PreproInit,
+    {
+        Self::load_with_encoders(inst, default_card_encoder, default_pb_encoder, inprocessing)
+    }
+    /// Gets the preprocessed instance over a new default variable manager,
+    /// see [`Prepro::extract_with_vm`]
+    fn extract(&mut self) -> I::WithVm<BasicVarManager> {
+        self.extract_with_vm(BasicVarManager::default())
+    }
+    /// Gets the preprocessed instance with the given variable manager,
+    /// typically the one of the original instance. All variables up to the
+    /// highest original or preprocessed variable are marked as used in the
+    /// manager, so that new variables do not clash with the preprocessed
+    /// instance or with variables that reconstruction assigns.
+    fn extract_with_vm<VM: ManageVars>(&mut self, vm: VM) -> I::WithVm<VM> {
+        let (cnf, objs) = <Self as PreproClauses>::prepro_instance(self);
+        I::from_clauses(cnf, objs, vm, crate::max_used_var(self))
+    }
+}
+
+impl<PP: PreproClauses + ?Sized, I: InstanceKind> Prepro<I> for PP {}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        instances::{BasicVarManager, ManageVars, SatInstance},
+        lit, var,
+    };
+
+    use super::Prepro;
+    use crate::{NoopPrepro, PreproClauses};
+
+    #[test]
+    fn sat_instances() {
+        let mut inst: SatInstance = SatInstance::new();
+        inst.add_binary(lit![0], lit![3]);
+        inst.add_card_constr(rustsat::types::constraints::CardConstraint::new_ub(
+            [lit![0], lit![1], lit![2]],
+            1,
+        ));
+        let mut pp: NoopPrepro = Prepro::load(inst, false);
+        pp.preprocess("[u]", 0, 1e9);
+        let prepro: SatInstance = Prepro::<SatInstance>::extract(&mut pp);
+        assert!(prepro.n_clauses() > 1);
+        let vm = BasicVarManager::default();
+        let prepro = Prepro::<SatInstance>::extract_with_vm(&mut pp, vm);
+        assert!(prepro.var_manager_ref().max_var() >= Some(var![3]));
+    }
+}
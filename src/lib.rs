@@ -3,91 +3,1111 @@
 //! A Rust interface to the [MaxPre](https://bitbucket.org/coreo-group/maxpre2)
 //! preprocessor for MaxSAT.
 
-use core::{
-    ffi::{c_int, c_uint},
-    time::Duration,
+use core::{ffi::c_int, ops::Range, time::Duration};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io,
 };
 
 use rustsat::{
-    instances::Cnf,
-    types::{Assignment, Clause, Lit, Var, WClsIter},
+    encodings::pb,
+    instances::{BasicVarManager, Cnf, ManageVars, ReindexVars, ReindexingVarManager},
+    types::{Assignment, Clause, Lit, RsHashMap, TernaryVal, Var},
 };
 
+mod anonymize;
+#[cfg(feature = "cxx")]
 mod base;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "clap")]
+mod cli;
+mod components;
+mod features;
+#[cfg(feature = "raw-ffi")]
+pub mod ffi;
+#[cfg(all(feature = "cxx", not(feature = "raw-ffi")))]
 mod ffi;
+mod graph;
+mod instance;
 #[cfg(feature = "multiopt")]
 mod multiopt;
+mod native;
+mod noop;
 #[cfg(feature = "optimization")]
 mod opt;
+mod preprocess;
+#[cfg(feature = "cxx")]
+mod reconstruct;
 mod sat;
+#[cfg(feature = "cxx")]
+mod shared;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+mod weight;
 
 // Rexports
-pub use base::MaxPre;
+pub use anonymize::Anonymization;
+#[cfg(feature = "cxx")]
+pub use base::{MaxPre, MaxPreBuilder};
+#[cfg(feature = "clap")]
+pub use cli::PreproArgs;
+pub use components::ComponentPrepro;
+pub use features::{FeatureSummary, Features};
+pub use instance::{InstanceKind, Prepro};
 #[cfg(feature = "multiopt")]
-pub use multiopt::PreproMultiOpt;
+pub use multiopt::{PreproMultiOpt, VerifyError};
+pub use native::NativePrepro;
+pub use noop::NoopPrepro;
 #[cfg(feature = "optimization")]
 pub use opt::PreproOpt;
+pub use preprocess::{PreproConfig, Preprocess};
+#[cfg(feature = "cxx")]
+pub use reconstruct::Reconstructor;
 pub use sat::PreproSat;
+#[cfg(feature = "cxx")]
+pub use shared::SharedPrepro;
+pub use weight::{OverflowPolicy, PreproWeights, WeightLike, WeightedSoftClauses};
 
 pub type SoftClauses = Vec<(Clause, usize)>;
 
+/// Collects the hard and soft clauses passed to [`PreproInit::new`]
+///
+/// # Panics
+///
+/// If a weight does not fit in [`usize`], see [`try_collect_instance`]
+pub(crate) fn collect_instance<H, S, CI>(hards: H, softs: S) -> (Cnf, Vec<(SoftClauses, isize)>)
+where
+    H: IntoIterator<Item = Clause>,
+    S: IntoIterator<Item = (CI, isize)>,
+    CI: IntoIterator<Item = (Clause, u64)>,
+{
+    match try_collect_instance(hards, softs) {
+        Ok(inst) => inst,
+        Err(_) => panic!("weight of a soft clause does not fit in usize"),
+    }
+}
+
+/// Collects the hard and soft clauses as [`collect_instance`], but returns
+/// [`Error::WeightOverflow`] if a weight does not fit in [`usize`]
+pub(crate) fn try_collect_instance<H, S, CI>(
+    hards: H,
+    softs: S,
+) -> Result<(Cnf, Vec<(SoftClauses, isize)>), Error>
+where
+    H: IntoIterator<Item = Clause>,
+    S: IntoIterator<Item = (CI, isize)>,
+    CI: IntoIterator<Item = (Clause, u64)>,
+{
+    let hards = hards.into_iter().collect();
+    let softs = softs
+        .into_iter()
+        .map(|(cls, ofs)| {
+            let cls = cls
+                .into_iter()
+                .map(|(cl, w)| Ok((cl, usize::try_from(w).map_err(|_| Error::WeightOverflow)?)))
+                .collect::<Result<_, Error>>()?;
+            Ok((cls, ofs))
+        })
+        .collect::<Result<_, Error>>()?;
+    Ok((hards, softs))
+}
+
 /// Errors in MaxPre
 pub enum Error {
     /// Generic MaxPre Error that is not further specified
     Generic,
+    /// The call is not valid in the current [state](PreproState) of the
+    /// preprocessor, e.g., getting the preprocessed instance before
+    /// preprocessing
+    InvalidState,
+    /// The call modifies the instance, which requires the preprocessor to be
+    /// in inprocessing mode
+    NotInprocessing,
+    /// A weight does not fit into the target weight type, see
+    /// [`OverflowPolicy`]
+    WeightOverflow,
+    /// The instance is rejected by input validation, see
+    /// [`MaxPre::try_new`](crate::MaxPre::try_new)
+    InvalidInput(InputError),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "unspecified error")
+        match self {
+            Error::Generic => write!(f, "unspecified error"),
+            Error::InvalidState => write!(f, "invalid call in the current preprocessor state"),
+            Error::NotInprocessing => write!(f, "preprocessor is not in inprocessing mode"),
+            Error::WeightOverflow => write!(f, "weight does not fit into the weight type"),
+            Error::InvalidInput(err) => write!(f, "invalid input: {err}"),
+        }
+    }
+}
+
+/// Why an instance is rejected by input validation. Soft clauses are given by
+/// the index of the objective and of the clause in the objective.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// A soft clause is empty
+    EmptySoftClause { obj_idx: usize, cl_idx: usize },
+    /// A soft clause contains a literal and its negation
+    TautologicalSoftClause { obj_idx: usize, cl_idx: usize },
+    /// A variable exceeds the largest variable index of the preprocessor, see
+    /// [`Capabilities::max_var_idx`]
+    VarOutOfRange(Var),
+    /// More variables are requested at once than the preprocessor can
+    /// represent
+    TooManyVars(usize),
+}
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputError::EmptySoftClause { obj_idx, cl_idx } => {
+                write!(f, "soft clause {cl_idx} of objective {obj_idx} is empty")
+            }
+            InputError::TautologicalSoftClause { obj_idx, cl_idx } => {
+                write!(
+                    f,
+                    "soft clause {cl_idx} of objective {obj_idx} is a tautology"
+                )
+            }
+            InputError::VarOutOfRange(var) => write!(f, "variable {var} is out of range"),
+            InputError::TooManyVars(n) => write!(f, "cannot add {n} variables at once"),
+        }
+    }
+}
+
+/// Error when parsing [`Options`] or [`Techniques`] from a string
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Cost of an assignment for each objective, not including the offsets
+pub(crate) fn costs(objs: &[(SoftClauses, isize)], assign: &Assignment) -> Vec<u64> {
+    objs.iter()
+        .map(|(softs, _)| {
+            softs
+                .iter()
+                .filter(|(cl, _)| cl.evaluate(assign) != TernaryVal::True)
+                .fold(0, |cost, (_, w)| cost + *w as u64)
+        })
+        .collect()
+}
+
+/// The values of a model for the variables of some clauses, see
+/// [`PreproClauses::phase_hints`]
+pub(crate) fn phase_hints<'a, CI: IntoIterator<Item = &'a Clause>>(
+    model: &Assignment,
+    clauses: CI,
+) -> Vec<Lit> {
+    let vars: BTreeSet<Var> = clauses
+        .into_iter()
+        .flat_map(|cl| cl.iter().map(|l| l.var()))
+        .collect();
+    vars.into_iter()
+        .filter_map(|var| match model.var_value(var) {
+            TernaryVal::True => Some(var.pos_lit()),
+            TernaryVal::False => Some(var.neg_lit()),
+            TernaryVal::DontCare => None,
+        })
+        .collect()
+}
+
+/// Assigns all variables up to `max_var` that an assignment does not assign to
+/// false
+pub(crate) fn assign_dont_cares(assign: &mut Assignment, max_var: Option<Var>) {
+    if let Some(max_var) = max_var {
+        for idx in 0..=max_var.idx32() {
+            let var = Var::new(idx);
+            if assign.var_value(var) == TernaryVal::DontCare {
+                assign.assign_lit(var.neg_lit());
+            }
+        }
+    }
+}
+
+/// Writes a clause of a preprocessed instance in WCNF, or in MCNF if there are
+/// multiple objectives. `soft` is the objective index and weight of a soft
+/// clause, [`None`] for a hard clause.
+pub(crate) fn write_dimacs_clause<LI: IntoIterator<Item = Lit>>(
+    writer: &mut dyn io::Write,
+    n_objs: usize,
+    soft: Option<(usize, u64)>,
+    lits: LI,
+) -> io::Result<()> {
+    match soft {
+        None => write!(writer, "h ")?,
+        Some((_, weight)) if n_objs <= 1 => write!(writer, "{weight} ")?,
+        Some((obj_idx, weight)) => write!(writer, "o{} {weight} ", obj_idx + 1)?,
+    }
+    for lit in lits {
+        write!(writer, "{} ", lit.to_ipasir())?;
+    }
+    writeln!(writer, "0")
+}
+
+/// Writes the names of the named objectives as DIMACS comments
+pub(crate) fn write_objective_names(
+    writer: &mut dyn io::Write,
+    names: &[Option<String>],
+) -> io::Result<()> {
+    for (obj_idx, name) in names.iter().enumerate() {
+        if let Some(name) = name {
+            writeln!(writer, "c objective {}: {name}", obj_idx + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the names of the objectives in the statistics, with one entry per
+/// objective
+pub(crate) fn set_objective_names(stats: &mut Stats, mut names: Vec<Option<String>>) {
+    names.resize(stats.n_objs, None);
+    stats.obj_names = names;
+}
+
+/// Converts soft clauses back to the input weight type of [`PreproInit::new`]
+pub(crate) fn weighted(
+    softs: Vec<(SoftClauses, isize)>,
+) -> impl Iterator<Item = (impl Iterator<Item = (Clause, u64)>, isize)> {
+    softs
+        .into_iter()
+        .map(|(cls, ofs)| (cls.into_iter().map(|(cl, w)| (cl, w as u64)), ofs))
+}
+
+/// Groups the labels of unit soft clauses by weight, in decreasing order of
+/// the weights. Labels are given such that they are true if the soft clause
+/// is satisfied.
+pub(crate) fn weight_strata(softs: &SoftClauses) -> Vec<(u64, Vec<Lit>)> {
+    let mut strata: BTreeMap<u64, Vec<Lit>> = BTreeMap::new();
+    for (cl, w) in softs {
+        if cl.len() == 1 {
+            strata.entry(*w as u64).or_default().push(cl[0]);
+        }
     }
+    strata.into_iter().rev().collect()
 }
 
+/// Keeps the redundant clauses whose variables all appear in the given
+/// clauses of a preprocessed instance and are not fixed
+pub(crate) fn retain_redundant<'a, CI: IntoIterator<Item = &'a Clause>>(
+    redundant: &mut Vec<Clause>,
+    clauses: CI,
+    fixed: &[Lit],
+) {
+    let vars: HashSet<Var> = clauses
+        .into_iter()
+        .flat_map(|cl| cl.iter().map(|l| l.var()))
+        .collect();
+    let fixed: HashSet<Var> = fixed.iter().map(|l| l.var()).collect();
+    redundant.retain(|cl| {
+        cl.iter()
+            .all(|l| vars.contains(&l.var()) && !fixed.contains(&l.var()))
+    });
+}
+
+/// Checks whether `order` contains every index in `0..n` exactly once
+pub(crate) fn is_permutation(order: &[usize], n: usize) -> bool {
+    let mut seen = vec![false; n];
+    order.len() == n
+        && order
+            .iter()
+            .all(|&idx| idx < n && !std::mem::replace(&mut seen[idx], true))
+}
+
+/// Decides the status of a preprocessor from its preprocessed instance, see
+/// [`PreproClauses::status`]
+pub(crate) fn prepro_status<PP: PreproClauses + ?Sized>(pp: &mut PP) -> PreproStatus {
+    if pp.state() != PreproState::Preprocessed {
+        return PreproStatus::Unknown;
+    }
+    let (hards, objs) = pp.prepro_instance();
+    if hards.iter().any(|cl| cl.is_empty()) {
+        return PreproStatus::Unsat;
+    }
+    if !hards.is_empty()
+        || objs
+            .iter()
+            .any(|(softs, _)| softs.iter().any(|(cl, _)| !cl.is_empty()))
+    {
+        return PreproStatus::Unknown;
+    }
+    let model = pp.reconstruct(Assignment::default());
+    let costs = pp.eval_objectives(&model, VarSpace::Original);
+    PreproStatus::OptimumFound(model, costs)
+}
+
+/// Gets the highest variable that is used in the original or the preprocessed
+/// instance of a preprocessor. Variable managers for the preprocessed instance
+/// are seeded with this, so that new variables do not clash with either.
+pub(crate) fn max_used_var<PP: PreproClauses + ?Sized>(pp: &PP) -> Var {
+    pp.max_prepro_var()
+        .map_or(pp.max_orig_var(), |var| var.max(pp.max_orig_var()))
+}
+
+/// The operations of a preprocessor. The trait is object safe, so the
+/// preprocessor can be chosen at runtime with `Box<dyn PreproClauses>`.
+/// Preprocessors are constructed via [`PreproInit`].
+///
+/// # Inprocessing
+///
+/// A preprocessor created in inprocessing mode can be preprocessed in
+/// multiple rounds, as tracked by [`PreproClauses::state`]. Between rounds,
+/// variables, clauses and labels can be added and weights altered, e.g., with
+/// clauses and bounds found while solving the preprocessed instance. These
+/// modifications take effect with the next call to
+/// [`PreproClauses::preprocess`], which preprocesses the current instance
+/// including the modifications. Reconstruction covers all rounds, so a
+/// solution of the latest preprocessed instance is reconstructed to a
+/// solution of the original instance with the modifications. Outside of
+/// inprocessing mode, all modifications fail with an error.
 pub trait PreproClauses {
-    /// Gets the signature of the preprocessor library
-    fn signature() -> &'static str;
-    /// Initializes a new preprocessor with hard clauses and optional multiple sets of soft clauses.
-    fn new<CI: WClsIter>(hards: Cnf, softs: Vec<(CI, isize)>, inprocessing: bool) -> Self;
     /// Performs preprocessing on the internal instance
     fn preprocess(&mut self, techniques: &str, log_level: c_int, time_limit: f64);
+    /// Performs preprocessing with [`Techniques::DEFAULT`], without logging
+    /// and time limit
+    fn preprocess_default(&mut self) {
+        self.preprocess(&Techniques::DEFAULT, 0, 1e9);
+    }
+    /// Preprocesses with the techniques whose [budget](TechniqueBudget) is not
+    /// used up yet, see [`Techniques::budget`]. If the budgets of all
+    /// techniques are used up, the instance is not preprocessed.
+    fn preprocess_budgeted(&mut self, techniques: &Techniques, log_level: c_int, time_limit: f64) {
+        let stats = self.technique_stats();
+        let left = techniques.retain(|technique| {
+            let time = stats
+                .iter()
+                .find(|ts| ts.technique == technique)
+                .map_or(Duration::ZERO, |ts| ts.time);
+            !techniques
+                .budget_of(technique)
+                .is_exhausted(self.technique_runs(technique), time)
+        });
+        if let Some(left) = left {
+            self.preprocess(&left, log_level, time_limit);
+        }
+    }
+    /// Runs a single technique once, without logging. The technique is not
+    /// run if its budget is used up, see [`TechniqueBudget::is_exhausted`].
+    /// Otherwise, preprocessing is time limited to the time left in the
+    /// budget.
+    fn apply(&mut self, technique: Technique, budget: TechniqueBudget) -> RoundResult {
+        let before = self.technique_stats();
+        let stats_of =
+            |stats: &[TechniqueStats]| stats.iter().find(|ts| ts.technique == technique).cloned();
+        let spent = stats_of(&before).map_or(Duration::ZERO, |ts| ts.time);
+        let mut result = RoundResult {
+            technique,
+            ran: false,
+            time: Duration::ZERO,
+            removed_clauses: 0,
+            removed_vars: 0,
+            removed_lits: 0,
+            removed_labels: 0,
+            hit_limit: false,
+        };
+        if budget.is_exhausted(self.technique_runs(technique), spent) {
+            return result;
+        }
+        let time_limit = budget
+            .max_time
+            .map_or(1e9, |max| max.saturating_sub(spent).as_secs_f64());
+        self.preprocess(&String::from(technique.char()), 0, time_limit);
+        result.ran = true;
+        if let Some(after) = stats_of(&self.technique_stats()) {
+            let before = stats_of(&before);
+            let before = |field: fn(&TechniqueStats) -> isize| before.as_ref().map_or(0, field);
+            result.time = after.time.saturating_sub(spent);
+            result.removed_clauses = after.removed_clauses - before(|ts| ts.removed_clauses);
+            result.removed_vars = after.removed_vars - before(|ts| ts.removed_vars);
+            result.removed_lits = after.removed_lits - before(|ts| ts.removed_lits);
+            result.removed_labels = after.removed_labels - before(|ts| ts.removed_labels);
+            result.hit_limit = after.hit_limit;
+        }
+        result
+    }
+    /// Preprocesses as [`PreproClauses::preprocess`] and summarizes the call.
+    /// If the time limit runs out, the summary reports the techniques that
+    /// did not get to run and where in `techniques` the cutoff hit, see
+    /// [`RoundSummary::skipped_techniques`].
+    fn preprocess_summarized(
+        &mut self,
+        techniques: &str,
+        log_level: c_int,
+        time_limit: f64,
+    ) -> RoundSummary {
+        let before = if self.state() == PreproState::Loaded {
+            self.n_orig_clauses()
+        } else {
+            self.n_prepro_clauses()
+        };
+        let stats_before = self.technique_stats();
+        self.preprocess(techniques, log_level, time_limit);
+        let n_clauses = self.n_prepro_clauses();
+        let (skipped_techniques, cutoff) = if self.hit_time_limit() {
+            let stats_after = self.technique_stats();
+            let runs = |stats: &[TechniqueStats], technique: Technique| {
+                stats
+                    .iter()
+                    .find(|ts| ts.technique == technique)
+                    .map_or((None, Duration::ZERO), |ts| (ts.invocations, ts.time))
+            };
+            schedule_cutoff(techniques, |technique| {
+                runs(&stats_before, technique) != runs(&stats_after, technique)
+            })
+        } else {
+            (vec![], None)
+        };
+        RoundSummary {
+            n_clauses,
+            n_vars: self.n_prepro_vars(),
+            reduction: if before == 0 {
+                0.
+            } else {
+                (before as f64 - n_clauses as f64) / before as f64
+            },
+            time: self.stats().last_prepro_time,
+            skipped_techniques,
+            cutoff,
+        }
+    }
+    /// Repeats preprocessing rounds with `techniques`, each limited to
+    /// `per_round_budget` seconds, until a round removes less than the
+    /// fraction `min_reduction` of the clauses left by the previous round, or
+    /// `time_limit` seconds are spent in total. The first round is always run
+    /// and only further rounds are subject to `min_reduction`, a round that
+    /// does not change the number of clauses also ends preprocessing. Outside of
+    /// inprocessing mode, only one round is run. Returns a summary of each
+    /// round.
+    fn preprocess_until_fixpoint(
+        &mut self,
+        techniques: &str,
+        per_round_budget: f64,
+        min_reduction: f64,
+        time_limit: f64,
+    ) -> Vec<RoundSummary> {
+        let mut rounds: Vec<RoundSummary> = vec![];
+        let mut spent = 0.;
+        let mut n_clauses = self.n_orig_clauses();
+        while spent < time_limit {
+            if !rounds.is_empty() && !self.is_inprocessing() {
+                break;
+            }
+            let round =
+                self.preprocess_summarized(techniques, 0, per_round_budget.min(time_limit - spent));
+            spent += round.time.as_secs_f64();
+            let before = core::mem::replace(&mut n_clauses, round.n_clauses);
+            let stalled = n_clauses == before || round.reduction < min_reduction;
+            rounds.push(round);
+            if rounds.len() > 1 && stalled {
+                break;
+            }
+        }
+        rounds
+    }
+    /// Preprocesses with techniques and budgets chosen from the size, weights
+    /// and number of objectives of the original instance, see
+    /// [`AutoPlan::choose`]. Returns the chosen plan.
+    fn preprocess_auto(&mut self, log_level: c_int, time_limit: f64) -> AutoPlan {
+        let n_objs = self.stats().n_objs;
+        let weighted = (0..n_objs)
+            .any(|obj_idx| self.orig_weight(obj_idx) != self.n_orig_soft_clauses(obj_idx));
+        let plan = AutoPlan::choose(self.n_orig_clauses(), n_objs, weighted, time_limit);
+        self.preprocess_budgeted(&plan.techniques, log_level, plan.time_limit);
+        plan
+    }
+    /// Gets the number of calls to [`PreproClauses::preprocess`] that ran a
+    /// technique
+    fn technique_runs(&self, technique: Technique) -> usize;
+    /// Gets where the preprocessor is in the
+    /// [inprocessing workflow](PreproClauses#inprocessing)
+    fn state(&self) -> PreproState;
+    /// Checks whether the preprocessor is in inprocessing mode, i.e., whether
+    /// the instance can be modified
+    fn is_inprocessing(&self) -> bool;
+    /// Checks whether the time limit of preprocessing ran out, after which
+    /// MaxPre does not give the remaining techniques any time. Time limits
+    /// accumulate over the calls to [`PreproClauses::preprocess`].
+    fn hit_time_limit(&self) -> bool;
+    /// Gets what the preprocessor supports, so that frontends can adapt to
+    /// it instead of failing at runtime
+    fn capabilities(&self) -> Capabilities;
+    /// Preprocesses as [`PreproClauses::preprocess`], but checks the order of
+    /// calls. Without inprocessing, an instance can only be preprocessed once.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::InvalidState`] if the preprocessor is not in inprocessing mode
+    /// and was already preprocessed
+    fn try_preprocess(
+        &mut self,
+        techniques: &str,
+        log_level: c_int,
+        time_limit: f64,
+    ) -> Result<(), Error> {
+        if !self.is_inprocessing() && self.state() != PreproState::Loaded {
+            return Err(Error::InvalidState);
+        }
+        self.preprocess(techniques, log_level, time_limit);
+        Ok(())
+    }
     /// Gets the top weight of the preprocessor
     fn top_weight(&self) -> u64;
     /// Gets the number of preprocessed clauses
-    fn n_prepro_clauses(&self) -> c_uint;
+    fn n_prepro_clauses(&self) -> usize;
     /// Gets the number of preprocessed labels
-    fn n_prepro_labels(&self) -> c_uint;
+    fn n_prepro_labels(&self) -> usize;
     /// Gets the number of fixed literals
-    fn n_prepro_fixed_lits(&self) -> c_uint;
+    fn n_prepro_fixed_lits(&self) -> usize;
+    /// Gets the number of distinct variables in the preprocessed instance
+    fn n_prepro_vars(&self) -> usize;
+    /// Gets the maximum variable in the preprocessed instance
+    fn max_prepro_var(&self) -> Option<Var>;
+    /// Gets the number of auxiliary variables that were introduced, e.g., for
+    /// labelling non-unit soft clauses or by
+    /// [techniques that introduce variables](Technique::introduces_vars). Zero
+    /// before preprocessing.
+    fn n_aux_vars(&self) -> usize;
     /// Gets the preprocessed instance
     fn prepro_instance(&mut self) -> (Cnf, Vec<(SoftClauses, isize)>);
+    /// Gets the preprocessed instance as [`PreproClauses::prepro_instance`],
+    /// but checks the order of calls
+    ///
+    /// # Errors
+    ///
+    /// [`Error::InvalidState`] if the instance was not preprocessed yet, in
+    /// which case the preprocessed instance of MaxPre is empty
+    fn try_prepro_instance(&mut self) -> Result<(Cnf, Vec<(SoftClauses, isize)>), Error> {
+        if self.state() == PreproState::Loaded {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.prepro_instance())
+    }
+    /// Gets the preprocessed instance with its variables renumbered to a dense
+    /// range starting from index 0. Variables keep their relative order. The
+    /// returned variable manager maps between the renumbered and the
+    /// preprocessed variables, solutions of the renumbered instance can be
+    /// reconstructed with [`PreproClauses::reconstruct_reindexed`].
+    fn prepro_instance_reindexed(
+        &mut self,
+    ) -> (Cnf, Vec<(SoftClauses, isize)>, ReindexingVarManager) {
+        let (hards, objs) = self.prepro_instance();
+        let mut vars = BTreeSet::new();
+        hards
+            .iter()
+            .chain(
+                objs.iter()
+                    .flat_map(|(softs, _)| softs.iter().map(|(cl, _)| cl)),
+            )
+            .for_each(|cl| vars.extend(cl.iter().map(|l| l.var())));
+        let mut reindexer = ReindexingVarManager::default();
+        vars.into_iter().for_each(|v| {
+            reindexer.reindex(v);
+        });
+        let mut reindex_cl =
+            |cl: &Clause| -> Clause { cl.iter().map(|l| reindexer.reindex_lit(*l)).collect() };
+        let hards = hards.iter().map(&mut reindex_cl).collect();
+        let objs = objs
+            .into_iter()
+            .map(|(softs, offset)| {
+                let softs = softs.iter().map(|(cl, w)| (reindex_cl(cl), *w)).collect();
+                (softs, offset)
+            })
+            .collect();
+        (hards, objs, reindexer)
+    }
+    /// Gets the preprocessed instance with the
+    /// [name](PreproClauses::objective_names) of each objective
+    #[allow(clippy::type_complexity)]
+    fn prepro_instance_named(&mut self) -> (Cnf, Vec<(SoftClauses, isize, Option<String>)>) {
+        let (hards, objs) = self.prepro_instance();
+        let names = self.objective_names();
+        let objs = objs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (softs, offset))| (softs, offset, names.get(idx).cloned().flatten()))
+            .collect();
+        (hards, objs)
+    }
+    /// Gets the preprocessed instance anonymized for publishing it, see
+    /// [`Anonymization`]. Variables are renamed to a dense range in a random
+    /// order and the clauses are shuffled. Without a seed, the seed is taken
+    /// from the system time, it is stored in the returned [`Anonymization`]
+    /// either way. Solutions of the anonymized instance are reconstructed with
+    /// [`PreproClauses::reconstruct_anonymized`].
+    fn prepro_instance_anonymized(
+        &mut self,
+        seed: Option<u64>,
+    ) -> (Cnf, Vec<(SoftClauses, isize)>, Anonymization) {
+        let seed = seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        let (hards, objs) = self.prepro_instance();
+        Anonymization::anonymize(&hards, &objs, seed)
+    }
     /// Gets the preprocessed labels
     fn prepro_labels(&self) -> Vec<Lit>;
+    /// Groups the preprocessed labels of an objective by their weight, in
+    /// decreasing order of the weights, for solvers that process the soft
+    /// clauses in strata. Labels are given in the same polarity as by
+    /// [`PreproClauses::prepro_labels`].
+    fn strata(&self, obj_idx: usize) -> Vec<(u64, Vec<Lit>)>;
+    /// Gets the map from the labels of the original instance to the index of
+    /// the objective and the soft clause that the label was created for.
+    /// Labels are given in the same polarity as by
+    /// [`PreproClauses::prepro_labels`], i.e., the label is true if the soft
+    /// clause is satisfied. Unit soft clauses are their own label, labels for
+    /// other soft clauses are only known if they were relaxed before being
+    /// passed to MaxPre, as done by the constructors of
+    /// [`PreproOpt`](crate::PreproOpt) and
+    /// [`PreproMultiOpt`](crate::PreproMultiOpt). If multiple soft clauses
+    /// share a label, the map contains the first of them.
+    fn label_map(&self) -> &RsHashMap<Lit, (usize, Clause)>;
+    /// Replaces the [label map](PreproClauses::label_map), used by
+    /// constructors that relax soft clauses before passing them on
+    fn set_label_map(&mut self, map: RsHashMap<Lit, (usize, Clause)>);
+    /// Gets the names of the objectives, by the index of the objective in the
+    /// original and the preprocessed instance. This is empty if no names were
+    /// set and otherwise has one entry per objective.
+    fn objective_names(&self) -> &[Option<String>];
+    /// Sets the names of the objectives, e.g., for keeping multi-objective
+    /// results interpretable. The names are part of the
+    /// [statistics](PreproClauses::stats) and written as comments by
+    /// [`PreproClauses::write_prepro_dimacs`]. Objectives without a name in
+    /// `names` are unnamed, names beyond the number of objectives are
+    /// ignored.
+    fn set_objective_names(&mut self, names: Vec<Option<String>>);
+    /// Gets at-most-one constraints over the preprocessed labels, i.e., sets of
+    /// labels of which at most one can be falsified. These are detected from
+    /// the binary hard clauses of the preprocessed instance and each label is
+    /// in at most one of the constraints. MaxPre's at-most-one techniques
+    /// rewrite the instance directly and do not store the constraints they
+    /// detect, so this can find fewer constraints than the techniques use.
+    fn prepro_label_am1s(&mut self) -> Vec<Vec<Lit>> {
+        let labels = self.prepro_labels();
+        let (hards, _) = self.prepro_instance();
+        let label_idx: HashMap<Lit, usize> = labels
+            .iter()
+            .enumerate()
+            .map(|(idx, &l)| (l, idx))
+            .collect();
+        let mut neighbours = vec![HashSet::new(); labels.len()];
+        for cl in hards.iter().filter(|cl| cl.len() == 2) {
+            if let (Some(&a), Some(&b)) = (label_idx.get(&cl[0]), label_idx.get(&cl[1])) {
+                neighbours[a].insert(b);
+                neighbours[b].insert(a);
+            }
+        }
+        // Greedily build disjoint cliques, starting from the most connected
+        // labels
+        let mut order: Vec<usize> = (0..labels.len()).collect();
+        order.sort_by_key(|&idx| std::cmp::Reverse(neighbours[idx].len()));
+        let mut used = vec![false; labels.len()];
+        let mut am1s = vec![];
+        for &idx in &order {
+            if used[idx] {
+                continue;
+            }
+            let mut clique = vec![idx];
+            for &cand in &order {
+                if !used[cand]
+                    && cand != idx
+                    && clique
+                        .iter()
+                        .all(|member| neighbours[cand].contains(member))
+                {
+                    clique.push(cand);
+                }
+            }
+            if clique.len() < 2 {
+                continue;
+            }
+            clique.iter().for_each(|&member| used[member] = true);
+            am1s.push(clique.into_iter().map(|member| labels[member]).collect());
+        }
+        am1s
+    }
     /// Gets the set of literals fixed to true by preprocessing
     fn prepro_fixed_lits(&self) -> Vec<Lit>;
+    /// Gets the literals fixed to true by preprocessing since the last call
+    /// of this method, so that only newly fixed literals need to be passed
+    /// on, e.g., to a SAT solver in an inprocessing loop. The first call, and
+    /// the first call after [`PreproClauses::reset`], returns all fixed
+    /// literals.
+    fn fixed_lits_since_last_call(&mut self) -> Vec<Lit>;
     /// Gets the maximum original variable
     fn max_orig_var(&self) -> Var;
+    /// Gets the number of original clauses, hard and soft
+    fn n_orig_clauses(&self) -> usize;
+    /// Gets the number of original soft clauses of an objective
+    ///
+    /// # Panics
+    ///
+    /// If `obj_idx` is not the index of an objective
+    fn n_orig_soft_clauses(&self, obj_idx: usize) -> usize;
+    /// Gets the number of variables in the original instance
+    fn n_orig_vars(&self) -> usize;
+    /// Gets the total weight of the original soft clauses of an objective
+    ///
+    /// # Panics
+    ///
+    /// If `obj_idx` is not the index of an objective
+    fn orig_weight(&self, obj_idx: usize) -> usize;
+    /// Gets the offset that an objective was given when initializing the
+    /// preprocessor. The offsets of the preprocessed instance are this offset
+    /// plus the [incurred](RemovedWeight::incurred) removed weight.
+    ///
+    /// # Panics
+    ///
+    /// If `obj_idx` is not the index of an objective
+    fn orig_offset(&self, obj_idx: usize) -> isize;
     /// Gets the upper bound on the objective found by preprocessing
     fn upper_bound(&self) -> u64;
-    /// Reconstructs an assignment
+    /// Gets a lower bound on each objective derived by preprocessing. This is
+    /// the removed weight, which includes the weight of labels fixed by
+    /// preprocessing, plus the weight of all preprocessed soft clauses that
+    /// are empty, i.e., falsified. The bounds do not include the objective
+    /// offsets.
+    fn lower_bounds(&mut self) -> Vec<u64> {
+        let (_, softs) = self.prepro_instance();
+        let removed = self.removed_weight().incurred();
+        softs
+            .iter()
+            .zip(removed)
+            .map(|((softs, _), removed)| {
+                softs
+                    .iter()
+                    .filter(|(cl, _)| cl.is_empty())
+                    .fold(removed, |lb, (_, w)| lb + *w as u64)
+            })
+            .collect()
+    }
+    /// Reconstructs an assignment. Original variables that reconstruction
+    /// does not determine are assigned an arbitrary value, see
+    /// [`PreproClauses::reconstruct_partial`] for keeping them unassigned.
     fn reconstruct(&mut self, sol: Assignment) -> Assignment;
+    /// Reconstructs an assignment, leaving original variables that
+    /// reconstruction does not determine, e.g., variables that MaxPre never
+    /// constrained, as [`TernaryVal::DontCare`]. Any value of these variables
+    /// extends the reconstruction to a solution.
+    fn reconstruct_partial(&mut self, sol: Assignment) -> Assignment;
+    /// Reconstructs an assignment given by its true literals, e.g., a model
+    /// straight from a SAT solver, see [`PreproClauses::reconstruct`]
+    fn reconstruct_lits<I: IntoIterator<Item = Lit>>(&mut self, lits: I) -> Assignment
+    where
+        Self: Sized,
+    {
+        self.reconstruct(lits.into_iter().collect())
+    }
+    /// Reconstructs an assignment of an instance obtained from
+    /// [`PreproClauses::prepro_instance_reindexed`], given the variable
+    /// manager returned with the instance
+    fn reconstruct_reindexed(
+        &mut self,
+        sol: Assignment,
+        reindexer: &ReindexingVarManager,
+    ) -> Assignment {
+        let sol = sol
+            .into_iter()
+            .filter_map(|l| reindexer.reverse_lit(l))
+            .collect();
+        self.reconstruct(sol)
+    }
+    /// Reconstructs an assignment of an instance obtained from
+    /// [`PreproClauses::prepro_instance_anonymized`], given the anonymization
+    /// returned with the instance
+    fn reconstruct_anonymized(&mut self, sol: Assignment, anon: &Anonymization) -> Assignment {
+        let sol = sol.into_iter().filter_map(|l| anon.prepro_lit(l)).collect();
+        self.reconstruct(sol)
+    }
+    /// Gets the value of an assignment for each objective, not including the
+    /// offsets. An assignment in the [original](VarSpace::Original) space is
+    /// evaluated on the instance the preprocessor was initialized with. An
+    /// assignment in the [preprocessed](VarSpace::Preprocessed) space is
+    /// evaluated on the preprocessed instance, including the weight removed by
+    /// preprocessing, so that a solution of the preprocessed instance has at
+    /// least the value of its reconstruction.
+    ///
+    /// # Panics
+    ///
+    /// If the original instance is not known, which is the case for a
+    /// `MaxPre` created from a raw handle
+    fn eval_objectives(&self, sol: &Assignment, space: VarSpace) -> Vec<u64>;
+    /// Gets the best model found by model search during preprocessing,
+    /// reconstructed to the original instance, and its cost for each
+    /// objective, not including the offsets. Returns [`None`] if no model was
+    /// found or the reconstructed model is not a model of the original
+    /// instance.
+    fn best_model(&mut self) -> Option<(Assignment, Vec<u64>)>;
+    /// Gets suggested phases for the variables of the preprocessed instance,
+    /// taken from the best model found by model search or local search, or
+    /// set with [`PreproClauses::set_initial_model`]. These can be used to
+    /// initialize the phases of a SAT solver on the preprocessed instance.
+    /// Only variables that occur in the preprocessed instance and are
+    /// assigned by the model are included, sorted by variable. Returns an
+    /// empty vector if no model is known.
+    fn phase_hints(&self) -> Vec<Lit>;
+    /// Gets whether preprocessing decided the instance, in which case the
+    /// solving phase can be skipped. The instance is unsatisfiable if the
+    /// preprocessed instance contains an empty hard clause and solved if no
+    /// non-empty clauses are left. The model of a solved instance is
+    /// reconstructed to the original instance, its costs do not include the
+    /// offsets. The instance is only decided in the
+    /// [`PreproState::Preprocessed`] state.
+    fn status(&mut self) -> PreproStatus {
+        prepro_status(self)
+    }
+    /// Runs SatLike local search on the current instance for at most
+    /// `time_limit` seconds, starting from the [best model](PreproClauses::best_model)
+    /// if there is one. Only the first objective is considered. Returns the
+    /// model found, reconstructed to the original instance. If the model
+    /// improves on the best model, it also updates the
+    /// [upper bound](PreproClauses::upper_bound).
+    fn local_search(&mut self, time_limit: f64) -> Option<Assignment>;
+    /// Provides a known model of the original instance. If it is better than
+    /// the [best model](PreproClauses::best_model) with respect to the first
+    /// objective, it becomes the best model, updates the
+    /// [upper bound](PreproClauses::upper_bound) and is the starting point
+    /// for [local search](PreproClauses::local_search). Note that the model
+    /// search of MaxPre's techniques does not start from this model.
+    ///
+    /// # Errors
+    ///
+    /// If the model does not satisfy the original hard clauses, as far as they
+    /// are known, or the original instance is not known
+    fn set_initial_model(&mut self, model: Assignment) -> Result<(), Error>;
     /// Adds a new variable to the preprocessor and return the variable
     fn add_var(&mut self) -> Result<Var, Error>;
+    /// Adds `n` new variables to the preprocessor and returns the range of
+    /// the new variables
+    ///
+    /// # Errors
+    ///
+    /// [`Error::InvalidInput`] if `n` exceeds what the preprocessor can
+    /// represent, otherwise if the preprocessor cannot add variables
+    fn add_vars(&mut self, n: usize) -> Result<Range<Var>, Error>;
+    /// Reserves memory for `n` more variables, so that adding them does not
+    /// need to reallocate
+    ///
+    /// # Errors
+    ///
+    /// [`Error::InvalidInput`] if `n` exceeds what the preprocessor can
+    /// represent
+    fn reserve_vars(&mut self, n: usize) -> Result<(), Error>;
     /// Adds a clause to the preprocessor
     fn add_clause(&mut self, clause: Clause) -> Result<(), Error>;
+    /// Adds a clause to the preprocessor that is either part of the instance,
+    /// as with [`PreproClauses::add_clause`], or redundant. Redundant clauses,
+    /// e.g., clauses learned by a solver, are not part of the instance that is
+    /// preprocessed and therefore do not restrict preprocessing. They are kept
+    /// as long as preprocessing does not remove or fix any of their
+    /// variables, see [`PreproClauses::prepro_redundant_clauses`]. MaxPre
+    /// itself has no notion of redundant clauses, so they are kept by the
+    /// wrapper. Only available in inprocessing mode.
+    fn add_clause_with_redundancy(
+        &mut self,
+        clause: Clause,
+        redundancy: Redundancy,
+    ) -> Result<(), Error>;
+    /// Gets the redundant clauses that are still over the variables of the
+    /// preprocessed instance, for passing them back to a solver
+    fn prepro_redundant_clauses(&self) -> Vec<Clause>;
+    /// Declares variables that a solver of the preprocessed instance assumes
+    /// values for, e.g., selectors of clauses added incrementally. These
+    /// variables are not eliminated or substituted by preprocessing, so that
+    /// solving the preprocessed instance under assumptions over them remains
+    /// sound. Assumptions are mapped to the preprocessed instance with
+    /// [`PreproClauses::map_assumption`]. MaxPre has no way of protecting
+    /// single variables, so once any are declared, it only preprocesses with
+    /// the techniques allowed by [`Preservation::Equivalence`] and skips all
+    /// other techniques in the technique string.
+    fn declare_assumption_vars(&mut self, vars: &[Var]);
+    /// Maps an assumption over a variable declared with
+    /// [`PreproClauses::declare_assumption_vars`] to the preprocessed
+    /// instance. Returns [`None`] if the variable was not declared.
+    fn map_assumption(&self, lit: Lit) -> Option<Assumption>;
     /// Adds a label to the preprocessor
     fn add_label(&mut self, label: Lit, weight: usize) -> Result<Lit, Error>;
+    /// Adds a group of soft clauses that share a single weight, i.e., the
+    /// weight is incurred once if any of the clauses is falsified. The group
+    /// is represented by a new label that the clauses are relaxed with. Only
+    /// available in inprocessing mode and for the first objective. As for
+    /// [`PreproClauses::add_label`], a weight of at least the
+    /// [top weight](PreproClauses::top_weight) makes the group hard.
+    fn add_group<CI: IntoIterator<Item = Clause>>(
+        &mut self,
+        obj_idx: usize,
+        clauses: CI,
+        weight: usize,
+    ) -> Result<GroupId, Error>
+    where
+        Self: Sized;
+    /// Adds hard clauses encoding that the value of objective `obj_idx`,
+    /// including its offset, is at most `bound`, with the pseudo-boolean
+    /// encoding `enc` over the labels of the preprocessed instance. The next
+    /// round of preprocessing can then prune solutions that exceed the bound,
+    /// e.g., a bound found by a solver between iterations. Soft clauses that
+    /// are not units are relaxed with a new variable. Only available in
+    /// inprocessing mode, since the encoding needs new variables.
+    ///
+    /// # Errors
+    ///
+    /// If the objective does not exist or variables or clauses cannot be
+    /// added
+    fn add_objective_bound<PBE>(
+        &mut self,
+        obj_idx: usize,
+        bound: isize,
+        enc: &mut PBE,
+    ) -> Result<(), Error>
+    where
+        PBE: pb::BoundUpper + Extend<(Lit, usize)>,
+        Self: Sized,
+    {
+        let (_, mut objs) = self.prepro_instance();
+        if obj_idx >= objs.len() {
+            return Err(Error::Generic);
+        }
+        let (softs, offset) = objs.swap_remove(obj_idx);
+        let Ok(bound) = usize::try_from(bound - offset) else {
+            // the offset alone exceeds the bound
+            return self.add_clause(Clause::new());
+        };
+        let mut lits = Vec::with_capacity(softs.len());
+        for (mut cl, w) in softs {
+            if cl.len() == 1 {
+                lits.push((!cl[0], w));
+            } else {
+                let relax = self.add_var()?.pos_lit();
+                cl.add(relax);
+                self.add_clause(cl)?;
+                lits.push((relax, w));
+            }
+        }
+        enc.extend(lits);
+        let first = self.add_var()?;
+        let mut vm = BasicVarManager::from_next_free(first);
+        let mut cnf = Cnf::new();
+        enc.encode_ub(bound..=bound, &mut cnf, &mut vm)
+            .map_err(|_| Error::Generic)?;
+        // the first variable is already added, add the rest used by the encoding
+        let n = vm
+            .max_var()
+            .map_or(0, |max| max.idx().saturating_sub(first.idx()));
+        if n > 0 && self.add_vars(n)?.start != first + 1 {
+            return Err(Error::Generic);
+        }
+        for cl in cnf {
+            self.add_clause(cl)?;
+        }
+        for unit in enc.enforce_ub(bound).map_err(|_| Error::Generic)? {
+            self.add_clause(Clause::from([unit]))?;
+        }
+        Ok(())
+    }
     /// Alters the weight of a label
     fn alter_weight(&mut self, label: Lit, weight: usize) -> Result<(), Error>;
+    /// Alters the weights of multiple labels at once. All labels are checked
+    /// before any weight is changed, so if an error is returned, no weight was
+    /// altered.
+    fn alter_weights<I: IntoIterator<Item = (Lit, u64)>>(
+        &mut self,
+        updates: I,
+    ) -> Result<(), Error>
+    where
+        Self: Sized;
     /// Turns a label into a normal variable
     fn label_to_var(&mut self, label: Lit) -> Result<(), Error>;
     /// Resets the removed weight
     fn reset_removed_weight(&mut self) -> Result<(), Error>;
-    /// Gets the removed weight
-    fn removed_weight(&mut self) -> Vec<usize>;
+    /// Resets the removed weight of a single objective, keeping the removed
+    /// weight of the other objectives
+    fn reset_removed_weight_for(&mut self, obj_idx: usize) -> Result<(), Error>;
+    /// Gets the weight removed by preprocessing, split by how it was removed
+    fn removed_weight(&mut self) -> RemovedWeight;
+    /// Gets the number of labels and their total weight for each objective,
+    /// before and after preprocessing
+    fn label_stats(&self) -> LabelStats;
+    /// Computes features of the preprocessed instance, e.g., for algorithm
+    /// selection
+    fn features(&mut self) -> Features {
+        let (hards, softs) = self.prepro_instance();
+        Features::of(&hards, &softs)
+    }
+    /// Gets the variable interaction graph of the preprocessed instance as
+    /// adjacency lists. The list at index `i` contains the variables that
+    /// occur in a common clause with the variable with index `i`, sorted.
+    fn interaction_graph(&mut self) -> Vec<Vec<Var>> {
+        let (hards, softs) = self.prepro_instance();
+        graph::interaction(&hards, &softs)
+    }
+    /// Gets the clause-variable incidence graph of the preprocessed instance
+    /// as adjacency lists. The list at index `i` contains the variables of the
+    /// `i`-th clause, sorted, where the clauses are ordered as in
+    /// [`PreproClauses::prepro_instance`] with the hard clauses first and then
+    /// the soft clauses of each objective.
+    fn incidence_graph(&mut self) -> Vec<Vec<Var>> {
+        let (hards, softs) = self.prepro_instance();
+        graph::incidence(&hards, &softs)
+    }
+    /// Sets a lexicographic order of the objectives, from the highest to the
+    /// lowest priority. Everything that only considers the first objective,
+    /// i.e., hardening, model search, the [upper bound](PreproClauses::upper_bound),
+    /// local search and adding labels and groups, considers the objective with
+    /// the highest priority instead. Objectives keep their indices otherwise.
+    /// Setting the order [resets](PreproClauses::reset) the preprocessor.
+    ///
+    /// # Errors
+    ///
+    /// If `order` is not a permutation of the objective indices or the
+    /// preprocessor cannot be reset
+    fn set_objective_order(&mut self, order: &[usize]) -> Result<(), Error>;
+    /// Gets the order of the objectives, from the highest to the lowest
+    /// priority
+    fn objective_order(&self) -> Vec<usize>;
     /// Sets options for the preprocessor
     fn set_options(&mut self, opts: Options);
+    /// Gets the options the preprocessor is configured with. Unlike the
+    /// options passed to [`PreproClauses::set_options`], this includes the
+    /// default values of all options that were not set. Preprocessors without
+    /// options return [`Options::default`].
+    fn options(&self) -> Options;
+    /// Discards all preprocessing and resets the preprocessor to the
+    /// originally loaded instance. Options that were set are kept, clauses,
+    /// labels and variables added through the inprocessing interface are
+    /// dropped. [`MaxPre`](crate::MaxPre) can only be reset if the original
+    /// hard clauses are [kept](crate::MaxPreBuilder::keep_original).
+    ///
+    /// Resetting to the original instance is the only way back, there are no
+    /// snapshots of intermediate states to restore, e.g., before trying
+    /// aggressive inprocessing. MaxPre's state cannot be copied, for the same
+    /// reasons that [`MaxPre`](crate::MaxPre) does not implement [`Clone`],
+    /// and replaying the calls after a reset does not reproduce the state if
+    /// a time limit was hit or randomized techniques were used. To roll back
+    /// inprocessing, keep the instance before the round and create a new
+    /// preprocessor from it.
+    fn reset(&mut self) -> Result<(), Error>;
     /// Prints the preprocessed instance to stdout
     fn print_instance(&self);
+    /// Writes the preprocessed instance in WCNF, or in MCNF if it has
+    /// multiple objectives, without building it in memory first. The offsets
+    /// are not part of the formats and are not written.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails
+    fn write_prepro_dimacs(&self, writer: &mut dyn io::Write) -> io::Result<()>;
     /// Reconstructs a solution and prints it to stdout
     fn print_solution(&self, sol: Assignment, weight: usize);
     /// Prints the reconstruction map to stdout
@@ -100,33 +1120,1773 @@ pub trait PreproClauses {
     fn print_stats(&self);
     /// Gets statistics of the preprocessor
     fn stats(&self) -> Stats;
+    /// Gets statistics for all techniques that were run, accumulated over all
+    /// calls to [`PreproClauses::preprocess`]
+    fn technique_stats(&self) -> Vec<TechniqueStats>;
+    /// Gets the time spent preprocessing, for solvers that subtract it from a
+    /// shared time budget. Times are measured as in [`Stats`].
+    fn elapsed(&self) -> Elapsed {
+        let stats = self.stats();
+        Elapsed {
+            last_call: stats.last_prepro_time,
+            total: stats.prepro_time,
+            techniques: self
+                .technique_stats()
+                .into_iter()
+                .map(|stats| (stats.technique, stats.time))
+                .collect(),
+        }
+    }
+    /// Gets the reconstruction trace, i.e., all operations applied to the
+    /// instance in the order they were applied. Replaying the trace in reverse
+    /// on a model of the preprocessed instance yields the
+    /// [reconstructed](PreproClauses::reconstruct) model.
+    fn trace(&self) -> Vec<TraceOp>;
+    /// Gets the classes of equivalent literals that were merged by
+    /// equivalence substitution, derived from the [`TraceOp::Equal`]
+    /// operations of the [trace](PreproClauses::trace). The first literal of
+    /// each class is the one that the other literals were substituted by, the
+    /// classes are sorted by it.
+    fn equivalences(&self) -> Vec<Vec<Lit>> {
+        // Union-find over variables, each variable is equivalent to the
+        // literal it maps to
+        let mut parent: BTreeMap<Var, Lit> = BTreeMap::new();
+        let find = |parent: &BTreeMap<Var, Lit>, mut lit: Lit| {
+            while let Some(&to) = parent.get(&lit.var()) {
+                lit = if lit.is_neg() { !to } else { to };
+            }
+            lit
+        };
+        for op in self.trace() {
+            let TraceOp::Equal { lit, equal_to } = op else {
+                continue;
+            };
+            let (lit, equal_to) = (find(&parent, lit), find(&parent, equal_to));
+            if lit.var() != equal_to.var() {
+                let to = if lit.is_neg() { !equal_to } else { equal_to };
+                parent.insert(lit.var(), to);
+            }
+        }
+        let mut classes: BTreeMap<Var, Vec<Lit>> = BTreeMap::new();
+        for &var in parent.keys() {
+            let root = find(&parent, var.pos_lit());
+            classes
+                .entry(root.var())
+                .or_insert_with(|| vec![root.var().pos_lit()])
+                .push(var.lit(root.is_neg()));
+        }
+        classes.into_values().collect()
+    }
+    /// Gets the [statistics](PreproClauses::stats) and
+    /// [technique statistics](PreproClauses::technique_stats) as a JSON object
+    /// with the fields `stats` and `techniques`
+    #[cfg(feature = "serde")]
+    fn stats_json(&self) -> String {
+        serde_json::json!({
+            "stats": self.stats(),
+            "techniques": self.technique_stats(),
+        })
+        .to_string()
+    }
+}
+
+/// Initialization of a preprocessor. This is separate from
+/// [`PreproClauses`] so that the latter can be used as a trait object.
+pub trait PreproInit: PreproClauses + Sized {
+    /// Gets the signature of the preprocessor library
+    fn signature() -> &'static str;
+    /// Initializes a new preprocessor with hard clauses and optional multiple
+    /// sets of soft clauses, each with an offset. The instance is not
+    /// validated, see [`MaxPre::try_new`](crate::MaxPre::try_new) for a
+    /// constructor that checks it.
+    ///
+    /// # Panics
+    ///
+    /// If a weight does not fit in [`usize`], which can only happen on targets
+    /// with a [`usize`] narrower than 64 bits.
+    /// [`MaxPre::try_new`](crate::MaxPre::try_new) returns
+    /// [`Error::WeightOverflow`] instead.
+    fn new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>;
+    /// Initializes a new preprocessor as [`PreproInit::new`], with a name
+    /// for each objective, see [`PreproClauses::objective_names`]
+    fn new_named<H, S, CI, N>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize, N)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+        N: Into<String>,
+    {
+        let mut names = vec![];
+        let softs: Vec<_> = softs
+            .into_iter()
+            .map(|(softs, offset, name)| {
+                names.push(Some(name.into()));
+                (softs, offset)
+            })
+            .collect();
+        let mut pp = Self::new(hards, softs, inprocessing);
+        pp.set_objective_names(names);
+        pp
+    }
+    /// Initializes a new single-objective preprocessor for a weighted sum of
+    /// the objectives of the original instance. The values of the individual
+    /// objectives can be recovered with [`Scalarized::objective_values`].
+    ///
+    /// # Errors
+    ///
+    /// If there is not exactly one weight per objective, the weighted sum
+    /// overflows, or the original instance is not known
+    fn scalarize(&self, weights: &[u64]) -> Result<Scalarized<Self>, Error>;
+}
+
+/// A single-objective preprocessor for a weighted sum of objectives, see
+/// [`PreproInit::scalarize`]. Dereferences to the preprocessor.
+pub struct Scalarized<PP> {
+    /// The preprocessor for the weighted sum
+    pp: PP,
+    /// The weights of the objectives
+    weights: Vec<u64>,
+    /// The objectives that were scalarized
+    objs: Vec<(SoftClauses, isize)>,
+}
+
+impl<PP: PreproInit> Scalarized<PP> {
+    /// Initializes a preprocessor for the weighted sum of objectives. Labels
+    /// in the label map are kept for all objectives with a non-zero weight.
+    pub(crate) fn new(
+        hards: Cnf,
+        objs: Vec<(SoftClauses, isize)>,
+        label_map: &RsHashMap<Lit, (usize, Clause)>,
+        weights: &[u64],
+        inprocessing: bool,
+    ) -> Result<Self, Error> {
+        if weights.len() != objs.len() {
+            return Err(Error::Generic);
+        }
+        let mut softs = vec![];
+        let mut offset: isize = 0;
+        for ((obj_softs, obj_offset), &factor) in objs.iter().zip(weights) {
+            if factor == 0 {
+                continue;
+            }
+            for (cl, w) in obj_softs {
+                let w = (*w as u64).checked_mul(factor).ok_or(Error::Generic)?;
+                softs.push((cl.clone(), w));
+            }
+            offset = isize::try_from(factor)
+                .ok()
+                .and_then(|factor| obj_offset.checked_mul(factor))
+                .and_then(|obj_offset| offset.checked_add(obj_offset))
+                .ok_or(Error::Generic)?;
+        }
+        // Check that the top weight MaxPre uses does not overflow
+        softs
+            .iter()
+            .try_fold(1u64, |top, (_, w)| top.checked_add(*w))
+            .ok_or(Error::Generic)?;
+        let mut pp = PP::new(hards, [(softs, offset)], inprocessing);
+        pp.set_label_map(
+            label_map
+                .iter()
+                .filter(|(_, (obj_idx, _))| weights[*obj_idx] > 0)
+                .map(|(&label, (_, cl))| (label, (0, cl.clone())))
+                .collect(),
+        );
+        Ok(Scalarized {
+            pp,
+            weights: weights.to_vec(),
+            objs,
+        })
+    }
+}
+
+impl<PP> Scalarized<PP> {
+    /// Gets the weights of the objectives
+    pub fn weights(&self) -> &[u64] {
+        &self.weights
+    }
+
+    /// Gets the value of each scalarized objective for an assignment of the
+    /// original instance, e.g., a reconstructed solution, not including the
+    /// offsets
+    pub fn objective_values(&self, sol: &Assignment) -> Vec<u64> {
+        costs(&self.objs, sol)
+    }
+
+    /// Gets the preprocessor for the weighted sum
+    pub fn into_inner(self) -> PP {
+        self.pp
+    }
+}
+
+impl<PP> core::ops::Deref for Scalarized<PP> {
+    type Target = PP;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pp
+    }
+}
+
+impl<PP> core::ops::DerefMut for Scalarized<PP> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pp
+    }
+}
+
+/// A preprocessor that only has the soft clauses of the first objective with a
+/// weight of at least a threshold active, for solvers that process the soft
+/// clauses in strata of decreasing weight. The soft clauses below the
+/// threshold are held back and not passed to the preprocessor, the soft
+/// clauses of the other objectives are passed on in full. The preprocessor is
+/// created in inprocessing mode. Dereferences to the preprocessor.
+pub struct Stratified<PP> {
+    /// The preprocessor for the active soft clauses
+    pp: PP,
+    /// The soft clauses of the first objective below the threshold, in
+    /// decreasing order of their weights
+    pending: SoftClauses,
+    /// The minimum weight of the active soft clauses
+    threshold: u64,
+}
+
+impl<PP: PreproInit> Stratified<PP> {
+    /// Initializes a preprocessor over the hard clauses and the soft clauses
+    /// with a weight of at least `threshold`
+    pub fn new<H, S, CI>(hards: H, softs: S, threshold: u64) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let (hards, mut softs) = collect_instance(hards, softs);
+        let mut pending = vec![];
+        if let Some((first, _)) = softs.first_mut() {
+            let (active, below) = std::mem::take(first)
+                .into_iter()
+                .partition(|(_, w)| *w as u64 >= threshold);
+            *first = active;
+            pending = below;
+            pending.sort_by(|(_, w1), (_, w2)| w2.cmp(w1));
+        }
+        let mut pp = PP::new(hards, weighted(softs), true);
+        // Make the preprocessor aware of variables that only appear in held
+        // back soft clauses, so that the labels of activated soft clauses do
+        // not clash with them
+        if let Some(max_var) = pending
+            .iter()
+            .flat_map(|(cl, _)| cl.iter().map(|l| l.var()))
+            .max()
+        {
+            if max_var > pp.max_orig_var() {
+                let Ok(next) = pp.add_var() else {
+                    panic!("preprocessor in inprocessing mode failed to add a variable")
+                };
+                if next < max_var && pp.add_vars(max_var.idx() - next.idx()).is_err() {
+                    panic!("preprocessor in inprocessing mode failed to add variables")
+                }
+            }
+        }
+        Stratified {
+            pp,
+            pending,
+            threshold,
+        }
+    }
+
+    /// Lowers the threshold to `threshold`, activates the held back soft
+    /// clauses with a weight of at least the new threshold, and preprocesses
+    /// the hard clauses and the active soft clauses. Calling this with
+    /// decreasing thresholds interleaves stratification and preprocessing. A
+    /// threshold above the current one does not activate any soft clauses.
+    ///
+    /// Activated soft clauses are added as [groups](PreproClauses::add_group)
+    /// with a new label each and are added to the
+    /// [label map](PreproClauses::label_map). As for any clause added in
+    /// inprocessing mode, the caller is responsible for the preprocessing of
+    /// previous levels not having removed the variables of activated soft
+    /// clauses.
+    ///
+    /// # Errors
+    ///
+    /// If adding an activated soft clause to the preprocessor fails. The soft
+    /// clauses activated before the failure stay active.
+    pub fn preprocess_level(
+        &mut self,
+        threshold: u64,
+        techniques: &str,
+        log_level: c_int,
+        time_limit: f64,
+    ) -> Result<(), Error> {
+        self.threshold = self.threshold.min(threshold);
+        let n_active = self
+            .pending
+            .iter()
+            .take_while(|(_, w)| *w as u64 >= self.threshold)
+            .count();
+        let mut label_map = self.pp.label_map().clone();
+        let mut res = Ok(());
+        let mut n_added = 0;
+        for (cl, w) in &self.pending[..n_active] {
+            match self.pp.add_group(0, [cl.clone()], *w) {
+                Ok(group) => {
+                    label_map.insert(!group.label(), (0, cl.clone()));
+                    n_added += 1;
+                }
+                Err(err) => {
+                    res = Err(err);
+                    break;
+                }
+            }
+        }
+        self.pending.drain(..n_added);
+        self.pp.set_label_map(label_map);
+        res?;
+        self.pp.preprocess(techniques, log_level, time_limit);
+        Ok(())
+    }
+}
+
+impl<PP> Stratified<PP> {
+    /// Gets the minimum weight of the active soft clauses
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    /// Gets the soft clauses that are held back, in decreasing order of their
+    /// weights
+    pub fn pending(&self) -> &[(Clause, usize)] {
+        &self.pending
+    }
+
+    /// Gets the preprocessor for the active soft clauses
+    pub fn into_inner(self) -> PP {
+        self.pp
+    }
+}
+
+impl<PP> core::ops::Deref for Stratified<PP> {
+    type Target = PP;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pp
+    }
+}
+
+impl<PP> core::ops::DerefMut for Stratified<PP> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.pp
+    }
+}
+
+/// Identifier of a group of soft clauses added with
+/// [`PreproClauses::add_group`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(Lit);
+
+impl GroupId {
+    /// Gets the label of the group. The label is true if the group's weight is
+    /// incurred. Unless preprocessing removes the group, the negation of the
+    /// label is one of the [preprocessed labels](PreproClauses::prepro_labels).
+    pub fn label(self) -> Lit {
+        self.0
+    }
+}
+
+/// The states of a preprocessor in the
+/// [inprocessing workflow](PreproClauses#inprocessing), see
+/// [`PreproClauses::state`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreproState {
+    /// The instance was loaded and not preprocessed yet
+    Loaded,
+    /// The instance was preprocessed and not modified since
+    Preprocessed,
+    /// The instance was modified since it was last preprocessed, the
+    /// preprocessed instance might not include the modifications until it is
+    /// preprocessed again
+    Modified,
+}
+
+impl PreproState {
+    /// Records a modification of the instance
+    pub(crate) fn modify(&mut self) {
+        if *self == PreproState::Preprocessed {
+            *self = PreproState::Modified;
+        }
+    }
+}
+
+/// Whether preprocessing decided an instance, see [`PreproClauses::status`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreproStatus {
+    /// Preprocessing did not decide the instance
+    Unknown,
+    /// The instance is unsatisfiable
+    Unsat,
+    /// The instance was solved, with an optimal model and its cost for each
+    /// objective
+    OptimumFound(Assignment, Vec<u64>),
+}
+
+/// An assumption mapped to the preprocessed instance, see
+/// [`PreproClauses::map_assumption`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assumption {
+    /// The literal of the preprocessed instance to assume
+    Lit(Lit),
+    /// The assumption is fixed to true or the variable does not appear in
+    /// the preprocessed instance, so nothing needs to be assumed
+    Satisfied,
+    /// The assumption is fixed to false, so the preprocessed instance is
+    /// unsatisfiable under it
+    Falsified,
 }
 
-/// Options that can be set for MaxPre
-#[derive(Clone, Default)]
+/// Whether a clause added to a preprocessor is part of the instance, see
+/// [`PreproClauses::add_clause_with_redundancy`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Redundancy {
+    /// The clause is part of the instance
+    #[default]
+    Irredundant,
+    /// The clause is implied by the instance, e.g., learned by a solver, and
+    /// can be deleted
+    Redundant,
+}
+
+/// The variables that an assignment is over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VarSpace {
+    /// The variables of the original instance
+    Original,
+    /// The variables of the preprocessed instance
+    Preprocessed,
+}
+
+/// Formats of proofs that MaxPre can log
+#[cfg(feature = "proofs")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofFormat {
+    /// A VeriPB proof where the preprocessed instance is implicit
+    VeriPb,
+    /// A VeriPB proof that certifies the preprocessed instance, as printed by
+    /// [`PreproClauses::print_instance`], as output
+    VeriPbWithOutput,
+}
+
+/// Options that can be set for MaxPre, grouped by the techniques they
+/// configure. Options that are not set keep the value of MaxPre.
+///
+/// The flat fields are from before the options were grouped and are
+/// deprecated. If both a flat field and the corresponding grouped option are
+/// set, the grouped option is used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
 pub struct Options {
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub bve: BveOptions,
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub bbtms: BbtmsOptions,
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub model_search: ModelSearchOptions,
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub labels: LabelOptions,
+    /// Skip techniques that do not simplify the instance for this many
+    /// sampled candidates, 0 never skips
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "skiptechnique", value_name = "SAMPLES", value_parser = cli::parse_skip_technique)
+    )]
+    pub skip_technique: Option<SkipTechnique>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::bve` instead")]
     pub bve_gate_extraction: Option<bool>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::labels` instead")]
     pub label_matching: Option<bool>,
-    pub skip_technique: Option<c_int>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::bve` instead")]
     pub bve_sort_max_first: Option<bool>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::bve` instead")]
     pub bve_local_grow_limit: Option<c_int>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::bve` instead")]
     pub bve_global_grow_limit: Option<c_int>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::bbtms` instead")]
     pub max_bbtms_vars: Option<c_int>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::model_search` instead")]
     pub harden_in_model_search: Option<bool>,
+    #[cfg_attr(feature = "clap", arg(skip))]
+    #[deprecated(note = "use `Options::model_search` instead")]
     pub model_search_iter_limits: Option<c_int>,
 }
 
+/// Options of bounded variable elimination
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct BveOptions {
+    /// Whether to extract gate definitions, MaxPre's default is `false`
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "bvegate", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new())
+    )]
+    pub gate_extraction: Option<bool>,
+    /// Whether to eliminate the variables with the most occurrences first,
+    /// MaxPre's default is `false`
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "bvesortmaxfirst", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new())
+    )]
+    pub sort_max_first: Option<bool>,
+    /// The maximum number of clauses that eliminating a single variable may
+    /// add, MaxPre's default is 0
+    #[cfg_attr(feature = "clap", arg(long = "bvelocalgrow", value_name = "LIMIT"))]
+    pub local_grow_limit: Option<c_int>,
+    /// The maximum number of clauses that elimination may add in total,
+    /// MaxPre's default is 0
+    #[cfg_attr(feature = "clap", arg(long = "bveglobalgrow", value_name = "LIMIT"))]
+    pub global_grow_limit: Option<c_int>,
+}
+
+/// Options of backbone-based techniques
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct BbtmsOptions {
+    /// The maximum number of variables to run the technique on, MaxPre's
+    /// default is 200
+    #[cfg_attr(feature = "clap", arg(long = "BBTMS_maxVars", value_name = "VARS"))]
+    pub max_vars: Option<c_int>,
+}
+
+/// Options of the model search during preprocessing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct ModelSearchOptions {
+    /// Whether to harden soft clauses with the found models, MaxPre's default
+    /// is `false`
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "hardenInModelSearch", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new())
+    )]
+    pub harden: Option<bool>,
+    /// The maximum number of iterations, MaxPre's default of -1 is unlimited
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "modelSearchIterLimit", value_name = "LIMIT")
+    )]
+    pub iter_limit: Option<c_int>,
+}
+
+/// Options of the label techniques
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct LabelOptions {
+    /// Whether to match labels, MaxPre's default is `false`
+    #[cfg_attr(
+        feature = "clap",
+        arg(long = "matchlabels", value_name = "BOOL", value_parser = clap::builder::BoolishValueParser::new())
+    )]
+    pub matching: Option<bool>,
+}
+
+/// Defines builder methods for the fields of an options type
+macro_rules! option_builders {
+    ($ty:ty { $($field:ident: $val:ty),* $(,)? }) => {
+        impl $ty {
+            $(
+                #[doc = concat!("Sets [`", stringify!($field), "`](Self::", stringify!($field), ")")]
+                #[must_use]
+                pub fn $field(mut self, val: $val) -> Self {
+                    self.$field = Some(val);
+                    self
+                }
+            )*
+        }
+    };
+}
+
+option_builders!(BveOptions {
+    gate_extraction: bool,
+    sort_max_first: bool,
+    local_grow_limit: c_int,
+    global_grow_limit: c_int,
+});
+option_builders!(BbtmsOptions { max_vars: c_int });
+option_builders!(ModelSearchOptions {
+    harden: bool,
+    iter_limit: c_int,
+});
+option_builders!(LabelOptions { matching: bool });
+option_builders!(Options {
+    skip_technique: SkipTechnique
+});
+
+impl Options {
+    /// Sets the options of bounded variable elimination
+    #[must_use]
+    pub fn bve(mut self, bve: BveOptions) -> Self {
+        self.bve = bve;
+        self
+    }
+
+    /// Sets the options of backbone-based techniques
+    #[must_use]
+    pub fn bbtms(mut self, bbtms: BbtmsOptions) -> Self {
+        self.bbtms = bbtms;
+        self
+    }
+
+    /// Sets the options of the model search
+    #[must_use]
+    pub fn model_search(mut self, model_search: ModelSearchOptions) -> Self {
+        self.model_search = model_search;
+        self
+    }
+
+    /// Sets the options of the label techniques
+    #[must_use]
+    pub fn labels(mut self, labels: LabelOptions) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Moves the values of the deprecated flat fields to the grouped options,
+    /// unless the grouped option is set
+    #[allow(deprecated)]
+    pub(crate) fn resolved(mut self) -> Options {
+        macro_rules! resolve {
+            ($($flat:ident => $group:ident.$field:ident),*) => {
+                $(
+                    if let Some(val) = self.$flat.take() {
+                        self.$group.$field.get_or_insert(val);
+                    }
+                )*
+            };
+        }
+        resolve!(
+            bve_gate_extraction => bve.gate_extraction,
+            label_matching => labels.matching,
+            bve_sort_max_first => bve.sort_max_first,
+            bve_local_grow_limit => bve.local_grow_limit,
+            bve_global_grow_limit => bve.global_grow_limit,
+            max_bbtms_vars => bbtms.max_vars,
+            harden_in_model_search => model_search.harden,
+            model_search_iter_limits => model_search.iter_limit
+        );
+        self
+    }
+
+    /// Overwrites all options that are set in `other`. Both options must be
+    /// [resolved](Options::resolved).
+    #[cfg_attr(not(feature = "cxx"), allow(dead_code))]
+    pub(crate) fn update(&mut self, other: &Options) {
+        macro_rules! update {
+            ($($($field:ident).+),*) => {
+                $(
+                    if other.$($field).+.is_some() {
+                        self.$($field).+ = other.$($field).+;
+                    }
+                )*
+            };
+        }
+        update!(
+            bve.gate_extraction,
+            bve.sort_max_first,
+            bve.local_grow_limit,
+            bve.global_grow_limit,
+            bbtms.max_vars,
+            model_search.harden,
+            model_search.iter_limit,
+            labels.matching,
+            skip_technique
+        );
+    }
+}
+
+/// Formats the options as flags of the MaxPre command line tool, e.g.,
+/// `-bvegate=1 -skiptechnique=20`. Options that are not set are omitted.
+impl std::fmt::Display for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let opts = self.clone().resolved();
+        let flags = [
+            (
+                "bvegate",
+                opts.bve.gate_extraction.map(|v| u8::from(v).to_string()),
+            ),
+            (
+                "matchlabels",
+                opts.labels.matching.map(|v| u8::from(v).to_string()),
+            ),
+            (
+                "skiptechnique",
+                opts.skip_technique.map(|v| v.c_value().to_string()),
+            ),
+            (
+                "bvesortmaxfirst",
+                opts.bve.sort_max_first.map(|v| u8::from(v).to_string()),
+            ),
+            (
+                "bvelocalgrow",
+                opts.bve.local_grow_limit.map(|v| v.to_string()),
+            ),
+            (
+                "bveglobalgrow",
+                opts.bve.global_grow_limit.map(|v| v.to_string()),
+            ),
+            ("BBTMS_maxVars", opts.bbtms.max_vars.map(|v| v.to_string())),
+            (
+                "hardenInModelSearch",
+                opts.model_search.harden.map(|v| u8::from(v).to_string()),
+            ),
+            (
+                "modelSearchIterLimit",
+                opts.model_search.iter_limit.map(|v| v.to_string()),
+            ),
+        ];
+        let mut first = true;
+        for (flag, val) in flags {
+            let Some(val) = val else {
+                continue;
+            };
+            if !first {
+                write!(f, " ")?;
+            }
+            first = false;
+            write!(f, "-{flag}={val}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses options from flags of the MaxPre command line tool, as written by
+/// the [`Display`](std::fmt::Display) implementation. Flags are separated by
+/// whitespace and may start with one or two dashes.
+impl core::str::FromStr for Options {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_bool(val: &str) -> Option<bool> {
+            match val {
+                "1" | "true" => Some(true),
+                "0" | "false" => Some(false),
+                _ => None,
+            }
+        }
+
+        let mut opts = Options::default();
+        for flag in s.split_whitespace() {
+            let invalid = || ParseError(format!("invalid option flag `{flag}`"));
+            let (name, val) = flag
+                .trim_start_matches('-')
+                .split_once('=')
+                .ok_or_else(invalid)?;
+            match name {
+                "bvegate" => opts.bve.gate_extraction = Some(parse_bool(val).ok_or_else(invalid)?),
+                "matchlabels" => opts.labels.matching = Some(parse_bool(val).ok_or_else(invalid)?),
+                "skiptechnique" => {
+                    opts.skip_technique = Some(SkipTechnique::from_c_value(
+                        val.parse().map_err(|_| invalid())?,
+                    ));
+                }
+                "bvesortmaxfirst" => {
+                    opts.bve.sort_max_first = Some(parse_bool(val).ok_or_else(invalid)?);
+                }
+                "bvelocalgrow" => {
+                    opts.bve.local_grow_limit = Some(val.parse().map_err(|_| invalid())?);
+                }
+                "bveglobalgrow" => {
+                    opts.bve.global_grow_limit = Some(val.parse().map_err(|_| invalid())?);
+                }
+                "BBTMS_maxVars" => opts.bbtms.max_vars = Some(val.parse().map_err(|_| invalid())?),
+                "hardenInModelSearch" => {
+                    opts.model_search.harden = Some(parse_bool(val).ok_or_else(invalid)?);
+                }
+                "modelSearchIterLimit" => {
+                    opts.model_search.iter_limit = Some(val.parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(ParseError(format!("unknown option `{name}`"))),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// The value of an option set by name with [`MaxPre::set_option_by_name`]. The
+/// variant must match the type of the option in MaxPre.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OptionValue {
+    Int(c_int),
+    Bool(bool),
+    Double(f64),
+    U64(u64),
+}
+
+impl From<c_int> for OptionValue {
+    fn from(val: c_int) -> Self {
+        OptionValue::Int(val)
+    }
+}
+
+impl From<bool> for OptionValue {
+    fn from(val: bool) -> Self {
+        OptionValue::Bool(val)
+    }
+}
+
+impl From<f64> for OptionValue {
+    fn from(val: f64) -> Self {
+        OptionValue::Double(val)
+    }
+}
+
+impl From<u64> for OptionValue {
+    fn from(val: u64) -> Self {
+        OptionValue::U64(val)
+    }
+}
+
+/// Whether MaxPre skips techniques that are unlikely to simplify the
+/// instance. This applies to blocked clause elimination, bounded variable
+/// elimination, group-subsumed label elimination, subsumption elimination and
+/// self-subsuming resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SkipTechnique {
+    /// Always run the techniques on all candidates, the default of MaxPre
+    #[default]
+    Never,
+    /// If a technique has at least four times the given number of candidates,
+    /// first try it on that many random candidates and skip it if none of
+    /// them simplify the instance. A sample size of 0 never skips.
+    Sample(u32),
+}
+
+impl SkipTechnique {
+    /// Gets the value of the option in the C API
+    pub(crate) fn c_value(self) -> c_int {
+        match self {
+            SkipTechnique::Never => 0,
+            SkipTechnique::Sample(n) => c_int::try_from(n).unwrap_or(c_int::MAX),
+        }
+    }
+
+    /// Converts a value of the option in the C API
+    pub(crate) fn from_c_value(val: c_int) -> Self {
+        match u32::try_from(val) {
+            Ok(n) if n > 0 => SkipTechnique::Sample(n),
+            _ => SkipTechnique::Never,
+        }
+    }
+}
+
+/// The version of the linked MaxPre library, parsed from its
+/// [signature](PreproInit::signature)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Version {
+    /// The major version, 0 if the signature does not contain a version
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// The commit MaxPre was built from, if known
+    pub git_commit: Option<String>,
+    /// The date and time the library was built at, as given by the C++
+    /// compiler
+    pub build_date: Option<String>,
+    /// The features of this crate that changed how the library was built
+    pub features: Vec<&'static str>,
+}
+
+impl Version {
+    /// Parses a signature of the form `MaxPRE <version> [<tag>] (<parts>,
+    /// <build date>)`, where one of the parts may be a source identifier
+    /// ending in `@<commit>`
+    #[cfg_attr(not(feature = "cxx"), allow(dead_code))]
+    pub(crate) fn parse(signature: &str) -> Version {
+        let (head, details) = match signature.split_once('(') {
+            Some((head, details)) => (head, details.trim_end().trim_end_matches(')')),
+            None => (signature, ""),
+        };
+        let mut numbers = head
+            .split_whitespace()
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or_default()
+            .split('.')
+            .map(|num| num.parse().unwrap_or(0));
+        let mut parts: Vec<&str> = details
+            .split(", ")
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+        let build_date = parts.pop().map(String::from);
+        let git_commit = parts.iter().find_map(|part| {
+            let (_, commit) = part.rsplit_once('@')?;
+            (!commit.is_empty() && commit.chars().all(|c| c.is_ascii_hexdigit()))
+                .then(|| String::from(commit))
+        });
+        let features = [
+            ("vendored", cfg!(feature = "vendored")),
+            ("debug-cxx", cfg!(feature = "debug-cxx")),
+            ("lto", cfg!(feature = "lto")),
+            ("asan", cfg!(feature = "asan")),
+            ("ubsan", cfg!(feature = "ubsan")),
+            ("wasi", cfg!(feature = "wasi")),
+        ]
+        .into_iter()
+        .filter_map(|(feature, enabled)| enabled.then_some(feature))
+        .collect();
+        Version {
+            major: numbers.next().unwrap_or(0),
+            minor: numbers.next().unwrap_or(0),
+            patch: numbers.next().unwrap_or(0),
+            git_commit,
+            build_date,
+            features,
+        }
+    }
+}
+
+/// Weight removed from the objectives by preprocessing, for each objective.
+/// The [incurred](RemovedWeight::incurred) weight is included in the offsets
+/// of the preprocessed instance.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RemovedWeight {
+    /// Weight removed by simplifications other than fixing soft clauses, for
+    /// example of at-most-one constraints over labels. This weight is
+    /// incurred by every solution.
+    pub simplified: Vec<u64>,
+    /// Weight of the original soft clauses that the fixed literals satisfy.
+    /// This weight is never incurred and not part of the offsets.
+    pub hardened: Vec<u64>,
+    /// Weight of the original soft clauses that the fixed literals falsify.
+    /// This weight is incurred by every solution.
+    pub falsified: Vec<u64>,
+}
+
+impl RemovedWeight {
+    /// Splits the total removed weight of each objective, given the original
+    /// objectives and the literals fixed by preprocessing. Falsified weight
+    /// that is not part of the total, for example after
+    /// [`PreproClauses::reset_removed_weight`], is not counted.
+    pub(crate) fn new(objs: &[(SoftClauses, isize)], fixed: &[Lit], total: &[usize]) -> Self {
+        let fixed: Assignment = fixed.iter().copied().collect();
+        let mut removed = RemovedWeight::default();
+        for (obj_idx, &total) in total.iter().enumerate() {
+            let (mut hardened, mut falsified) = (0, 0);
+            if let Some((softs, _)) = objs.get(obj_idx) {
+                for (cl, w) in softs {
+                    match cl.evaluate(&fixed) {
+                        TernaryVal::True => hardened += *w as u64,
+                        TernaryVal::False => falsified += *w as u64,
+                        TernaryVal::DontCare => (),
+                    }
+                }
+            }
+            let falsified = falsified.min(total as u64);
+            removed.simplified.push(total as u64 - falsified);
+            removed.hardened.push(hardened);
+            removed.falsified.push(falsified);
+        }
+        removed
+    }
+
+    /// Gets the removed weight that every solution incurs, i.e., the
+    /// simplified and the falsified weight, for each objective
+    #[must_use]
+    pub fn incurred(&self) -> Vec<u64> {
+        self.simplified
+            .iter()
+            .zip(&self.falsified)
+            .map(|(simplified, falsified)| simplified + falsified)
+            .collect()
+    }
+}
+
+/// The number of labels, i.e., soft clauses, and their total weight for each
+/// objective, before and after preprocessing
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LabelStats {
+    pub n_orig_labels: Vec<usize>,
+    pub orig_weight: Vec<u64>,
+    pub n_prepro_labels: Vec<usize>,
+    pub prepro_weight: Vec<u64>,
+}
+
 /// Statistics of the MaxPre preprocessor
 #[derive(Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Stats {
     pub n_objs: usize,
     pub n_orig_hard_clauses: usize,
     pub n_orig_soft_clauses: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_var"))]
     pub max_orig_var: Option<Var>,
+    pub orig_weight: Vec<usize>,
     pub n_prepro_hard_clauses: usize,
     pub n_prepro_soft_clauses: Vec<usize>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_var"))]
     pub max_prepro_var: Option<Var>,
     pub removed_weight: Vec<usize>,
     pub prepro_time: Duration,
+    /// The time spent in the last call to [`PreproClauses::preprocess`]
+    pub last_prepro_time: Duration,
     pub reconst_time: Duration,
+    /// The names of the objectives, see [`PreproClauses::objective_names`]
+    pub obj_names: Vec<Option<String>>,
+}
+
+/// The largest total weight of the soft clauses of all objectives. The top
+/// weight is one more than the total weight and weights are stored as
+/// [`usize`].
+pub(crate) const MAX_TOTAL_WEIGHT: u64 = usize::MAX as u64 - 1;
+
+/// What a preprocessor supports, see [`PreproClauses::capabilities`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Capabilities {
+    /// Whether the preprocessor is in inprocessing mode
+    pub inprocessing: bool,
+    /// Whether proof logging is compiled in and supported by the
+    /// preprocessor
+    pub proofs: bool,
+    /// The techniques that the preprocessor applies, other techniques in a
+    /// technique string are ignored
+    pub techniques: Vec<Technique>,
+    /// The largest total weight of the soft clauses of all objectives
+    pub max_total_weight: u64,
+    /// The largest index of a variable in the instance
+    pub max_var_idx: u32,
+}
+
+/// Time spent preprocessing, see [`PreproClauses::elapsed`]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Elapsed {
+    /// The time spent in the last call to [`PreproClauses::preprocess`]
+    pub last_call: Duration,
+    /// The time spent in all calls to [`PreproClauses::preprocess`]
+    pub total: Duration,
+    /// The time spent in each technique that was run, over all calls. Only
+    /// the MaxPre preprocessor tracks this.
+    pub techniques: Vec<(Technique, Duration)>,
+}
+
+/// Preprocessing techniques of MaxPre
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Technique {
+    /// Blocked clause elimination
+    Bce,
+    /// Unit propagation
+    Up,
+    /// Bounded variable elimination
+    Bve,
+    /// Subsumption elimination
+    Se,
+    /// Self-subsuming resolution
+    Ssr,
+    /// Subsumed label elimination
+    Sle,
+    /// Binary core removal
+    Bcr,
+    /// Structure-based label elimination
+    Sie,
+    /// Equivalence elimination
+    Ee,
+    /// Bounded variable addition
+    Bva,
+    /// Group subsumed label elimination
+    Gsle,
+    /// Failed literal propagation
+    Flp,
+    /// Unhiding
+    Uh,
+    /// Label substitution
+    Ls,
+    /// At-most-one detection
+    Am1,
+    /// Tight model search
+    Tms,
+    /// Backbone-based tight model search
+    Bbtms,
+    /// Label-based redundancy detection
+    Lred,
+    /// Clause redundancy detection
+    Cred,
+    /// Model-based redundancy detection
+    Mred,
+    /// Unit-based redundancy detection
+    Ured,
+    /// Hardening
+    Hard,
+    /// Failed literal elimination
+    Fle,
+}
+
+impl Technique {
+    /// All techniques
+    pub const ALL: [Technique; 23] = [
+        Technique::Bce,
+        Technique::Up,
+        Technique::Bve,
+        Technique::Se,
+        Technique::Ssr,
+        Technique::Sle,
+        Technique::Bcr,
+        Technique::Sie,
+        Technique::Ee,
+        Technique::Bva,
+        Technique::Gsle,
+        Technique::Flp,
+        Technique::Uh,
+        Technique::Ls,
+        Technique::Am1,
+        Technique::Tms,
+        Technique::Bbtms,
+        Technique::Lred,
+        Technique::Cred,
+        Technique::Mred,
+        Technique::Ured,
+        Technique::Hard,
+        Technique::Fle,
+    ];
+
+    /// Whether the technique introduces auxiliary variables, i.e., bounded
+    /// variable addition and at-most-one detection, which replaces the labels
+    /// of an at-most-one constraint by a new label
+    pub fn introduces_vars(self) -> bool {
+        matches!(self, Technique::Bva | Technique::Am1)
+    }
+
+    /// Gets the character identifying the technique in a technique string
+    pub fn char(self) -> char {
+        match self {
+            Technique::Bce => 'b',
+            Technique::Up => 'u',
+            Technique::Bve => 'v',
+            Technique::Se => 's',
+            Technique::Ssr => 'r',
+            Technique::Sle => 'l',
+            Technique::Bcr => 'c',
+            Technique::Sie => 'i',
+            Technique::Ee => 'e',
+            Technique::Bva => 'a',
+            Technique::Gsle => 'g',
+            Technique::Flp => 'p',
+            Technique::Uh => 'h',
+            Technique::Ls => 't',
+            Technique::Am1 => 'm',
+            Technique::Tms => 'T',
+            Technique::Bbtms => 'V',
+            Technique::Lred => 'd',
+            Technique::Cred => 'D',
+            Technique::Mred => 'M',
+            Technique::Ured => 'L',
+            Technique::Hard => 'H',
+            Technique::Fle => 'U',
+        }
+    }
+}
+
+/// What preprocessing preserves of the instance, which restricts the
+/// techniques that are applied
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Preservation {
+    /// The optimal cost and satisfiability, which allows all techniques
+    #[default]
+    Satisfiability,
+    /// The models of the instance, each model of the preprocessed instance is
+    /// reconstructed to a single model of the instance and all models of the
+    /// instance are obtained this way. This allows techniques that only
+    /// remove implied clauses or substitute equivalent literals, which is
+    /// required for enumerating or counting models.
+    Models,
+    /// The equivalence of the instance, the preprocessed instance is the
+    /// instance with fixed literals and implied clauses removed
+    Equivalence,
+}
+
+impl Preservation {
+    /// Whether a technique preserves what is to be preserved
+    pub fn allows(self, technique: Technique) -> bool {
+        match self {
+            Preservation::Satisfiability => true,
+            Preservation::Models => {
+                matches!(technique, Technique::Ee | Technique::Uh)
+                    || Preservation::Equivalence.allows(technique)
+            }
+            Preservation::Equivalence => matches!(
+                technique,
+                Technique::Up | Technique::Se | Technique::Ssr | Technique::Flp
+            ),
+        }
+    }
+}
+
+/// A technique string for [`PreproClauses::preprocess`]. Each character is a
+/// [technique](Technique), techniques in brackets are applied until none of
+/// them changes the instance, and techniques before `#` are applied before
+/// labels are added. Apart from [`Techniques::DEFAULT`], the presets are
+/// choices of this crate and are not documented or recommended by MaxPre.
+///
+/// Techniques can additionally have a [budget](TechniqueBudget). MaxPre only
+/// has a global time limit, so budgets are enforced by
+/// [`PreproClauses::preprocess_budgeted`], which leaves out techniques whose
+/// budget is used up, and are not part of the technique string.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Techniques {
+    string: Cow<'static, str>,
+    budgets: Vec<(Technique, TechniqueBudget)>,
+}
+
+impl Techniques {
+    /// The default techniques of the MaxPre command line tool
+    pub const DEFAULT: Techniques = Techniques::preset("[bu]#[buvsrgc]");
+    /// This crate's choice for weighted instances, the default techniques and
+    /// additionally hardening and at-most-one detection with greedy weights
+    pub const WEIGHTED: Techniques = Techniques::preset("[bu]#[buvsrgcHG]");
+    /// This crate's choice for unweighted instances, the default techniques
+    /// and additionally at-most-one detection with greedy weights
+    pub const UNWEIGHTED: Techniques = Techniques::preset("[bu]#[buvsrgcG]");
+    /// This crate's choice of only the cheap techniques blocked clause
+    /// elimination, unit propagation and subsumption elimination
+    pub const LIGHT: Techniques = Techniques::preset("[bu]#[bus]");
+    /// This crate's choice of an expensive preset, the default techniques and
+    /// additionally the model search techniques and failed literal
+    /// elimination. It does not contain all techniques of MaxPre, e.g.,
+    /// `l`, `a`, `e`, `h` and `t` are left out.
+    pub const AGGRESSIVE: Techniques = Techniques::preset("[bu]#[buvsrgcHTVGR]");
+
+    const fn preset(techniques: &'static str) -> Self {
+        Techniques {
+            string: Cow::Borrowed(techniques),
+            budgets: Vec::new(),
+        }
+    }
+
+    /// Creates a technique string. The string is passed to MaxPre as is.
+    pub fn new<S: Into<Cow<'static, str>>>(techniques: S) -> Self {
+        Techniques {
+            string: techniques.into(),
+            budgets: Vec::new(),
+        }
+    }
+
+    /// Gets the technique string
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// Sets the budget of a technique, replacing an earlier budget of it
+    #[must_use]
+    pub fn budget(mut self, technique: Technique, budget: TechniqueBudget) -> Self {
+        self.budgets.retain(|(t, _)| *t != technique);
+        self.budgets.push((technique, budget));
+        self
+    }
+
+    /// Gets the budget of a technique, which is unlimited if none was set
+    pub fn budget_of(&self, technique: Technique) -> TechniqueBudget {
+        self.budgets
+            .iter()
+            .find(|(t, _)| *t == technique)
+            .map_or(TechniqueBudget::default(), |(_, b)| *b)
+    }
+
+    /// Removes the techniques for which `keep` returns false, including
+    /// groups that become empty, which MaxPre does not accept. Returns
+    /// [`None`] if no technique is left.
+    #[must_use]
+    pub fn retain<F: FnMut(Technique) -> bool>(&self, mut keep: F) -> Option<Techniques> {
+        let mut any = false;
+        let mut string: String = self
+            .string
+            .chars()
+            .filter(|&c| match TechniqueInfo::from_char(c) {
+                Some(info) if keep(info.technique) => {
+                    any = true;
+                    true
+                }
+                Some(_) => false,
+                None => true,
+            })
+            .collect();
+        while string.contains("[]") {
+            string = string.replace("[]", "");
+        }
+        any.then(|| Techniques {
+            string: Cow::Owned(string),
+            budgets: self.budgets.clone(),
+        })
+    }
+}
+
+impl Default for Techniques {
+    fn default() -> Self {
+        Techniques::DEFAULT
+    }
+}
+
+impl core::ops::Deref for Techniques {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.string
+    }
+}
+
+impl AsRef<str> for Techniques {
+    fn as_ref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl std::fmt::Display for Techniques {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.string)
+    }
+}
+
+/// Parses a technique string, checking that it only contains known
+/// techniques, that the brackets are balanced and that `#` appears at most
+/// once outside of brackets
+impl core::str::FromStr for Techniques {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut depth = 0usize;
+        let mut seen_sep = false;
+        for c in s.chars() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .ok_or_else(|| ParseError(format!("unbalanced `]` in `{s}`")))?;
+                }
+                '#' if depth == 0 && !seen_sep => seen_sep = true,
+                '#' => return Err(ParseError(format!("misplaced `#` in `{s}`"))),
+                c if TechniqueInfo::from_char(c).is_some() => {}
+                c => return Err(ParseError(format!("unknown technique `{c}` in `{s}`"))),
+            }
+        }
+        if depth > 0 {
+            return Err(ParseError(format!("unbalanced `[` in `{s}`")));
+        }
+        Ok(Techniques::new(String::from(s)))
+    }
+}
+
+/// A preprocessing plan chosen by [`PreproClauses::preprocess_auto`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoPlan {
+    /// The techniques with their budgets
+    pub techniques: Techniques,
+    /// The time limit of preprocessing in seconds
+    pub time_limit: f64,
+    /// Why the techniques were chosen
+    pub reasons: Vec<&'static str>,
+}
+
+impl AutoPlan {
+    /// Instances with at most this many clauses are small
+    pub const SMALL_CLAUSES: usize = 50_000;
+    /// Instances with more than this many clauses are large
+    pub const LARGE_CLAUSES: usize = 1_000_000;
+    /// Instances with more than this many clauses are huge
+    pub const HUGE_CLAUSES: usize = 10_000_000;
+
+    /// Chooses techniques for an instance with `n_clauses` clauses and
+    /// `n_objs` objectives. Small single-objective instances are preprocessed
+    /// with [`Techniques::AGGRESSIVE`], where the model search techniques are
+    /// only run in one call. Otherwise, [`Techniques::WEIGHTED`] or
+    /// [`Techniques::UNWEIGHTED`] are used for single-objective instances and
+    /// [`Techniques::DEFAULT`] for multiple objectives, since hardening and
+    /// model search only consider the first objective. Large instances use
+    /// [`Techniques::DEFAULT`] with variable elimination limited to half of
+    /// the time limit, and huge ones only [`Techniques::LIGHT`].
+    pub fn choose(n_clauses: usize, n_objs: usize, weighted: bool, time_limit: f64) -> Self {
+        let mut reasons = vec![];
+        let techniques = if n_clauses > Self::HUGE_CLAUSES {
+            reasons.push("huge instance, only cheap techniques");
+            Techniques::LIGHT
+        } else if n_clauses > Self::LARGE_CLAUSES {
+            reasons.push("large instance, variable elimination is time limited");
+            Techniques::DEFAULT.budget(
+                Technique::Bve,
+                TechniqueBudget::default().max_time(Duration::from_secs_f64(time_limit / 2.)),
+            )
+        } else if n_objs > 1 {
+            reasons.push("multiple objectives, no objective-specific techniques");
+            Techniques::DEFAULT
+        } else if n_clauses <= Self::SMALL_CLAUSES {
+            reasons.push("small instance, model search is run once");
+            let once = TechniqueBudget::default().max_applications(1);
+            Techniques::AGGRESSIVE
+                .budget(Technique::Tms, once)
+                .budget(Technique::Bbtms, once)
+        } else if weighted {
+            reasons.push("weighted instance, hardening is used");
+            Techniques::WEIGHTED
+        } else {
+            reasons.push("unweighted instance");
+            Techniques::UNWEIGHTED
+        };
+        AutoPlan {
+            techniques,
+            time_limit,
+            reasons,
+        }
+    }
+}
+
+/// Limits of a single technique over all calls of
+/// [`PreproClauses::preprocess_budgeted`] on a preprocessor
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TechniqueBudget {
+    /// The maximum time spent in the technique, as reported by
+    /// [`PreproClauses::technique_stats`]. The technique is left out once
+    /// the time is used up, but it is not interrupted within a call.
+    pub max_time: Option<Duration>,
+    /// The maximum number of preprocessing calls the technique is run in
+    pub max_applications: Option<usize>,
+}
+
+impl TechniqueBudget {
+    /// Limits the time spent in the technique
+    #[must_use]
+    pub fn max_time(mut self, time: Duration) -> Self {
+        self.max_time = Some(time);
+        self
+    }
+
+    /// Limits the number of preprocessing calls the technique is run in
+    #[must_use]
+    pub fn max_applications(mut self, applications: usize) -> Self {
+        self.max_applications = Some(applications);
+        self
+    }
+
+    /// Checks whether the budget is used up after `applications` calls that
+    /// spent `time` in the technique
+    pub fn is_exhausted(&self, applications: usize, time: Duration) -> bool {
+        self.max_applications.is_some_and(|max| applications >= max)
+            || self.max_time.is_some_and(|max| time >= max)
+    }
+}
+
+/// Counts a run of each technique in a technique string
+pub(crate) fn count_runs(runs: &mut RsHashMap<Technique, usize>, techniques: &str) {
+    for technique in techniques_in(techniques) {
+        *runs.entry(technique).or_default() += 1;
+    }
+}
+
+/// The distinct techniques that occur in a technique string, sorted by their
+/// characters
+pub(crate) fn techniques_in(techniques: &str) -> Vec<Technique> {
+    let mut seen: Vec<Technique> = techniques
+        .chars()
+        .filter_map(|c| TechniqueInfo::from_char(c).map(|info| info.technique))
+        .collect();
+    seen.sort_unstable_by_key(|t| t.char());
+    seen.dedup();
+    seen
+}
+
+/// Information on a technique that can be used in a technique string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TechniqueInfo {
+    pub technique: Technique,
+    /// The character identifying the technique in a technique string. Some
+    /// techniques have multiple variants with different characters.
+    pub character: char,
+    /// The abbreviation MaxPre uses for the technique
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+impl TechniqueInfo {
+    /// Gets the information on the technique with a character, if it is
+    /// known to the bindings
+    pub(crate) fn from_char(character: char) -> Option<TechniqueInfo> {
+        let (technique, name, description) = match character {
+            'b' => (Technique::Bce, "BCE", "Blocked clause elimination"),
+            'u' => (Technique::Up, "UP", "Unit propagation"),
+            'v' => (Technique::Bve, "BVE", "Bounded variable elimination"),
+            's' => (Technique::Se, "SE", "Subsumption elimination"),
+            'r' => (Technique::Ssr, "SSR", "Self-subsuming resolution"),
+            'l' => (Technique::Sle, "SLE", "Subsumed label elimination"),
+            'c' => (Technique::Bcr, "BCR", "Binary core removal"),
+            'i' => (Technique::Sie, "SIE", "Structure-based label elimination"),
+            'e' => (Technique::Ee, "EE", "Equivalence elimination"),
+            'a' => (Technique::Bva, "BVA", "Bounded variable addition"),
+            'g' => (Technique::Gsle, "GSLE", "Group subsumed label elimination"),
+            'p' => (Technique::Flp, "FLP", "Failed literal propagation"),
+            'h' => (Technique::Uh, "UH", "Unhiding"),
+            't' => (Technique::Ls, "LS", "Label substitution"),
+            'm' => (Technique::Am1, "AM1", "At-most-one detection"),
+            'G' => (
+                Technique::Am1,
+                "AM1",
+                "At-most-one detection with greedy weights",
+            ),
+            'S' => (
+                Technique::Am1,
+                "AM1",
+                "At-most-one detection with greedy weights and stratification",
+            ),
+            'Q' => (
+                Technique::Am1,
+                "AM1",
+                "At-most-one detection with greedy weights and stratification, repeated until \
+                 no more are found",
+            ),
+            'T' => (Technique::Tms, "TMS", "Tight model search"),
+            'V' => (
+                Technique::Bbtms,
+                "BBTMS",
+                "Backbone-based tight model search",
+            ),
+            'd' => (Technique::Lred, "LRED", "Label-based redundancy detection"),
+            'D' => (Technique::Cred, "CRED", "Clause redundancy detection"),
+            'M' => (Technique::Mred, "MRED", "Model-based redundancy detection"),
+            'L' => (Technique::Ured, "URED", "Unit-based redundancy detection"),
+            'H' => (Technique::Hard, "HARD", "Hardening"),
+            'U' => (Technique::Fle, "FLE", "Failed literal elimination"),
+            'R' => (
+                Technique::Fle,
+                "FLE",
+                "Failed literal elimination with redundancy detection",
+            ),
+            _ => return None,
+        };
+        Some(TechniqueInfo {
+            technique,
+            character,
+            name,
+            description,
+        })
+    }
+}
+
+/// Statistics of a single preprocessing technique
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TechniqueStats {
+    pub technique: Technique,
+    /// The time spent in the technique
+    pub time: Duration,
+    /// The number of times the technique was run. MaxPre only tracks this if
+    /// preprocessing is time limited.
+    pub invocations: Option<usize>,
+    /// The net number of removed clauses. Techniques that add clauses can
+    /// make this negative.
+    pub removed_clauses: isize,
+    /// The net number of removed variables
+    pub removed_vars: isize,
+    /// The net number of removed literals
+    pub removed_lits: isize,
+    /// The net number of removed labels
+    pub removed_labels: isize,
+    /// Whether the technique used up the time allocated to it
+    pub hit_limit: bool,
+}
+
+/// The result of running a single technique with [`PreproClauses::apply`].
+/// The statistics are the changes of the
+/// [technique statistics](PreproClauses::technique_stats) in the run, they
+/// are zero for preprocessors that do not report technique statistics.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RoundResult {
+    pub technique: Technique,
+    /// Whether the technique was run, it is not if its budget is used up
+    pub ran: bool,
+    /// The time spent in the technique
+    pub time: Duration,
+    /// The net number of removed clauses
+    pub removed_clauses: isize,
+    /// The net number of removed variables
+    pub removed_vars: isize,
+    /// The net number of removed literals
+    pub removed_lits: isize,
+    /// The net number of removed labels
+    pub removed_labels: isize,
+    /// Whether the technique used up the time allocated to it
+    pub hit_limit: bool,
+}
+
+/// Summary of a preprocessing round of
+/// [`PreproClauses::preprocess_until_fixpoint`]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RoundSummary {
+    /// The number of clauses after the round
+    pub n_clauses: usize,
+    /// The number of variables after the round
+    pub n_vars: usize,
+    /// The fraction of clauses removed in the round, relative to the instance
+    /// before the round, which is the original instance for the first round.
+    /// This is negative if the round added clauses.
+    pub reduction: f64,
+    /// The time spent in the round, measured as in [`Stats`]
+    pub time: Duration,
+    /// The techniques that did not get to run because the time limit ran
+    /// out, in the order of the technique string. These are the techniques
+    /// that only occur after the last technique that was run and whose
+    /// [invocation count and time](TechniqueStats) did not change either.
+    /// MaxPre counts an invocation when a technique requests time, also if
+    /// the request is denied, so a technique that the schedule reaches after
+    /// the time limit ran out is not reported here.
+    pub skipped_techniques: Vec<Technique>,
+    /// The position in the technique string where the time limit cut off the
+    /// schedule, [`None`] if it did not run out or every technique was run
+    pub cutoff: Option<usize>,
+}
+
+/// Determines where the time limit cut off a technique string, given which
+/// techniques were run. Returns the techniques that never ran and the
+/// position of the cutoff, see [`RoundSummary::skipped_techniques`].
+pub(crate) fn schedule_cutoff<F: Fn(Technique) -> bool>(
+    techniques: &str,
+    ran: F,
+) -> (Vec<Technique>, Option<usize>) {
+    let schedule: Vec<(usize, Technique)> = techniques
+        .char_indices()
+        .filter_map(|(pos, c)| Some((pos, TechniqueInfo::from_char(c)?.technique)))
+        .collect();
+    let first = schedule
+        .iter()
+        .rposition(|&(_, t)| ran(t))
+        .map_or(0, |idx| idx + 1);
+    let Some(&(cutoff, _)) = schedule.get(first) else {
+        return (vec![], None);
+    };
+    let mut skipped = vec![];
+    for &(_, technique) in &schedule[first..] {
+        if !ran(technique)
+            && !schedule[..first].iter().any(|&(_, t)| t == technique)
+            && !skipped.contains(&technique)
+        {
+            skipped.push(technique);
+        }
+    }
+    (skipped, Some(cutoff))
+}
+
+/// A single operation of the reconstruction trace of MaxPre. All literals are
+/// over the internal variables of MaxPre. These coincide with the original
+/// variables of the instance, variables with a higher index were introduced
+/// during preprocessing.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TraceOp {
+    /// A variable was fixed to the value of `lit`
+    Fix { lit: Lit },
+    /// Bounded variable elimination of `var`. `clauses` are the clauses that
+    /// contained the negation of `var`, without that literal. When
+    /// reconstructing, `var` is set to false if one of them is falsified and
+    /// to true otherwise.
+    Bve { var: Var, clauses: Vec<Clause> },
+    /// Blocked clause elimination of the clause `lit` and `clause`. When
+    /// reconstructing, `lit` is set to true if `clause` is falsified.
+    Bce { lit: Lit, clause: Clause },
+    /// Binary core removal merging the labels `lbl1` and `lbl2`. `clauses`
+    /// are the removed clauses, without the variable of `lbl1`.
+    Bcr {
+        lbl1: Lit,
+        lbl2: Lit,
+        clauses: Vec<Clause>,
+    },
+    /// `lit` was substituted by the equivalent literal `equal_to`
+    Equal { lit: Lit, equal_to: Lit },
+    /// Label substitution of the clause `lit` and `clause`. When
+    /// reconstructing with `label` true, `lit` is set to true if `clause` is
+    /// falsified.
+    Ls {
+        label: Lit,
+        lit: Lit,
+        clause: Clause,
+    },
+    /// Label elimination of `lbl1` and `lbl2` with the tautological literal
+    /// `taut`
+    LabelElim { lbl1: Lit, lbl2: Lit, taut: Lit },
+}
+
+impl TraceOp {
+    /// Gets the variables that the operation assigns or depends on when
+    /// reconstructing
+    pub fn vars(&self) -> Vec<Var> {
+        let vars = |cl: &Clause| cl.iter().map(|l| l.var()).collect::<Vec<_>>();
+        match self {
+            TraceOp::Fix { lit } => vec![lit.var()],
+            TraceOp::Bve { var, clauses } => clauses.iter().flat_map(vars).chain([*var]).collect(),
+            TraceOp::Bce { lit, clause } => vars(clause).into_iter().chain([lit.var()]).collect(),
+            TraceOp::Bcr {
+                lbl1,
+                lbl2,
+                clauses,
+            } => clauses
+                .iter()
+                .flat_map(vars)
+                .chain([lbl1.var(), lbl2.var()])
+                .collect(),
+            TraceOp::Equal { lit, equal_to } => vec![lit.var(), equal_to.var()],
+            TraceOp::Ls { label, lit, clause } => vars(clause)
+                .into_iter()
+                .chain([label.var(), lit.var()])
+                .collect(),
+            TraceOp::LabelElim { lbl1, lbl2, taut } => {
+                vec![lbl1.var(), lbl2.var(), taut.var()]
+            }
+        }
+    }
+}
+
+/// Where a clause of the preprocessed instance comes from, see
+/// [`MaxPre::track_provenance`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClauseOrigin {
+    /// The clause is a clause of the original instance
+    Original,
+    /// The clause was added through the inprocessing interface after `round`
+    /// preprocessing calls
+    Added { round: usize },
+    /// The clause was produced or last modified by the preprocessing call with
+    /// index `round`, which ran `techniques`. MaxPre does not report which of
+    /// the techniques of a call changed a clause, so the technique is only
+    /// determined if the call ran a single technique.
+    Preprocessed {
+        round: usize,
+        techniques: Vec<Technique>,
+    },
+}
+
+/// The origins of the clauses of the preprocessed instance, in the same order
+/// as [`PreproClauses::prepro_instance`] returns the clauses
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// The origin of each hard clause
+    pub hards: Vec<ClauseOrigin>,
+    /// The origin of each soft clause, for each objective
+    pub softs: Vec<Vec<ClauseOrigin>>,
+}
+
+/// Serializes a variable by its index
+#[cfg(feature = "serde")]
+fn serialize_var<S: serde::Serializer>(
+    var: &Option<Var>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serde::Serialize::serialize(&var.map(|v| v.idx32()), serializer)
 }
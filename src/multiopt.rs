@@ -1,16 +1,19 @@
 //! # Interface for Preprocessing [`rustsat::instances::MultiOptInstance`] types
 
 use rustsat::{
-    encodings::{card, pb},
-    instances::{Cnf, ManageVars, MultiOptInstance, Objective, SatInstance},
-    types::constraints::{CardConstraint, PBConstraint},
+    instances::{Cnf, ManageVars, MultiOptInstance, ReindexingVarManager},
+    types::{
+        constraints::{CardConstraint, PBConstraint},
+        Assignment, TernaryVal,
+    },
 };
 
-use crate::PreproClauses;
+use crate::{Prepro, PreproClauses, PreproInit};
 
 pub trait PreproMultiOpt: PreproClauses {
     /// Initializes a new preprocessor from a [`MultioptInstance`] where the instance
-    /// is converted to [`CNF`] with the given encoders.
+    /// is converted to [`CNF`] with the given encoders. Non-unit soft clauses
+    /// are relaxed as for [`PreproOpt::new_with_encoders`](crate::PreproOpt).
     fn new_with_encoders<VM, CardEnc, PBEnc>(
         inst: MultiOptInstance<VM>,
         card_encoder: CardEnc,
@@ -21,46 +24,142 @@ pub trait PreproMultiOpt: PreproClauses {
         VM: ManageVars,
         CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
         PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
-        Self: Sized,
+        Self: PreproInit,
     {
-        let (constrs, objs) = inst.decompose();
-        let (cnf, _) = constrs.into_cnf_with_encoders(card_encoder, pb_encoder);
-        let softs: Vec<(_, isize)> = objs.into_iter().map(|o| o.into_soft_cls()).collect();
-        <Self as PreproClauses>::new(cnf, softs, inprocessing)
+        Prepro::load_with_encoders(inst, card_encoder, pb_encoder, inprocessing)
     }
     /// Initializes a new preprocessor from a [`SatInstance`]
     fn new<VM>(inst: MultiOptInstance<VM>, inprocessing: bool) -> Self
     where
         VM: ManageVars,
-        Self: Sized,
+        Self: PreproInit,
     {
-        Self::new_with_encoders(
-            inst,
-            |constr, cnf, vm| {
-                card::default_encode_cardinality_constraint(constr, cnf, vm)
-                    .expect("cardinality encoding ran out of memory")
-            },
-            |constr, cnf, vm| {
-                pb::default_encode_pb_constraint(constr, cnf, vm)
-                    .expect("pb encoding ran out of memory")
-            },
-            inprocessing,
-        )
+        Prepro::load(inst, inprocessing)
     }
-    /// Gets the preprocessed instance as a [`SatInstance`]
+    /// Gets the preprocessed instance as a [`MultiOptInstance`]
     fn prepro_instance(&mut self) -> MultiOptInstance {
-        let (cnf, objs) = <Self as PreproClauses>::prepro_instance(self);
-        let constrs = SatInstance::from(cnf);
-        let objs = objs
-            .into_iter()
-            .map(|(softs, offset)| {
-                let mut obj = Objective::from_iter(softs);
-                obj.set_offset(offset);
-                obj
+        Prepro::<MultiOptInstance>::extract(self)
+    }
+    /// Gets the preprocessed instance as a [`MultiOptInstance`] with the
+    /// given variable manager, see
+    /// [`PreproOpt::prepro_instance_with_vm`](crate::PreproOpt::prepro_instance_with_vm)
+    fn prepro_instance_with_vm<VM: ManageVars>(&mut self, vm: VM) -> MultiOptInstance<VM>
+    where
+        Self: Sized,
+    {
+        Prepro::<MultiOptInstance>::extract_with_vm(self, vm)
+    }
+    /// Gets the preprocessed instance with its variables renumbered to a dense
+    /// range, see [`PreproClauses::prepro_instance_reindexed`]
+    fn prepro_instance_reindexed(&mut self) -> MultiOptInstance<ReindexingVarManager> {
+        <Self as PreproMultiOpt>::prepro_instance(self)
+            .reindex_ordered(ReindexingVarManager::default())
+    }
+    /// Verifies a solution of the preprocessed instance against the original
+    /// instance. The solution is reconstructed, checked to satisfy the
+    /// constraints of `orig`, and the cost of the reconstructed solution is
+    /// checked to not exceed the cost of the solution in the preprocessed
+    /// instance. Returns the costs of the reconstructed solution for all
+    /// objectives, not including the objective offsets.
+    ///
+    /// # Errors
+    ///
+    /// If any of the checks fail, see [`VerifyError`]
+    fn verify<VM: ManageVars>(
+        &mut self,
+        orig: &MultiOptInstance<VM>,
+        prepro_sol: &Assignment,
+    ) -> Result<Vec<u64>, VerifyError>
+    where
+        Self: Sized,
+    {
+        let (hards, objs) = <Self as PreproClauses>::prepro_instance(self);
+        if hards.evaluate(prepro_sol) != TernaryVal::True {
+            return Err(VerifyError::PreproUnsat);
+        }
+        let rec = self.reconstruct(prepro_sol.clone());
+        if orig.constraints_ref().evaluate(&rec) != TernaryVal::True {
+            return Err(VerifyError::Unsat);
+        }
+        if objs.len() != orig.n_objectives() {
+            return Err(VerifyError::ObjectiveCount {
+                orig: orig.n_objectives(),
+                prepro: objs.len(),
+            });
+        }
+        orig.iter_obj()
+            .zip(objs)
+            .enumerate()
+            .map(|(obj_idx, (obj, (softs, offset)))| {
+                let prepro_cost: isize = softs
+                    .iter()
+                    .filter(|(cl, _)| cl.evaluate(prepro_sol) != TernaryVal::True)
+                    .map(|(_, w)| *w as isize)
+                    .sum::<isize>()
+                    + offset;
+                let cost = obj.evaluate_no_offset(&rec);
+                let orig_cost = cost as isize + obj.offset();
+                if orig_cost > prepro_cost {
+                    return Err(VerifyError::Cost {
+                        obj_idx,
+                        orig: orig_cost,
+                        prepro: prepro_cost,
+                    });
+                }
+                Ok(cost as u64)
             })
-            .collect();
-        MultiOptInstance::compose(constrs, objs)
+            .collect()
     }
 }
 
 impl<PP: PreproClauses> PreproMultiOpt for PP {}
+
+/// Errors from [`PreproMultiOpt::verify`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The solution does not satisfy the hard clauses of the preprocessed
+    /// instance
+    PreproUnsat,
+    /// The reconstructed solution does not satisfy the constraints of the
+    /// original instance
+    Unsat,
+    /// The original and the preprocessed instance have a different number of
+    /// objectives
+    ObjectiveCount { orig: usize, prepro: usize },
+    /// The cost of the reconstructed solution in the original instance is
+    /// higher than the cost of the solution in the preprocessed instance.
+    /// Costs include the objective offsets.
+    Cost {
+        obj_idx: usize,
+        orig: isize,
+        prepro: isize,
+    },
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::PreproUnsat => {
+                write!(f, "solution does not satisfy the preprocessed instance")
+            }
+            VerifyError::Unsat => write!(
+                f,
+                "reconstructed solution does not satisfy the original instance"
+            ),
+            VerifyError::ObjectiveCount { orig, prepro } => write!(
+                f,
+                "original instance has {orig} objectives, preprocessed instance has {prepro}"
+            ),
+            VerifyError::Cost {
+                obj_idx,
+                orig,
+                prepro,
+            } => write!(
+                f,
+                "reconstructed cost {orig} of objective {obj_idx} exceeds preprocessed cost {prepro}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
@@ -0,0 +1,649 @@
+//! # Native Rust Preprocessor
+//!
+//! A small preprocessor written in Rust that does not depend on the C++
+//! build of MaxPre. Without the `cxx` feature, this is the only
+//! preprocessor, otherwise it serves as a simple baseline.
+
+use core::{ffi::c_int, ops::Range};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+#[cfg(not(target_os = "wasi"))]
+use cpu_time::ProcessTime;
+use rustsat::{
+    instances::Cnf,
+    types::{Assignment, Clause, Lit, RsHashMap, TernaryVal, Var},
+};
+#[cfg(target_os = "wasi")]
+use std::time::Instant as ProcessTime;
+
+use crate::{
+    Assumption, Capabilities, Error, GroupId, LabelStats, NoopPrepro, Options, PreproClauses,
+    PreproInit, PreproState, Redundancy, RemovedWeight, Scalarized, SoftClauses, Stats, Technique,
+    TechniqueStats, TraceOp, VarSpace,
+};
+
+/// A preprocessor implemented in Rust. It removes tautologies and duplicate
+/// literals, removes duplicate hard clauses, aggregates the weights of
+/// duplicate soft clauses and, if the technique string contains
+/// [`Technique::Up`], propagates hard unit clauses. All other techniques in
+/// the technique string are ignored.
+pub struct NativePrepro {
+    /// The current instance, incremental operations are performed on it
+    inst: NoopPrepro,
+    /// The literals fixed by unit propagation
+    fixed: Vec<Lit>,
+    /// The number of fixed literals returned by
+    /// [`PreproClauses::fixed_lits_since_last_call`]
+    fixed_cursor: usize,
+    /// The weight of the soft clauses falsified by the fixed literals
+    removed: Vec<usize>,
+    /// The best known model and its cost for the objective with the highest
+    /// priority
+    best: Option<(Assignment, u64)>,
+}
+
+impl NativePrepro {
+    /// Removes duplicate literals, tautologies and duplicate clauses and
+    /// aggregates the weights of duplicate soft clauses
+    fn simplify(&mut self) {
+        let canonical = |cl: &Clause| -> Option<Clause> {
+            let mut lits: Vec<Lit> = cl.iter().copied().collect();
+            lits.sort_unstable();
+            lits.dedup();
+            if lits.windows(2).any(|w| w[0] == !w[1]) {
+                return None;
+            }
+            Some(lits.into_iter().collect())
+        };
+        let mut hards = Cnf::new();
+        let mut seen = HashSet::new();
+        for cl in self.inst.hards.iter() {
+            if let Some(cl) = canonical(cl) {
+                if seen.insert(cl.clone()) {
+                    hards.add_clause(cl);
+                }
+            }
+        }
+        for (softs, _) in &mut self.inst.softs {
+            let mut idxs: HashMap<Clause, usize> = HashMap::new();
+            let mut aggregated: SoftClauses = vec![];
+            for (cl, w) in softs.drain(..) {
+                let Some(cl) = canonical(&cl) else {
+                    continue;
+                };
+                // Soft clauses that are also hard are always satisfied
+                if seen.contains(&cl) {
+                    continue;
+                }
+                match idxs.get(&cl) {
+                    Some(&idx) => aggregated[idx].1 += w,
+                    None => {
+                        idxs.insert(cl.clone(), aggregated.len());
+                        aggregated.push((cl, w));
+                    }
+                }
+            }
+            *softs = aggregated;
+        }
+        self.inst.hards = hards;
+    }
+
+    /// Propagates the hard unit clauses until fixpoint
+    fn propagate(&mut self) {
+        let mut assign = Assignment::default();
+        for lit in &self.fixed {
+            assign.assign_lit(*lit);
+        }
+        loop {
+            let units: Vec<Lit> = self
+                .inst
+                .hards
+                .iter()
+                .filter(|cl| cl.len() == 1 && assign.lit_value(cl[0]) == TernaryVal::DontCare)
+                .map(|cl| cl[0])
+                .collect();
+            if units.is_empty() {
+                break;
+            }
+            for lit in units {
+                if assign.lit_value(lit) == TernaryVal::DontCare {
+                    assign.assign_lit(lit);
+                    self.fixed.push(lit);
+                }
+            }
+            let reduce = |cl: &Clause| -> Option<Clause> {
+                if cl.iter().any(|l| assign.lit_value(*l) == TernaryVal::True) {
+                    return None;
+                }
+                Some(
+                    cl.iter()
+                        .filter(|l| assign.lit_value(**l) != TernaryVal::False)
+                        .copied()
+                        .collect(),
+                )
+            };
+            // The hard unit clauses of the fixed literals are satisfied and
+            // therefore removed, clauses that become empty are kept
+            self.inst.hards = self.inst.hards.iter().filter_map(reduce).collect();
+            for ((softs, _), removed) in self.inst.softs.iter_mut().zip(&mut self.removed) {
+                *softs = softs
+                    .drain(..)
+                    .filter_map(|(cl, w)| {
+                        let cl = reduce(&cl)?;
+                        if cl.is_empty() {
+                            *removed += w;
+                            return None;
+                        }
+                        Some((cl, w))
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// Cost of an assignment for each objective of the original instance
+    fn orig_costs(&self, assign: &Assignment) -> Vec<u64> {
+        crate::costs(&self.inst.orig.1, assign)
+    }
+}
+
+impl PreproInit for NativePrepro {
+    fn signature() -> &'static str {
+        concat!("maxpre-rs native ", env!("CARGO_PKG_VERSION"))
+    }
+
+    fn new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let inst = NoopPrepro::new(hards, softs, inprocessing);
+        let removed = vec![0; inst.stats.n_objs];
+        Self {
+            inst,
+            fixed: vec![],
+            fixed_cursor: 0,
+            removed,
+            best: None,
+        }
+    }
+
+    fn scalarize(&self, weights: &[u64]) -> Result<Scalarized<Self>, Error> {
+        let (hards, softs) = self.inst.orig.clone();
+        Scalarized::new(
+            hards,
+            softs,
+            self.label_map(),
+            weights,
+            self.inst.inprocessing,
+        )
+    }
+}
+
+impl PreproClauses for NativePrepro {
+    fn preprocess(&mut self, techniques: &str, _log_level: c_int, _time_limit: f64) {
+        let start = ProcessTime::now();
+        self.simplify();
+        if techniques.contains(Technique::Up.char()) {
+            self.propagate();
+        }
+        let mut redundant = core::mem::take(&mut self.inst.redundant);
+        crate::retain_redundant(&mut redundant, self.inst.clauses(), &self.fixed);
+        self.inst.redundant = redundant;
+        self.inst.stats.last_prepro_time = start.elapsed();
+        self.inst.stats.prepro_time += self.inst.stats.last_prepro_time;
+        crate::count_runs(&mut self.inst.runs, techniques);
+        self.inst.state = PreproState::Preprocessed;
+    }
+
+    fn state(&self) -> PreproState {
+        self.inst.state()
+    }
+
+    fn is_inprocessing(&self) -> bool {
+        self.inst.is_inprocessing()
+    }
+
+    fn hit_time_limit(&self) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            techniques: vec![Technique::Up],
+            ..self.inst.capabilities()
+        }
+    }
+
+    fn technique_runs(&self, technique: Technique) -> usize {
+        self.inst.technique_runs(technique)
+    }
+
+    fn top_weight(&self) -> u64 {
+        self.inst.top_weight()
+    }
+
+    fn n_prepro_clauses(&self) -> usize {
+        self.inst.n_prepro_clauses()
+    }
+
+    fn n_prepro_labels(&self) -> usize {
+        self.inst.n_prepro_labels()
+    }
+
+    fn n_prepro_fixed_lits(&self) -> usize {
+        self.fixed.len()
+    }
+
+    fn n_prepro_vars(&self) -> usize {
+        self.inst.n_prepro_vars()
+    }
+
+    fn max_prepro_var(&self) -> Option<Var> {
+        self.inst.max_prepro_var()
+    }
+
+    fn n_aux_vars(&self) -> usize {
+        self.inst.n_aux_vars()
+    }
+
+    fn prepro_instance(&mut self) -> (Cnf, Vec<(SoftClauses, isize)>) {
+        let (hards, softs) = self.inst.prepro_instance();
+        self.inst.stats.removed_weight = self.removed.clone();
+        let softs = softs
+            .into_iter()
+            .zip(&self.removed)
+            .map(|((softs, offset), removed)| (softs, offset + *removed as isize))
+            .collect();
+        (hards, softs)
+    }
+
+    fn prepro_labels(&self) -> Vec<Lit> {
+        self.inst.prepro_labels()
+    }
+
+    fn strata(&self, obj_idx: usize) -> Vec<(u64, Vec<Lit>)> {
+        self.inst.strata(obj_idx)
+    }
+
+    fn label_map(&self) -> &RsHashMap<Lit, (usize, Clause)> {
+        self.inst.label_map()
+    }
+
+    fn set_label_map(&mut self, map: RsHashMap<Lit, (usize, Clause)>) {
+        self.inst.set_label_map(map);
+    }
+
+    fn objective_names(&self) -> &[Option<String>] {
+        self.inst.objective_names()
+    }
+
+    fn set_objective_names(&mut self, names: Vec<Option<String>>) {
+        self.inst.set_objective_names(names);
+    }
+
+    fn prepro_fixed_lits(&self) -> Vec<Lit> {
+        self.fixed.clone()
+    }
+
+    fn fixed_lits_since_last_call(&mut self) -> Vec<Lit> {
+        let new = self.fixed[self.fixed_cursor..].to_vec();
+        self.fixed_cursor = self.fixed.len();
+        new
+    }
+
+    fn max_orig_var(&self) -> Var {
+        self.inst.max_orig_var()
+    }
+
+    fn n_orig_clauses(&self) -> usize {
+        self.inst.n_orig_clauses()
+    }
+
+    fn n_orig_soft_clauses(&self, obj_idx: usize) -> usize {
+        self.inst.n_orig_soft_clauses(obj_idx)
+    }
+
+    fn n_orig_vars(&self) -> usize {
+        self.inst.n_orig_vars()
+    }
+
+    fn orig_weight(&self, obj_idx: usize) -> usize {
+        self.inst.orig_weight(obj_idx)
+    }
+
+    fn orig_offset(&self, obj_idx: usize) -> isize {
+        self.inst.orig_offset(obj_idx)
+    }
+
+    fn upper_bound(&self) -> u64 {
+        self.best
+            .as_ref()
+            .map_or(self.inst.upper_bound(), |(_, cost)| *cost)
+    }
+
+    fn reconstruct(&mut self, sol: Assignment) -> Assignment {
+        let mut rec = self.reconstruct_partial(sol);
+        // Variables that were removed from the instance can take any value
+        crate::assign_dont_cares(&mut rec, self.inst.stats.max_orig_var);
+        rec
+    }
+
+    fn reconstruct_partial(&mut self, sol: Assignment) -> Assignment {
+        let start = ProcessTime::now();
+        let mut rec = sol;
+        for lit in &self.fixed {
+            rec.assign_lit(*lit);
+        }
+        self.inst.stats.reconst_time += start.elapsed();
+        rec
+    }
+
+    fn best_model(&mut self) -> Option<(Assignment, Vec<u64>)> {
+        let (model, _) = self.best.as_ref()?;
+        Some((model.clone(), self.orig_costs(model)))
+    }
+
+    fn phase_hints(&self) -> Vec<Lit> {
+        self.best
+            .as_ref()
+            .map(|(model, _)| crate::phase_hints(model, self.inst.clauses()))
+            .unwrap_or_default()
+    }
+
+    fn eval_objectives(&self, sol: &Assignment, space: VarSpace) -> Vec<u64> {
+        match space {
+            VarSpace::Original => self.orig_costs(sol),
+            VarSpace::Preprocessed => crate::costs(&self.inst.softs, sol)
+                .into_iter()
+                .zip(&self.removed)
+                .map(|(cost, removed)| cost + *removed as u64)
+                .collect(),
+        }
+    }
+
+    fn local_search(&mut self, _time_limit: f64) -> Option<Assignment> {
+        None
+    }
+
+    fn set_initial_model(&mut self, model: Assignment) -> Result<(), Error> {
+        if self
+            .inst
+            .orig
+            .0
+            .iter()
+            .any(|cl| cl.evaluate(&model) != TernaryVal::True)
+        {
+            return Err(Error::Generic);
+        }
+        let cost = self
+            .orig_costs(&model)
+            .get(self.inst.first_obj())
+            .copied()
+            .unwrap_or(0);
+        if cost < self.upper_bound() {
+            self.best = Some((model, cost));
+        }
+        Ok(())
+    }
+
+    fn add_var(&mut self) -> Result<Var, Error> {
+        self.inst.add_var()
+    }
+
+    fn add_vars(&mut self, n: usize) -> Result<Range<Var>, Error> {
+        self.inst.add_vars(n)
+    }
+
+    fn reserve_vars(&mut self, n: usize) -> Result<(), Error> {
+        self.inst.reserve_vars(n)
+    }
+
+    fn add_clause(&mut self, clause: Clause) -> Result<(), Error> {
+        self.inst.add_clause(clause)
+    }
+
+    fn add_clause_with_redundancy(
+        &mut self,
+        clause: Clause,
+        redundancy: Redundancy,
+    ) -> Result<(), Error> {
+        self.inst.add_clause_with_redundancy(clause, redundancy)
+    }
+
+    fn prepro_redundant_clauses(&self) -> Vec<Clause> {
+        self.inst.prepro_redundant_clauses()
+    }
+
+    fn declare_assumption_vars(&mut self, vars: &[Var]) {
+        self.inst.declare_assumption_vars(vars);
+    }
+
+    fn map_assumption(&self, lit: Lit) -> Option<Assumption> {
+        if !self.inst.assumption_vars.contains(&lit.var()) {
+            return None;
+        }
+        if self.fixed.contains(&lit) {
+            return Some(Assumption::Satisfied);
+        }
+        if self.fixed.contains(&!lit) {
+            return Some(Assumption::Falsified);
+        }
+        self.inst.map_assumption(lit)
+    }
+
+    fn add_label(&mut self, label: Lit, weight: usize) -> Result<Lit, Error> {
+        self.inst.add_label(label, weight)
+    }
+
+    fn add_group<CI: IntoIterator<Item = Clause>>(
+        &mut self,
+        obj_idx: usize,
+        clauses: CI,
+        weight: usize,
+    ) -> Result<GroupId, Error> {
+        self.inst.add_group(obj_idx, clauses, weight)
+    }
+
+    fn alter_weight(&mut self, label: Lit, weight: usize) -> Result<(), Error> {
+        self.inst.alter_weight(label, weight)
+    }
+
+    fn alter_weights<I: IntoIterator<Item = (Lit, u64)>>(
+        &mut self,
+        updates: I,
+    ) -> Result<(), Error> {
+        self.inst.alter_weights(updates)
+    }
+
+    fn label_to_var(&mut self, label: Lit) -> Result<(), Error> {
+        self.inst.label_to_var(label)
+    }
+
+    fn reset_removed_weight(&mut self) -> Result<(), Error> {
+        self.removed.iter_mut().for_each(|w| *w = 0);
+        Ok(())
+    }
+
+    fn reset_removed_weight_for(&mut self, obj_idx: usize) -> Result<(), Error> {
+        let Some(removed) = self.removed.get_mut(obj_idx) else {
+            return Err(Error::Generic);
+        };
+        *removed = 0;
+        Ok(())
+    }
+
+    fn removed_weight(&mut self) -> RemovedWeight {
+        self.inst.stats.removed_weight = self.removed.clone();
+        RemovedWeight::new(&self.inst.orig.1, &self.fixed, &self.removed)
+    }
+
+    fn label_stats(&self) -> LabelStats {
+        self.inst.label_stats()
+    }
+
+    fn set_objective_order(&mut self, order: &[usize]) -> Result<(), Error> {
+        if !crate::is_permutation(order, self.inst.stats.n_objs) {
+            return Err(Error::Generic);
+        }
+        self.inst.obj_order = order.to_vec();
+        self.reset()
+    }
+
+    fn objective_order(&self) -> Vec<usize> {
+        self.inst.obj_order.clone()
+    }
+
+    fn set_options(&mut self, _opts: Options) {}
+
+    fn options(&self) -> Options {
+        Options::default()
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.inst.reset()?;
+        self.fixed.clear();
+        self.fixed_cursor = 0;
+        self.removed.iter_mut().for_each(|w| *w = 0);
+        Ok(())
+    }
+
+    fn print_instance(&self) {
+        self.inst.print_instance();
+    }
+
+    fn write_prepro_dimacs(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.inst.write_prepro_dimacs(writer)
+    }
+
+    fn print_solution(&self, sol: Assignment, weight: usize) {
+        self.inst.print_solution(sol, weight);
+    }
+
+    fn print_map(&self) {}
+
+    fn print_technique_log(&self) {}
+
+    fn print_info_log(&self) {}
+
+    fn print_stats(&self) {}
+
+    fn stats(&self) -> Stats {
+        self.inst.stats()
+    }
+
+    fn technique_stats(&self) -> Vec<TechniqueStats> {
+        vec![]
+    }
+
+    fn trace(&self) -> Vec<TraceOp> {
+        self.fixed.iter().map(|&lit| TraceOp::Fix { lit }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::Cnf,
+        lit,
+        types::{Assignment, TernaryVal},
+    };
+
+    use super::NativePrepro;
+    use crate::{PreproClauses, PreproInit, PreproState, TraceOp};
+
+    #[test]
+    fn simplify() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        cnf.add_binary(lit![1], lit![0]);
+        cnf.add_binary(lit![2], !lit![2]);
+        cnf.add_ternary(lit![1], lit![2], lit![2]);
+        let softs = vec![
+            (clause![!lit![0]], 2),
+            (clause![!lit![0]], 1),
+            (clause![lit![1], lit![0]], 3),
+            (clause![lit![3], !lit![3]], 4),
+        ];
+        let mut pp = NativePrepro::new(cnf, vec![(softs, 0)], false);
+        pp.preprocess("[bu]", 0, 1e9);
+        let (hards, objs) = pp.prepro_instance();
+        assert_eq!(hards.len(), 2);
+        assert!(hards.iter().any(|cl| *cl == clause![lit![0], lit![1]]));
+        assert!(hards.iter().any(|cl| *cl == clause![lit![1], lit![2]]));
+        assert_eq!(objs, vec![(vec![(clause![!lit![0]], 3)], 0)]);
+    }
+
+    #[test]
+    fn propagate() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(!lit![0], lit![1]);
+        cnf.add_binary(!lit![1], lit![2]);
+        cnf.add_ternary(!lit![0], lit![3], lit![4]);
+        let softs = vec![(clause![!lit![2]], 3), (clause![!lit![3]], 1)];
+        let mut pp = NativePrepro::new(cnf.clone(), vec![(softs.clone(), 1)], false);
+        pp.preprocess("[u]", 0, 1e9);
+        assert_eq!(pp.prepro_fixed_lits(), vec![lit![0], lit![1], lit![2]]);
+        assert_eq!(pp.fixed_lits_since_last_call(), pp.prepro_fixed_lits());
+        assert!(pp.fixed_lits_since_last_call().is_empty());
+        let (hards, objs) = pp.prepro_instance();
+        assert_eq!(hards.len(), 1);
+        assert!(hards.iter().any(|cl| *cl == clause![lit![3], lit![4]]));
+        assert_eq!(objs, vec![(vec![(clause![!lit![3]], 1)], 4)]);
+        assert_eq!(pp.lower_bounds(), vec![3]);
+        assert!(pp.trace().contains(&TraceOp::Fix { lit: lit![1] }));
+        let sol: Assignment = [!lit![3], lit![4]].into_iter().collect();
+        let rec = pp.reconstruct(sol);
+        assert!(cnf.iter().all(|cl| cl.evaluate(&rec) == TernaryVal::True));
+        assert_eq!(rec.lit_value(lit![2]), TernaryVal::True);
+        assert!(pp.reset().is_ok());
+        assert!(pp.prepro_fixed_lits().is_empty());
+    }
+
+    #[test]
+    fn inprocessing_rounds() {
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_ternary(!lit![0], lit![1], lit![2]);
+        let softs = vec![(clause![!lit![1]], 1), (clause![!lit![2]], 2)];
+        let mut pp = NativePrepro::new(cnf, vec![(softs, 0)], true);
+        assert_eq!(pp.state(), PreproState::Loaded);
+        pp.preprocess("u", 0, 1e9);
+        assert_eq!(pp.state(), PreproState::Preprocessed);
+        assert!(pp.add_clause(clause![!lit![2]]).is_ok());
+        assert_eq!(pp.state(), PreproState::Modified);
+        pp.preprocess("u", 0, 1e9);
+        assert_eq!(pp.state(), PreproState::Preprocessed);
+        let (hards, _) = pp.prepro_instance();
+        assert!(hards.is_empty());
+        let rec = pp.reconstruct(Assignment::default());
+        assert_eq!(rec.lit_value(lit![0]), TernaryVal::True);
+        assert_eq!(rec.lit_value(lit![1]), TernaryVal::True);
+        assert_eq!(rec.lit_value(lit![2]), TernaryVal::False);
+        assert!(pp.reset().is_ok());
+        assert_eq!(pp.state(), PreproState::Loaded);
+    }
+
+    #[test]
+    fn assumptions() {
+        use crate::Assumption;
+        use rustsat::var;
+
+        let mut cnf = Cnf::new();
+        cnf.add_unit(lit![0]);
+        cnf.add_binary(lit![1], lit![2]);
+        let mut pp = NativePrepro::new(cnf, vec![(vec![(clause![lit![3]], 1)], 0)], false);
+        pp.declare_assumption_vars(&[var![0], var![1]]);
+        pp.preprocess("u", 0, 1e9);
+        assert_eq!(pp.map_assumption(lit![0]), Some(Assumption::Satisfied));
+        assert_eq!(pp.map_assumption(!lit![0]), Some(Assumption::Falsified));
+        assert_eq!(pp.map_assumption(!lit![1]), Some(Assumption::Lit(!lit![1])));
+        assert_eq!(pp.map_assumption(lit![2]), None);
+    }
+}
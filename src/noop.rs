@@ -0,0 +1,741 @@
+//! # Identity Preprocessor
+//!
+//! A preprocessor that does not change the instance, for comparing against
+//! MaxPre or testing code that is generic over [`PreproClauses`].
+
+use core::{ffi::c_int, ops::Range};
+use std::io;
+
+use rustsat::{
+    instances::Cnf,
+    types::{Assignment, Clause, Lit, RsHashMap, RsHashSet, TernaryVal, Var},
+};
+
+use crate::{
+    Assumption, Capabilities, Error, GroupId, InputError, LabelStats, Options, PreproClauses,
+    PreproInit, PreproState, Redundancy, RemovedWeight, Scalarized, SoftClauses, Stats, Technique,
+    TechniqueStats, TraceOp, VarSpace,
+};
+
+/// Upper bound reported if no model is known, the same as MaxPre's
+const NO_UPPER_BOUND: u64 = 1 << 63;
+
+/// A preprocessor that passes the instance through unchanged. Reconstruction
+/// returns the solution as is. Like [`MaxPre`](crate::MaxPre), variables,
+/// clauses and labels can only be added in inprocessing mode.
+pub struct NoopPrepro {
+    /// The hard clauses
+    pub(crate) hards: Cnf,
+    /// The objectives
+    pub(crate) softs: Vec<(SoftClauses, isize)>,
+    /// Whether the preprocessor is in inprocessing mode
+    pub(crate) inprocessing: bool,
+    /// The top weight, weights at least this high make soft clauses hard
+    top: u64,
+    /// The instance the preprocessor was initialized with
+    pub(crate) orig: (Cnf, Vec<(SoftClauses, isize)>),
+    /// Statistics of the preprocessor
+    pub(crate) stats: Stats,
+    /// The best known model and its cost for the objective with the highest
+    /// priority
+    best: Option<(Assignment, u64)>,
+    /// Map from labels to the objective and soft clause they were created for
+    label_map: RsHashMap<Lit, (usize, Clause)>,
+    /// The objectives by priority, see [`PreproClauses::set_objective_order`]
+    pub(crate) obj_order: Vec<usize>,
+    /// The state in the inprocessing workflow
+    pub(crate) state: PreproState,
+    /// The redundant clauses, which are not part of the instance
+    pub(crate) redundant: Vec<Clause>,
+    /// The variables declared with
+    /// [`PreproClauses::declare_assumption_vars`]
+    pub(crate) assumption_vars: RsHashSet<Var>,
+    /// The number of preprocessing calls each technique was run in
+    pub(crate) runs: RsHashMap<Technique, usize>,
+}
+
+impl NoopPrepro {
+    /// Clauses of the instance
+    pub(crate) fn clauses(&self) -> impl Iterator<Item = &Clause> {
+        self.hards.iter().chain(
+            self.softs
+                .iter()
+                .flat_map(|(softs, _)| softs.iter().map(|(cl, _)| cl)),
+        )
+    }
+
+    /// Cost of an assignment for each objective, not including the offsets
+    fn costs(&self, assign: &Assignment) -> Vec<u64> {
+        crate::costs(&self.softs, assign)
+    }
+
+    /// Gets the index of the objective with the highest priority
+    pub(crate) fn first_obj(&self) -> usize {
+        self.obj_order.first().copied().unwrap_or(0)
+    }
+
+    /// Checks whether a variable appears in the instance
+    fn has_var(&self, var: Var) -> bool {
+        self.stats.max_prepro_var.is_some_and(|max| var <= max)
+    }
+
+    /// Finds the soft clause of a label in the first objective
+    fn label_idx(&self, label: Lit) -> Option<usize> {
+        self.softs
+            .get(self.first_obj())?
+            .0
+            .iter()
+            .position(|(cl, _)| cl.len() == 1 && cl[0].var() == label.var())
+    }
+
+    /// Sets the weight of the soft clause of a label, making it hard if the
+    /// weight is at least the top weight
+    fn set_weight(&mut self, idx: usize, weight: u64) {
+        if weight >= self.top {
+            let first = self.first_obj();
+            let (cl, _) = self.softs[first].0.remove(idx);
+            self.hards.add_clause(cl);
+        } else {
+            let first = self.first_obj();
+            self.softs[first].0[idx].1 = weight as usize;
+        }
+    }
+}
+
+impl PreproInit for NoopPrepro {
+    fn signature() -> &'static str {
+        "noop"
+    }
+
+    fn new<H, S, CI>(hards: H, softs: S, inprocessing: bool) -> Self
+    where
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, u64)>,
+    {
+        let (hards, softs) = crate::collect_instance(hards, softs);
+        let top = softs.iter().fold(1, |top, softs| {
+            softs.0.iter().fold(top, |top, (_, w)| top + *w as u64)
+        });
+        let mut label_map = RsHashMap::default();
+        for (obj_idx, (softs, _)) in softs.iter().enumerate() {
+            for (cl, _) in softs {
+                if cl.len() == 1 {
+                    label_map
+                        .entry(cl[0])
+                        .or_insert_with(|| (obj_idx, cl.clone()));
+                }
+            }
+        }
+        let max_var = hards
+            .iter()
+            .chain(softs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+            .flat_map(|cl| cl.iter().map(|l| l.var()))
+            .max();
+        let stats = Stats {
+            n_objs: softs.len(),
+            n_orig_hard_clauses: hards.len(),
+            n_orig_soft_clauses: softs.iter().map(|(s, _)| s.len()).collect(),
+            max_orig_var: max_var,
+            orig_weight: softs
+                .iter()
+                .map(|(s, _)| s.iter().map(|(_, w)| w).sum())
+                .collect(),
+            max_prepro_var: max_var,
+            removed_weight: vec![0; softs.len()],
+            ..Default::default()
+        };
+        let obj_order = (0..softs.len()).collect();
+        Self {
+            orig: (hards.clone(), softs.clone()),
+            hards,
+            softs,
+            inprocessing,
+            top,
+            stats,
+            best: None,
+            label_map,
+            obj_order,
+            state: PreproState::Loaded,
+            redundant: vec![],
+            assumption_vars: RsHashSet::default(),
+            runs: RsHashMap::default(),
+        }
+    }
+
+    fn scalarize(&self, weights: &[u64]) -> Result<Scalarized<Self>, Error> {
+        let (hards, softs) = self.orig.clone();
+        Scalarized::new(hards, softs, &self.label_map, weights, self.inprocessing)
+    }
+}
+
+impl PreproClauses for NoopPrepro {
+    fn preprocess(&mut self, techniques: &str, _log_level: c_int, _time_limit: f64) {
+        crate::count_runs(&mut self.runs, techniques);
+        self.state = PreproState::Preprocessed;
+    }
+
+    fn state(&self) -> PreproState {
+        self.state
+    }
+
+    fn is_inprocessing(&self) -> bool {
+        self.inprocessing
+    }
+
+    fn hit_time_limit(&self) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            inprocessing: self.inprocessing,
+            proofs: false,
+            techniques: vec![],
+            max_total_weight: crate::MAX_TOTAL_WEIGHT,
+            max_var_idx: Var::MAX_IDX,
+        }
+    }
+
+    fn technique_runs(&self, technique: Technique) -> usize {
+        self.runs.get(&technique).copied().unwrap_or(0)
+    }
+
+    fn top_weight(&self) -> u64 {
+        self.top
+    }
+
+    fn n_prepro_clauses(&self) -> usize {
+        self.clauses().count()
+    }
+
+    fn n_prepro_labels(&self) -> usize {
+        self.prepro_labels().len()
+    }
+
+    fn n_prepro_fixed_lits(&self) -> usize {
+        0
+    }
+
+    fn n_prepro_vars(&self) -> usize {
+        let mut vars: Vec<_> = self
+            .clauses()
+            .flat_map(|cl| cl.iter().map(|l| l.var()))
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars.len()
+    }
+
+    fn max_prepro_var(&self) -> Option<Var> {
+        self.clauses()
+            .flat_map(|cl| cl.iter().map(|l| l.var()))
+            .max()
+    }
+
+    fn n_aux_vars(&self) -> usize {
+        0
+    }
+
+    fn prepro_instance(&mut self) -> (Cnf, Vec<(SoftClauses, isize)>) {
+        self.stats.n_prepro_hard_clauses = self.hards.len();
+        self.stats.n_prepro_soft_clauses = self.softs.iter().map(|(s, _)| s.len()).collect();
+        (self.hards.clone(), self.softs.clone())
+    }
+
+    fn prepro_labels(&self) -> Vec<Lit> {
+        self.softs
+            .iter()
+            .flat_map(|(softs, _)| softs.iter())
+            .filter(|(cl, _)| cl.len() == 1)
+            .map(|(cl, _)| cl[0])
+            .collect()
+    }
+
+    fn strata(&self, obj_idx: usize) -> Vec<(u64, Vec<Lit>)> {
+        self.softs
+            .get(obj_idx)
+            .map_or_else(Vec::new, |(softs, _)| crate::weight_strata(softs))
+    }
+
+    fn label_map(&self) -> &RsHashMap<Lit, (usize, Clause)> {
+        &self.label_map
+    }
+
+    fn set_label_map(&mut self, map: RsHashMap<Lit, (usize, Clause)>) {
+        self.label_map = map;
+    }
+
+    fn objective_names(&self) -> &[Option<String>] {
+        &self.stats.obj_names
+    }
+
+    fn set_objective_names(&mut self, names: Vec<Option<String>>) {
+        crate::set_objective_names(&mut self.stats, names);
+    }
+
+    fn prepro_fixed_lits(&self) -> Vec<Lit> {
+        vec![]
+    }
+
+    fn fixed_lits_since_last_call(&mut self) -> Vec<Lit> {
+        vec![]
+    }
+
+    fn max_orig_var(&self) -> Var {
+        self.stats.max_orig_var.unwrap_or(Var::new(0))
+    }
+
+    fn n_orig_clauses(&self) -> usize {
+        self.stats.n_orig_hard_clauses + self.stats.n_orig_soft_clauses.iter().sum::<usize>()
+    }
+
+    fn n_orig_soft_clauses(&self, obj_idx: usize) -> usize {
+        self.stats.n_orig_soft_clauses[obj_idx]
+    }
+
+    fn n_orig_vars(&self) -> usize {
+        self.stats
+            .max_orig_var
+            .map_or(0, |max_var| max_var.idx() + 1)
+    }
+
+    fn orig_weight(&self, obj_idx: usize) -> usize {
+        self.stats.orig_weight[obj_idx]
+    }
+
+    fn orig_offset(&self, obj_idx: usize) -> isize {
+        self.orig.1[obj_idx].1
+    }
+
+    fn upper_bound(&self) -> u64 {
+        self.best.as_ref().map_or(NO_UPPER_BOUND, |(_, cost)| *cost)
+    }
+
+    fn reconstruct(&mut self, sol: Assignment) -> Assignment {
+        sol
+    }
+
+    fn reconstruct_partial(&mut self, sol: Assignment) -> Assignment {
+        sol
+    }
+
+    fn best_model(&mut self) -> Option<(Assignment, Vec<u64>)> {
+        let (model, _) = self.best.as_ref()?;
+        Some((model.clone(), self.costs(model)))
+    }
+
+    fn phase_hints(&self) -> Vec<Lit> {
+        self.best
+            .as_ref()
+            .map(|(model, _)| crate::phase_hints(model, self.clauses()))
+            .unwrap_or_default()
+    }
+
+    fn eval_objectives(&self, sol: &Assignment, space: VarSpace) -> Vec<u64> {
+        match space {
+            VarSpace::Original => crate::costs(&self.orig.1, sol),
+            VarSpace::Preprocessed => self.costs(sol),
+        }
+    }
+
+    fn local_search(&mut self, _time_limit: f64) -> Option<Assignment> {
+        None
+    }
+
+    fn set_initial_model(&mut self, model: Assignment) -> Result<(), Error> {
+        if self
+            .hards
+            .iter()
+            .any(|cl| cl.evaluate(&model) != TernaryVal::True)
+        {
+            return Err(Error::Generic);
+        }
+        let cost = self
+            .costs(&model)
+            .get(self.first_obj())
+            .copied()
+            .unwrap_or(0);
+        if cost < self.upper_bound() {
+            self.best = Some((model, cost));
+        }
+        Ok(())
+    }
+
+    fn add_var(&mut self) -> Result<Var, Error> {
+        Ok(self.add_vars(1)?.start)
+    }
+
+    fn add_vars(&mut self, n: usize) -> Result<Range<Var>, Error> {
+        if !self.inprocessing {
+            return Err(Error::NotInprocessing);
+        }
+        let count =
+            u32::try_from(n).map_err(|_| Error::InvalidInput(InputError::TooManyVars(n)))?;
+        let first = self.stats.max_prepro_var.map_or(Var::new(0), |v| v + 1);
+        let vars = first..first + count;
+        if n > 0 {
+            self.stats.max_prepro_var = Some(vars.end - 1);
+        }
+        Ok(vars)
+    }
+
+    fn reserve_vars(&mut self, n: usize) -> Result<(), Error> {
+        match u32::try_from(n) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Error::InvalidInput(InputError::TooManyVars(n))),
+        }
+    }
+
+    fn add_clause(&mut self, clause: Clause) -> Result<(), Error> {
+        if !self.inprocessing {
+            return Err(Error::NotInprocessing);
+        }
+        if let Some(max) = clause.iter().map(|l| l.var()).max() {
+            self.stats.max_prepro_var = self.stats.max_prepro_var.max(Some(max));
+        }
+        self.hards.add_clause(clause);
+        self.state.modify();
+        Ok(())
+    }
+
+    fn add_clause_with_redundancy(
+        &mut self,
+        clause: Clause,
+        redundancy: Redundancy,
+    ) -> Result<(), Error> {
+        match redundancy {
+            Redundancy::Irredundant => self.add_clause(clause),
+            Redundancy::Redundant => {
+                if !self.inprocessing {
+                    return Err(Error::NotInprocessing);
+                }
+                self.redundant.push(clause);
+                Ok(())
+            }
+        }
+    }
+
+    fn prepro_redundant_clauses(&self) -> Vec<Clause> {
+        self.redundant.clone()
+    }
+
+    fn declare_assumption_vars(&mut self, vars: &[Var]) {
+        self.assumption_vars.extend(vars.iter().copied());
+    }
+
+    fn map_assumption(&self, lit: Lit) -> Option<Assumption> {
+        if !self.assumption_vars.contains(&lit.var()) {
+            return None;
+        }
+        Some(
+            if self
+                .clauses()
+                .any(|cl| cl.iter().any(|l| l.var() == lit.var()))
+            {
+                Assumption::Lit(lit)
+            } else {
+                Assumption::Satisfied
+            },
+        )
+    }
+
+    fn add_label(&mut self, label: Lit, weight: usize) -> Result<Lit, Error> {
+        if !self.inprocessing {
+            return Err(Error::NotInprocessing);
+        }
+        if self.has_var(label.var()) || self.softs.is_empty() {
+            return Err(Error::Generic);
+        }
+        self.stats.max_prepro_var = self.stats.max_prepro_var.max(Some(label.var()));
+        let label = label.var().pos_lit();
+        let cl = Clause::from([!label]);
+        if weight as u64 >= self.top {
+            self.hards.add_clause(cl);
+        } else {
+            let first = self.first_obj();
+            self.softs[first].0.push((cl, weight));
+        }
+        self.state.modify();
+        Ok(label)
+    }
+
+    fn add_group<CI: IntoIterator<Item = Clause>>(
+        &mut self,
+        obj_idx: usize,
+        clauses: CI,
+        weight: usize,
+    ) -> Result<GroupId, Error> {
+        if obj_idx != self.first_obj() {
+            return Err(Error::Generic);
+        }
+        let fresh = self.stats.max_prepro_var.map_or(Var::new(0), |v| v + 1);
+        let label = self.add_label(fresh.pos_lit(), weight)?;
+        for mut clause in clauses {
+            clause.add(label);
+            self.add_clause(clause)?;
+        }
+        Ok(GroupId(label))
+    }
+
+    fn alter_weight(&mut self, label: Lit, weight: usize) -> Result<(), Error> {
+        self.alter_weights([(label, weight as u64)])
+    }
+
+    fn alter_weights<I: IntoIterator<Item = (Lit, u64)>>(
+        &mut self,
+        updates: I,
+    ) -> Result<(), Error> {
+        if !self.inprocessing {
+            return Err(Error::NotInprocessing);
+        }
+        let updates: Vec<_> = updates.into_iter().collect();
+        let mut hardened = vec![];
+        for (label, weight) in &updates {
+            if self.label_idx(*label).is_none() || hardened.contains(&label.var()) {
+                return Err(Error::Generic);
+            }
+            if *weight >= self.top {
+                hardened.push(label.var());
+            }
+        }
+        for (label, weight) in updates {
+            let idx = self.label_idx(label).unwrap();
+            self.set_weight(idx, weight);
+        }
+        self.state.modify();
+        Ok(())
+    }
+
+    fn label_to_var(&mut self, label: Lit) -> Result<(), Error> {
+        if !self.inprocessing {
+            return Err(Error::NotInprocessing);
+        }
+        if let Some(idx) = self.label_idx(label) {
+            let first = self.first_obj();
+            self.softs[first].0.remove(idx);
+        }
+        self.state.modify();
+        Ok(())
+    }
+
+    fn reset_removed_weight(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn reset_removed_weight_for(&mut self, obj_idx: usize) -> Result<(), Error> {
+        if obj_idx >= self.stats.n_objs {
+            return Err(Error::Generic);
+        }
+        Ok(())
+    }
+
+    fn removed_weight(&mut self) -> RemovedWeight {
+        RemovedWeight::new(&self.orig.1, &[], &self.stats.removed_weight)
+    }
+
+    fn label_stats(&self) -> LabelStats {
+        LabelStats {
+            n_orig_labels: self.stats.n_orig_soft_clauses.clone(),
+            orig_weight: self.stats.orig_weight.iter().map(|&w| w as u64).collect(),
+            n_prepro_labels: self.softs.iter().map(|(s, _)| s.len()).collect(),
+            prepro_weight: self
+                .softs
+                .iter()
+                .map(|(s, _)| s.iter().map(|(_, w)| *w as u64).sum())
+                .collect(),
+        }
+    }
+
+    fn set_objective_order(&mut self, order: &[usize]) -> Result<(), Error> {
+        if !crate::is_permutation(order, self.stats.n_objs) {
+            return Err(Error::Generic);
+        }
+        self.obj_order = order.to_vec();
+        self.reset()
+    }
+
+    fn objective_order(&self) -> Vec<usize> {
+        self.obj_order.clone()
+    }
+
+    fn set_options(&mut self, _opts: Options) {}
+
+    fn options(&self) -> Options {
+        Options::default()
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        let (hards, softs) = self.orig.clone();
+        let label_map = core::mem::take(&mut self.label_map);
+        let obj_order = core::mem::take(&mut self.obj_order);
+        let assumption_vars = core::mem::take(&mut self.assumption_vars);
+        let obj_names = core::mem::take(&mut self.stats.obj_names);
+        *self = Self::new(hards, crate::weighted(softs), self.inprocessing);
+        self.stats.obj_names = obj_names;
+        self.label_map = label_map;
+        self.obj_order = obj_order;
+        self.assumption_vars = assumption_vars;
+        Ok(())
+    }
+
+    fn print_instance(&self) {
+        for cl in self.hards.iter() {
+            println!("h {}", dimacs_lits(cl));
+        }
+        for (softs, _) in &self.softs {
+            for (cl, w) in softs {
+                println!("{w} {}", dimacs_lits(cl));
+            }
+        }
+    }
+
+    fn write_prepro_dimacs(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        crate::write_objective_names(writer, &self.stats.obj_names)?;
+        let n_objs = self.softs.len();
+        for cl in self.hards.iter() {
+            crate::write_dimacs_clause(writer, n_objs, None, cl.iter().copied())?;
+        }
+        for (obj_idx, (softs, _)) in self.softs.iter().enumerate() {
+            for (cl, w) in softs {
+                let soft = Some((obj_idx, *w as u64));
+                crate::write_dimacs_clause(writer, n_objs, soft, cl.iter().copied())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_solution(&self, sol: Assignment, weight: usize) {
+        println!("o {weight}");
+        println!("s OPTIMUM FOUND");
+        let values: String = sol
+            .iter()
+            .map(|l| if l.is_pos() { '1' } else { '0' })
+            .collect();
+        println!("v {values}");
+    }
+
+    fn print_map(&self) {}
+
+    fn print_technique_log(&self) {}
+
+    fn print_info_log(&self) {}
+
+    fn print_stats(&self) {}
+
+    fn stats(&self) -> Stats {
+        self.stats.clone()
+    }
+
+    fn technique_stats(&self) -> Vec<TechniqueStats> {
+        vec![]
+    }
+
+    fn trace(&self) -> Vec<TraceOp> {
+        vec![]
+    }
+}
+
+/// Formats the literals of a clause in DIMACS, terminated by 0
+fn dimacs_lits(cl: &Clause) -> String {
+    cl.iter()
+        .map(|l| format!("{} ", l.to_ipasir()))
+        .chain(core::iter::once(String::from("0")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        clause,
+        instances::Cnf,
+        lit,
+        types::{Assignment, TernaryVal},
+    };
+
+    use super::NoopPrepro;
+    use crate::{PreproClauses, PreproInit};
+
+    #[test]
+    fn identity() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1], lit![2]], 1)];
+        let mut pp = NoopPrepro::new(cnf.clone(), vec![(softs.clone(), 3)], false);
+        pp.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let (hards, objs) = pp.prepro_instance();
+        assert!(hards.iter().eq(cnf.iter()));
+        assert_eq!(
+            objs,
+            vec![(
+                vec![(clause![!lit![0]], 2), (clause![!lit![1], lit![2]], 1)],
+                3
+            )]
+        );
+        assert_eq!(pp.prepro_labels(), vec![!lit![0]]);
+        assert_eq!(pp.top_weight(), 4);
+        let sol: Assignment = [!lit![0], lit![1], !lit![2]].into_iter().collect();
+        assert_eq!(pp.reconstruct(sol.clone()), sol);
+        assert!(pp.add_var().is_err());
+        assert!(pp.set_initial_model(sol.clone()).is_ok());
+        assert_eq!(pp.upper_bound(), 1);
+        let Some((model, costs)) = pp.best_model() else {
+            panic!("no best model");
+        };
+        assert_eq!(model, sol);
+        assert_eq!(costs, vec![1]);
+        let unsat: Assignment = [!lit![0], !lit![1], lit![2]].into_iter().collect();
+        assert!(cnf.iter().any(|cl| cl.evaluate(&unsat) != TernaryVal::True));
+        assert!(pp.set_initial_model(unsat).is_err());
+    }
+
+    #[test]
+    fn call_order_guards() {
+        use crate::Error;
+
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 1)];
+        let mut pp = NoopPrepro::new(cnf, vec![(softs, 0)], false);
+        assert!(matches!(pp.try_prepro_instance(), Err(Error::InvalidState)));
+        assert!(pp.try_preprocess("[u]", 0, 1e9).is_ok());
+        assert!(matches!(
+            pp.try_preprocess("[u]", 0, 1e9),
+            Err(Error::InvalidState)
+        ));
+        assert!(matches!(
+            pp.add_clause(clause![lit![0]]),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(
+            pp.add_label(lit![2], 1),
+            Err(Error::NotInprocessing)
+        ));
+        assert!(matches!(pp.add_var(), Err(Error::NotInprocessing)));
+    }
+
+    #[test]
+    fn inprocessing() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let mut pp = NoopPrepro::new(cnf, vec![(softs, 0)], true);
+        assert!(pp.alter_weights([(lit![0], 1), (lit![5], 1)]).is_err());
+        assert!(pp.alter_weights([(lit![0], 1), (lit![1], 4)]).is_ok());
+        let Ok(group) = pp.add_group(0, [clause![!lit![0]]], 3) else {
+            panic!("failed to add group");
+        };
+        let (hards, objs) = pp.prepro_instance();
+        assert!(hards.iter().any(|cl| *cl == clause![!lit![1]]));
+        assert!(hards
+            .iter()
+            .any(|cl| *cl == clause![!lit![0], group.label()]));
+        assert_eq!(
+            objs[0].0,
+            vec![(clause![!lit![0]], 1), (clause![!group.label()], 3)]
+        );
+        assert!(pp.reset().is_ok());
+        let (hards, _) = pp.prepro_instance();
+        assert_eq!(hards.len(), 1);
+    }
+}
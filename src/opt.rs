@@ -1,16 +1,27 @@
 //! # Interface for Preprocessing [`rustsat::instances::OptInstance`] types
 
 use rustsat::{
-    encodings::{card, pb},
-    instances::{Cnf, ManageVars, Objective, OptInstance, SatInstance},
-    types::constraints::{CardConstraint, PBConstraint},
+    instances::{Cnf, ManageVars, OptInstance, ReindexingVarManager},
+    types::{
+        constraints::{CardConstraint, PBConstraint},
+        Clause, Lit, RsHashMap, WClsIter,
+    },
 };
 
-use crate::PreproClauses;
+use crate::{Prepro, PreproClauses, PreproInit};
 
 pub trait PreproOpt: PreproClauses {
     /// Initializes a new preprocessor from a [`OptInstance`] where the instance
     /// is converted to [`CNF`] with the given encoders.
+    ///
+    /// MaxPre's techniques only operate on clauses. Its cardinality
+    /// constraint code is a CNF encoder for its input reader and is not a
+    /// native constraint representation, so constraints are always encoded
+    /// before being passed to MaxPre.
+    ///
+    /// Non-unit soft clauses are relaxed with a fresh variable from the
+    /// instance's variable manager, so that the
+    /// [label map](PreproClauses::label_map) covers all soft clauses.
     fn new_with_encoders<VM, CardEnc, PBEnc>(
         inst: OptInstance<VM>,
         card_encoder: CardEnc,
@@ -21,46 +32,68 @@ pub trait PreproOpt: PreproClauses {
         VM: ManageVars,
         CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
         PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
-        Self: Sized,
+        Self: PreproInit,
     {
-        let (constrs, obj) = inst.decompose();
-        let (cnf, _) = constrs.into_cnf_with_encoders(card_encoder, pb_encoder);
-        let softs = obj.into_soft_cls();
-        <Self as PreproClauses>::new(cnf, vec![softs], inprocessing)
+        Prepro::load_with_encoders(inst, card_encoder, pb_encoder, inprocessing)
     }
     /// Initializes a new preprocessor from a [`SatInstance`]
     fn new<VM>(inst: OptInstance<VM>, inprocessing: bool) -> Self
     where
         VM: ManageVars,
-        Self: Sized,
+        Self: PreproInit,
     {
-        Self::new_with_encoders(
-            inst,
-            |constr, cnf, vm| {
-                card::default_encode_cardinality_constraint(constr, cnf, vm)
-                    .expect("cardinality encoding ran out of memory")
-            },
-            |constr, cnf, vm| {
-                pb::default_encode_pb_constraint(constr, cnf, vm)
-                    .expect("pb encoding ran out of memory")
-            },
-            inprocessing,
-        )
+        Prepro::load(inst, inprocessing)
     }
-    /// Gets the preprocessed instance as a [`SatInstance`]
+    /// Gets the preprocessed instance as an [`OptInstance`]
     fn prepro_instance(&mut self) -> OptInstance {
-        let (cnf, objs) = <Self as PreproClauses>::prepro_instance(self);
-        debug_assert_eq!(objs.len(), 1);
-        let constrs = SatInstance::from(cnf);
-        let obj = if let Some((softs, offset)) = objs.into_iter().last() {
-            let mut obj = Objective::from_iter(softs);
-            obj.set_offset(offset);
-            obj
-        } else {
-            panic!()
-        };
-        OptInstance::compose(constrs, obj)
+        Prepro::<OptInstance>::extract(self)
+    }
+    /// Gets the preprocessed instance as an [`OptInstance`] with the given
+    /// variable manager, typically the one of the original instance. All
+    /// variables up to the highest original or preprocessed variable are
+    /// marked as used in the manager, so that new variables do not clash with
+    /// the preprocessed instance or with variables that reconstruction
+    /// assigns.
+    fn prepro_instance_with_vm<VM: ManageVars>(&mut self, vm: VM) -> OptInstance<VM>
+    where
+        Self: Sized,
+    {
+        Prepro::<OptInstance>::extract_with_vm(self, vm)
+    }
+    /// Gets the preprocessed instance with its variables renumbered to a dense
+    /// range, see [`PreproClauses::prepro_instance_reindexed`]
+    fn prepro_instance_reindexed(&mut self) -> OptInstance<ReindexingVarManager> {
+        <Self as PreproOpt>::prepro_instance(self).reindex_ordered(ReindexingVarManager::default())
     }
 }
 
 impl<PP: PreproClauses> PreproOpt for PP {}
+
+/// Relaxes all non-unit soft clauses of an objective with fresh variables,
+/// adding the relaxed clauses to `cnf` and recording the labels of all soft
+/// clauses in `label_map`
+pub(crate) fn relax_softs<CI: WClsIter>(
+    (softs, offset): (CI, isize),
+    obj_idx: usize,
+    cnf: &mut Cnf,
+    vm: &mut dyn ManageVars,
+    label_map: &mut RsHashMap<Lit, (usize, Clause)>,
+) -> (Vec<(Clause, u64)>, isize) {
+    let softs = softs
+        .into_iter()
+        .map(|(cl, w)| {
+            let label = if cl.len() == 1 {
+                cl[0]
+            } else {
+                let blit = vm.new_var().pos_lit();
+                let mut relaxed = cl.clone();
+                relaxed.add(blit);
+                cnf.add_clause(relaxed);
+                !blit
+            };
+            label_map.entry(label).or_insert((obj_idx, cl));
+            (Clause::from([label]), w as u64)
+        })
+        .collect();
+    (softs, offset)
+}
@@ -0,0 +1,110 @@
+//! # Generic Preprocessing Interface
+//!
+//! A trait for preprocessing an instance and reconstructing solutions that
+//! does not depend on a concrete preprocessor, so that solvers can be generic
+//! over the preprocessor they use.
+
+use core::ffi::c_int;
+
+use rustsat::{instances::BasicVarManager, types::Assignment};
+
+use crate::{InstanceKind, Options, Prepro, PreproClauses, PreproInit, Techniques};
+
+/// A preprocessor that transforms an instance and reconstructs solutions of
+/// the transformed instance to solutions of the original instance
+pub trait Preprocess<I>: Sized {
+    /// The type of the preprocessed instance
+    type Output;
+    /// Configuration of preprocessing, e.g., the techniques to use
+    type Config;
+    /// Preprocesses an instance. Returns the preprocessor, which is needed for
+    /// reconstructing solutions, and the preprocessed instance.
+    fn transform(inst: I, config: &Self::Config) -> (Self, Self::Output);
+    /// Reconstructs a solution of the original instance from a solution of
+    /// the preprocessed instance
+    fn reconstruct_solution(&mut self, sol: Assignment) -> Assignment;
+}
+
+/// Configuration of preprocessing with the preprocessors of this crate
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreproConfig {
+    /// The techniques to preprocess with
+    pub techniques: Techniques,
+    /// The log level of MaxPre
+    pub log_level: c_int,
+    /// The time limit for preprocessing in seconds
+    pub time_limit: f64,
+    /// Whether to create the preprocessor in inprocessing mode
+    pub inprocessing: bool,
+    pub options: Options,
+}
+
+impl Default for PreproConfig {
+    fn default() -> Self {
+        PreproConfig {
+            techniques: Techniques::DEFAULT,
+            log_level: 0,
+            time_limit: 1e9,
+            inprocessing: false,
+            options: Options::default(),
+        }
+    }
+}
+
+impl PreproConfig {
+    /// Sets the options on a preprocessor and preprocesses with the
+    /// techniques and limits
+    fn apply<PP: PreproClauses>(&self, pp: &mut PP) {
+        pp.set_options(self.options.clone());
+        pp.preprocess(&self.techniques, self.log_level, self.time_limit);
+    }
+}
+
+impl<PP: PreproInit, I: InstanceKind> Preprocess<I> for PP {
+    type Output = I::WithVm<BasicVarManager>;
+    type Config = PreproConfig;
+
+    fn transform(inst: I, config: &PreproConfig) -> (Self, Self::Output) {
+        let mut pp = <PP as Prepro<I>>::load(inst, config.inprocessing);
+        config.apply(&mut pp);
+        let inst = <PP as Prepro<I>>::extract(&mut pp);
+        (pp, inst)
+    }
+
+    fn reconstruct_solution(&mut self, sol: Assignment) -> Assignment {
+        self.reconstruct(sol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        instances::SatInstance,
+        lit,
+        types::{Assignment, TernaryVal},
+    };
+
+    use super::{PreproConfig, Preprocess};
+    use crate::{NativePrepro, NoopPrepro};
+
+    /// Stands in for a solver that is generic over its preprocessor
+    fn solve<P: Preprocess<SatInstance, Output = SatInstance, Config = PreproConfig>>(
+        inst: SatInstance,
+    ) -> Assignment {
+        let (mut pp, _) = P::transform(inst, &PreproConfig::default());
+        pp.reconstruct_solution(Assignment::default())
+    }
+
+    #[test]
+    fn generic_preprocessor() {
+        let mut inst = SatInstance::new();
+        inst.add_unit(lit![0]);
+        inst.add_binary(!lit![0], lit![1]);
+        let (_, prepro) = NoopPrepro::transform(inst.clone(), &PreproConfig::default());
+        assert_eq!(prepro.n_clauses(), 2);
+        let (_, prepro) = NativePrepro::transform(inst.clone(), &PreproConfig::default());
+        assert_eq!(prepro.n_clauses(), 0);
+        let sol = solve::<NativePrepro>(inst);
+        assert_eq!(sol.lit_value(lit![1]), TernaryVal::True);
+    }
+}
@@ -0,0 +1,251 @@
+//! # Standalone Reconstruction
+//!
+//! A snapshot of the reconstruction trace of [`MaxPre`](crate::MaxPre) that
+//! reconstructs solutions without access to the preprocessor. Unlike the
+//! preprocessor, a [`Reconstructor`] can be shared between threads, which is
+//! used to reconstruct many solutions in parallel.
+
+use rustsat::types::{Assignment, Clause, Lit, Var};
+
+use crate::TraceOp;
+
+/// Reconstructs solutions of a preprocessed instance the same way as
+/// [`PreproClauses::reconstruct`](crate::PreproClauses::reconstruct) on the
+/// preprocessor it was obtained from at the time it was obtained
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reconstructor {
+    /// The internal variable of MaxPre for each variable of a solution
+    internal: Vec<Option<Lit>>,
+    /// The number of internal variables
+    n_internal_vars: usize,
+    /// The number of original variables that values are reconstructed for
+    n_orig_vars: usize,
+    /// The reconstruction trace
+    trace: Vec<TraceOp>,
+}
+
+impl Reconstructor {
+    pub(crate) fn new(
+        internal: Vec<Option<Lit>>,
+        n_internal_vars: usize,
+        n_orig_vars: usize,
+        trace: Vec<TraceOp>,
+    ) -> Self {
+        Reconstructor {
+            internal,
+            n_internal_vars,
+            n_orig_vars,
+            trace,
+        }
+    }
+
+    /// Reconstructs an assignment given by its true literals
+    pub fn reconstruct<I: IntoIterator<Item = Lit>>(&self, sol: I) -> Assignment {
+        let n_vars = self
+            .trace
+            .iter()
+            .flat_map(TraceOp::vars)
+            .fold(self.n_internal_vars, |n, var| n.max(var.idx() + 1));
+        let mut values = Values(vec![None; n_vars]);
+        for lit in sol {
+            let Some(int) = self.internal.get(lit.var().idx()).copied().flatten() else {
+                continue;
+            };
+            let int = if lit.is_neg() { !int } else { int };
+            if int.var().idx() < self.n_internal_vars {
+                values.make_true(int);
+            }
+        }
+        // Variables that the solution does not assign are true, as in MaxPre
+        for val in &mut values.0[..self.n_internal_vars] {
+            val.get_or_insert(true);
+        }
+        for op in self.trace.iter().rev() {
+            values.apply(op);
+        }
+        (0..self.n_orig_vars)
+            .map(|idx| Var::new(idx as u32).lit(values.0[idx] == Some(false)))
+            .collect()
+    }
+
+    /// Reconstructs multiple assignments in parallel
+    #[cfg(feature = "rayon")]
+    pub fn par_reconstruct(&self, sols: &[Assignment]) -> Vec<Assignment> {
+        use rayon::prelude::*;
+
+        sols.par_iter()
+            .map(|sol| self.reconstruct(sol.iter()))
+            .collect()
+    }
+}
+
+/// The values of the internal variables during reconstruction
+struct Values(Vec<Option<bool>>);
+
+impl Values {
+    fn is_true(&self, lit: Lit) -> bool {
+        self.0[lit.var().idx()] == Some(lit.is_pos())
+    }
+
+    fn is_false(&self, lit: Lit) -> bool {
+        self.0[lit.var().idx()] == Some(lit.is_neg())
+    }
+
+    fn make_true(&mut self, lit: Lit) {
+        self.0[lit.var().idx()] = Some(lit.is_pos());
+    }
+
+    /// Checks whether a literal is true, making it true if its variable is not
+    /// assigned yet
+    fn check_or_make_true(&mut self, lit: Lit) -> bool {
+        let val = self.0[lit.var().idx()].get_or_insert(lit.is_pos());
+        *val == lit.is_pos()
+    }
+
+    /// Checks whether a clause is satisfied, all unassigned variables are
+    /// assigned to satisfy their literal
+    fn all_satisfied(&mut self, cl: &Clause) -> bool {
+        let mut sat = false;
+        for &lit in cl {
+            sat |= self.check_or_make_true(lit);
+        }
+        sat
+    }
+
+    /// Checks whether a clause is satisfied, assigning unassigned variables up
+    /// to the first satisfied literal
+    fn first_satisfied(&mut self, cl: &Clause) -> bool {
+        cl.iter().any(|&lit| self.check_or_make_true(lit))
+    }
+
+    /// Undoes a trace operation, following the reconstruction of MaxPre
+    fn apply(&mut self, op: &TraceOp) {
+        match op {
+            TraceOp::Fix { lit } => self.make_true(*lit),
+            TraceOp::Bve { var, clauses } => {
+                let sat = clauses.iter().all(|cl| self.all_satisfied(cl));
+                self.0[var.idx()] = Some(sat);
+            }
+            TraceOp::Bce { lit, clause } => {
+                if !self.first_satisfied(clause) {
+                    self.make_true(*lit);
+                }
+            }
+            TraceOp::Bcr {
+                lbl1,
+                lbl2,
+                clauses,
+            } => {
+                if clauses.iter().all(|cl| self.all_satisfied(cl)) {
+                    self.make_true(!*lbl1);
+                    self.make_true(*lbl2);
+                } else {
+                    self.make_true(*lbl1);
+                }
+            }
+            TraceOp::Equal { lit, equal_to } => {
+                let val = *self.0[equal_to.var().idx()].get_or_insert(true);
+                self.0[lit.var().idx()] = Some(val ^ equal_to.is_neg() ^ lit.is_neg());
+            }
+            TraceOp::Ls { label, lit, clause } => {
+                if self.is_true(*label) && !self.first_satisfied(clause) {
+                    self.make_true(*lit);
+                }
+            }
+            TraceOp::LabelElim { lbl1, lbl2, taut } => {
+                if self.is_false(*lbl1) {
+                    if self.is_true(*taut) {
+                        self.make_true(*lbl2);
+                        self.make_true(!*lbl1);
+                    } else {
+                        self.make_true(!*lbl2);
+                        self.make_true(*lbl1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{
+        instances::Cnf,
+        types::{Assignment, Clause, Var},
+    };
+
+    use crate::{testing::Softs, MaxPre, PreproClauses, PreproInit};
+
+    /// Assignments of the preprocessed instance, including partial ones
+    fn solutions(maxpre: &MaxPre, seed: u64) -> Vec<Assignment> {
+        let Some(max_var) = maxpre.max_prepro_var() else {
+            return vec![Assignment::default()];
+        };
+        (0..8u64)
+            .map(|idx| {
+                let bits = (seed + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ idx;
+                (0..=max_var.idx32())
+                    .filter(|&var| idx % 3 != 0 || var % 2 == 0)
+                    .map(|var| Var::new(var).lit(bits.rotate_left(var) & 1 == 0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Random instances with binary and ternary hard clauses and soft
+    /// clauses over some of the variables, so that label-based techniques
+    /// apply
+    fn instance(seed: u64) -> (Cnf, Vec<(Softs, isize)>) {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        let mut next = |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+        let mut lit = || Var::new(next(10) as u32).lit(next(2) == 0);
+        let cnf = (0..14)
+            .map(|idx| (0..2 + idx % 2).map(|_| lit()).collect::<Clause>())
+            .collect();
+        let softs = (0..6)
+            .map(|idx| {
+                (
+                    (0..1 + idx % 2).map(|_| lit()).collect(),
+                    1 + idx as u64 % 3,
+                )
+            })
+            .collect();
+        (cnf, vec![(softs, 0)])
+    }
+
+    #[test]
+    fn matches_maxpre() {
+        let techniques = ["[bu]#[buvsrgc]", "[uvea]", "[uvsrlcit]", "[uebvhmp]"];
+        for (seed, techniques) in (0..200).zip(techniques.iter().cycle()) {
+            for inprocessing in [false, true] {
+                let (cnf, softs) = instance(seed);
+                let mut maxpre = MaxPre::new(cnf, softs, inprocessing);
+                maxpre.preprocess(techniques, 0, 1e9);
+                let rec = maxpre.reconstructor();
+                for sol in solutions(&maxpre, seed) {
+                    assert_eq!(rec.reconstruct(sol.iter()), maxpre.reconstruct(sol));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel() {
+        let (cnf, softs) = instance(3);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let sols = solutions(&maxpre, 3);
+        let par = maxpre.par_reconstruct(&sols);
+        let seq: Vec<_> = sols
+            .into_iter()
+            .map(|sol| maxpre.reconstruct(sol))
+            .collect();
+        assert_eq!(par, seq);
+    }
+}
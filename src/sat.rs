@@ -1,19 +1,16 @@
 //! # Interface for Preprocessing [`rustsat::instances::SatInstance`] types
 
 use rustsat::{
-    encodings::{card, pb},
     instances::{Cnf, ManageVars, SatInstance},
-    types::{
-        constraints::{CardConstraint, PBConstraint},
-        Clause,
-    },
+    types::constraints::{CardConstraint, PBConstraint},
 };
 
-use crate::PreproClauses;
+use crate::{Prepro, PreproClauses, PreproInit};
 
 pub trait PreproSat: PreproClauses {
     /// Initializes a new preprocessor from a [`SatInstance`] where the instance
-    /// is converted to [`CNF`] with the given encoders.
+    /// is converted to [`CNF`] with the given encoders, see
+    /// [`Prepro::load_with_encoders`]
     fn new_with_encoders<VM, CardEnc, PBEnc>(
         inst: SatInstance<VM>,
         card_encoder: CardEnc,
@@ -24,35 +21,27 @@ pub trait PreproSat: PreproClauses {
         VM: ManageVars,
         CardEnc: FnMut(CardConstraint, &mut Cnf, &mut dyn ManageVars),
         PBEnc: FnMut(PBConstraint, &mut Cnf, &mut dyn ManageVars),
-        Self: Sized,
+        Self: PreproInit,
     {
-        let (cnf, _) = inst.into_cnf_with_encoders(card_encoder, pb_encoder);
-        <Self as PreproClauses>::new::<Vec<(Clause, usize)>>(cnf, vec![], inprocessing)
+        Prepro::load_with_encoders(inst, card_encoder, pb_encoder, inprocessing)
     }
     /// Initializes a new preprocessor from a [`SatInstance`]
     fn new<VM>(inst: SatInstance<VM>, inprocessing: bool) -> Self
     where
         VM: ManageVars,
-        Self: Sized,
+        Self: PreproInit,
     {
-        Self::new_with_encoders(
-            inst,
-            |constr, cnf, vm| {
-                card::default_encode_cardinality_constraint(constr, cnf, vm)
-                    .expect("cardinality encoding ran out of memory")
-            },
-            |constr, cnf, vm| {
-                pb::default_encode_pb_constraint(constr, cnf, vm)
-                    .expect("pb encoding ran out of memory")
-            },
-            inprocessing,
-        )
+        Prepro::load(inst, inprocessing)
     }
-    /// Gets the preprocessed instance as a [`SatInstance`]
+    /// Gets the preprocessed instance as a [`SatInstance`]. The variable
+    /// manager is seeded with the highest original or preprocessed variable.
     fn prepro_instance(&mut self) -> SatInstance {
-        let (cnf, objs) = <Self as PreproClauses>::prepro_instance(self);
-        debug_assert!(objs.is_empty());
-        SatInstance::from(cnf)
+        Prepro::<SatInstance>::extract(self)
+    }
+    /// Gets the preprocessed instance as a [`SatInstance`] with the given
+    /// variable manager, see [`Prepro::extract_with_vm`]
+    fn prepro_instance_with_vm<VM: ManageVars>(&mut self, vm: VM) -> SatInstance<VM> {
+        Prepro::<SatInstance>::extract_with_vm(self, vm)
     }
 }
 
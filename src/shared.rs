@@ -0,0 +1,121 @@
+//! # Shared Access after Preprocessing
+//!
+//! All queries of [`MaxPre`] take `&mut self` since they go through the C
+//! API, which reconstructs solutions in a buffer of the preprocessor. Once
+//! preprocessing is done, [`MaxPre::into_shared`] caches the preprocessed
+//! instance and the statistics on the Rust side and reconstructs with a
+//! [`Reconstructor`], so that the result can be queried from multiple
+//! threads, e.g., behind an [`Arc`](std::sync::Arc).
+
+use rustsat::{
+    instances::Cnf,
+    types::{Assignment, Lit},
+};
+
+use crate::{MaxPre, PreproClauses, Reconstructor, SoftClauses, Stats};
+
+/// A preprocessed instance with read-only access from multiple threads, see
+/// [`MaxPre::into_shared`]
+#[derive(Clone)]
+pub struct SharedPrepro {
+    hards: Cnf,
+    objs: Vec<(SoftClauses, isize)>,
+    stats: Stats,
+    reconstructor: Reconstructor,
+}
+
+impl SharedPrepro {
+    /// Gets the preprocessed hard clauses and objectives
+    pub fn prepro_instance(&self) -> (&Cnf, &[(SoftClauses, isize)]) {
+        (&self.hards, &self.objs)
+    }
+
+    /// Gets the final statistics of the preprocessor
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Reconstructs a solution of the preprocessed instance, the same way as
+    /// [`PreproClauses::reconstruct`]
+    pub fn reconstruct(&self, sol: &Assignment) -> Assignment {
+        self.reconstructor.reconstruct(sol.iter())
+    }
+
+    /// Reconstructs an assignment given by its true literals
+    pub fn reconstruct_lits<I: IntoIterator<Item = Lit>>(&self, lits: I) -> Assignment {
+        self.reconstructor.reconstruct(lits)
+    }
+
+    /// Gets the reconstructor for the preprocessed instance
+    pub fn reconstructor(&self) -> &Reconstructor {
+        &self.reconstructor
+    }
+}
+
+impl MaxPre {
+    /// Releases the preprocessor after preprocessing and keeps the
+    /// preprocessed instance, the statistics and the reconstruction trace, all
+    /// of which can then be accessed through a shared reference
+    pub fn into_shared(mut self) -> SharedPrepro {
+        let (hards, objs) = self.prepro_instance();
+        let (stats, reconstructor) = self.close_with_reconstructor();
+        SharedPrepro {
+            hards,
+            objs,
+            stats,
+            reconstructor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rustsat::types::{Assignment, Var};
+
+    use super::SharedPrepro;
+    use crate::{
+        testing::{random_instance, InstanceConfig},
+        MaxPre, PreproClauses, PreproInit,
+    };
+
+    #[test]
+    fn concurrent_readers() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<SharedPrepro>();
+
+        let (cnf, softs) = random_instance(&InstanceConfig::DEFAULT, 4);
+        let mut maxpre = MaxPre::new(cnf, softs, false);
+        maxpre.preprocess("[bu]#[buvsrgc]", 0, 1e9);
+        let max_var = maxpre.max_prepro_var().map_or(0, |var| var.idx32());
+        let sols: Vec<Assignment> = (0..4)
+            .map(|seed| {
+                (0..=max_var)
+                    .map(|var| Var::new(var).lit((var + seed) % 3 == 0))
+                    .collect()
+            })
+            .collect();
+        let expected: Vec<_> = sols
+            .iter()
+            .map(|sol| maxpre.reconstruct(sol.clone()))
+            .collect();
+        let (hards, objs) = maxpre.prepro_instance();
+        let n_prepro_hard_clauses = maxpre.stats().n_prepro_hard_clauses;
+        let shared = Arc::new(maxpre.into_shared());
+        assert_eq!(shared.prepro_instance(), (&hards, &objs[..]));
+        assert_eq!(shared.stats().n_prepro_hard_clauses, n_prepro_hard_clauses);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = sols
+                .iter()
+                .map(|sol| {
+                    let shared = Arc::clone(&shared);
+                    scope.spawn(move || shared.reconstruct(sol))
+                })
+                .collect();
+            for (handle, expected) in handles.into_iter().zip(expected) {
+                assert_eq!(handle.join().unwrap(), expected);
+            }
+        });
+    }
+}
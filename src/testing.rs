@@ -0,0 +1,206 @@
+//! # Testing Utilities
+//!
+//! Generators for pseudo-random instances, for testing preprocessors and the
+//! preprocess-solve-reconstruct loop of solvers using them. The same seed and
+//! configuration always generate the same instance.
+
+use rustsat::{
+    instances::Cnf,
+    types::{Assignment, Clause, Lit, TernaryVal, Var},
+};
+
+use crate::SoftClauses;
+
+/// Soft clauses as passed to [`PreproInit::new`](crate::PreproInit::new)
+pub type Softs = Vec<(Clause, u64)>;
+
+/// The distribution of generated instances. Hard clauses have a fixed length
+/// and literals drawn uniformly over all variables. Each objective has a unit
+/// soft clause for every variable, with a weight drawn uniformly from
+/// `1..=max_weight`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstanceConfig {
+    /// The number of variables
+    pub n_vars: u32,
+    /// The number of hard clauses
+    pub n_clauses: usize,
+    /// The length of the hard clauses
+    pub clause_len: usize,
+    /// The number of objectives
+    pub n_objs: usize,
+    /// The maximum weight of a soft clause
+    pub max_weight: u64,
+}
+
+impl InstanceConfig {
+    /// Small weighted instances with a single objective, close to the
+    /// satisfiability threshold of random 3-SAT
+    pub const DEFAULT: InstanceConfig = InstanceConfig {
+        n_vars: 30,
+        n_clauses: 80,
+        clause_len: 3,
+        n_objs: 1,
+        max_weight: 5,
+    };
+    /// Small unweighted instances with a single objective
+    pub const UNWEIGHTED: InstanceConfig = InstanceConfig {
+        max_weight: 1,
+        ..InstanceConfig::DEFAULT
+    };
+    /// Small weighted instances with three objectives
+    pub const MULTI_OBJECTIVE: InstanceConfig = InstanceConfig {
+        n_objs: 3,
+        ..InstanceConfig::DEFAULT
+    };
+    /// Larger weighted instances with a single objective
+    pub const LARGE: InstanceConfig = InstanceConfig {
+        n_vars: 300,
+        n_clauses: 800,
+        ..InstanceConfig::DEFAULT
+    };
+}
+
+impl Default for InstanceConfig {
+    fn default() -> Self {
+        InstanceConfig::DEFAULT
+    }
+}
+
+/// A linear congruential generator, so that instances do not depend on the
+/// random number generators of other crates
+struct Lcg(u64);
+
+impl Lcg {
+    /// Draws a number from `0..n`
+    fn next(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+}
+
+/// Generates a pseudo-random instance with the given seed, in the form
+/// expected by [`PreproInit::new`](crate::PreproInit::new)
+#[must_use]
+pub fn random_instance(config: &InstanceConfig, seed: u64) -> (Cnf, Vec<(Softs, isize)>) {
+    let mut rng = Lcg(seed);
+    let n_vars = u64::from(config.n_vars);
+    let cnf = (0..config.n_clauses)
+        .map(|_| {
+            (0..config.clause_len)
+                .map(|_| Lit::new(rng.next(n_vars) as u32, rng.next(2) == 0))
+                .collect::<Clause>()
+        })
+        .collect();
+    let objs = (0..config.n_objs)
+        .map(|_| {
+            let softs = (0..config.n_vars)
+                .map(|v| {
+                    (
+                        Clause::from([Lit::new(v, true)]),
+                        rng.next(config.max_weight) + 1,
+                    )
+                })
+                .collect();
+            (softs, 0)
+        })
+        .collect();
+    (cnf, objs)
+}
+
+/// Generates a pseudo-random [`MultiOptInstance`](rustsat::instances::MultiOptInstance)
+/// with the given seed, containing the same constraints and objectives as
+/// [`random_instance`]
+#[cfg(feature = "multiopt")]
+#[must_use]
+pub fn random_multiopt_instance(
+    config: &InstanceConfig,
+    seed: u64,
+) -> rustsat::instances::MultiOptInstance {
+    use rustsat::instances::{MultiOptInstance, Objective, SatInstance};
+
+    let (cnf, objs) = random_instance(config, seed);
+    let objs = objs
+        .into_iter()
+        .map(|(softs, offset)| {
+            let mut obj: Objective = softs.into_iter().map(|(cl, w)| (cl, w as usize)).collect();
+            obj.set_offset(offset);
+            obj
+        })
+        .collect();
+    MultiOptInstance::compose(SatInstance::from(cnf), objs)
+}
+
+/// Solves an instance by enumerating all assignments of the variables in its
+/// clauses, for checking preprocessed instances and reconstructed solutions.
+/// Returns an optimal solution for the first objective and its cost, not
+/// including the offset, or [`None`] if the hard clauses are unsatisfiable.
+///
+/// # Panics
+///
+/// If the clauses contain a variable with an index of 20 or more
+#[must_use]
+pub fn optimum(hards: &Cnf, objs: &[(SoftClauses, isize)]) -> Option<(Assignment, u64)> {
+    let n_vars = hards
+        .iter()
+        .chain(objs.iter().flat_map(|(s, _)| s.iter().map(|(cl, _)| cl)))
+        .flat_map(|cl| cl.iter().map(|l| l.var().idx32() + 1))
+        .max()
+        .unwrap_or(0);
+    assert!(n_vars <= 20, "too many variables to enumerate");
+    (0..1u32 << n_vars)
+        .map(|bits| -> Assignment {
+            (0..n_vars)
+                .map(|v| Var::new(v).lit(bits & (1 << v) == 0))
+                .collect()
+        })
+        .filter(|sol| hards.iter().all(|cl| cl.evaluate(sol) == TernaryVal::True))
+        .map(|sol| {
+            let cost = objs
+                .first()
+                .map_or(0, |obj| crate::costs(core::slice::from_ref(obj), &sol)[0]);
+            (sol, cost)
+        })
+        .min_by_key(|(_, cost)| *cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{clause, instances::Cnf, lit, types::TernaryVal};
+
+    use super::{optimum, random_instance, InstanceConfig};
+
+    #[test]
+    fn random_instances() {
+        let config = InstanceConfig::MULTI_OBJECTIVE;
+        let (cnf, objs) = random_instance(&config, 0);
+        assert_eq!(cnf.len(), config.n_clauses);
+        assert!(cnf.iter().all(|cl| cl.len() == config.clause_len));
+        assert_eq!(objs.len(), config.n_objs);
+        assert!(objs
+            .iter()
+            .all(|(softs, _)| softs.len() == config.n_vars as usize
+                && softs
+                    .iter()
+                    .all(|(_, w)| (1..=config.max_weight).contains(w))));
+        assert_eq!(random_instance(&config, 0), (cnf.clone(), objs.clone()));
+        assert_ne!(random_instance(&config, 1).0, cnf);
+    }
+
+    #[test]
+    fn brute_force_optimum() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 2), (clause![!lit![1]], 1)];
+        let Some((sol, cost)) = optimum(&cnf, &[(softs, 0)]) else {
+            panic!("instance is satisfiable");
+        };
+        assert_eq!(cost, 1);
+        assert_eq!(sol.lit_value(lit![1]), TernaryVal::True);
+        cnf.add_unit(!lit![0]);
+        cnf.add_unit(!lit![1]);
+        assert!(optimum(&cnf, &[]).is_none());
+    }
+}
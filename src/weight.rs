@@ -0,0 +1,230 @@
+//! # Weight Types
+//!
+//! MaxPre represents weights as 64-bit unsigned integers. Solvers that use
+//! other weight types convert at the interface to the preprocessor with
+//! [`PreproWeights`], which applies an [`OverflowPolicy`] to weights that do
+//! not fit. Big-integer types can be used by implementing [`WeightLike`] for
+//! them.
+
+use rustsat::{
+    instances::Cnf,
+    types::{Clause, Lit},
+};
+
+use crate::{Error, PreproClauses, PreproInit};
+
+/// A type of weights that can be converted to and from the weights of MaxPre
+pub trait WeightLike: Copy {
+    /// The largest weight, which weights saturate to
+    const MAX: Self;
+    /// Converts the weight to a weight of MaxPre, returns [`None`] if it does
+    /// not fit
+    fn to_u64(self) -> Option<u64>;
+    /// Converts a weight of MaxPre to this type, returns [`None`] if it does
+    /// not fit
+    fn from_u64(weight: u64) -> Option<Self>;
+}
+
+macro_rules! impl_weight_like {
+    ($($ty:ty),*) => {
+        $(
+            impl WeightLike for $ty {
+                const MAX: Self = <$ty>::MAX;
+
+                fn to_u64(self) -> Option<u64> {
+                    u64::try_from(self).ok()
+                }
+
+                fn from_u64(weight: u64) -> Option<Self> {
+                    <$ty>::try_from(weight).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_weight_like!(u8, u16, u32, u64, u128, usize);
+
+/// How weights that do not fit into the target type are converted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Fail with [`Error::WeightOverflow`]
+    #[default]
+    Error,
+    /// Use the largest weight of the target type. For weights passed to
+    /// MaxPre, this typically makes the clause hard.
+    Saturate,
+}
+
+impl OverflowPolicy {
+    /// Converts a weight to a weight of MaxPre
+    ///
+    /// # Errors
+    ///
+    /// [`Error::WeightOverflow`] if the weight does not fit and the policy is
+    /// [`OverflowPolicy::Error`]
+    pub fn to_u64<W: WeightLike>(self, weight: W) -> Result<u64, Error> {
+        match (weight.to_u64(), self) {
+            (Some(weight), _) => Ok(weight),
+            (None, OverflowPolicy::Saturate) => Ok(u64::MAX),
+            (None, OverflowPolicy::Error) => Err(Error::WeightOverflow),
+        }
+    }
+
+    /// Converts a weight of MaxPre to another weight type
+    ///
+    /// # Errors
+    ///
+    /// [`Error::WeightOverflow`] if the weight does not fit and the policy is
+    /// [`OverflowPolicy::Error`]
+    pub fn from_u64<W: WeightLike>(self, weight: u64) -> Result<W, Error> {
+        match (W::from_u64(weight), self) {
+            (Some(weight), _) => Ok(weight),
+            (None, OverflowPolicy::Saturate) => Ok(W::MAX),
+            (None, OverflowPolicy::Error) => Err(Error::WeightOverflow),
+        }
+    }
+}
+
+/// Soft clauses with weights of type `W`
+pub type WeightedSoftClauses<W> = Vec<(Clause, W)>;
+
+pub trait PreproWeights: PreproClauses {
+    /// Initializes a new preprocessor from soft clauses with weights of type
+    /// `W`, see [`PreproInit::new`]
+    ///
+    /// # Errors
+    ///
+    /// If a weight does not fit into the weights of MaxPre and the policy is
+    /// [`OverflowPolicy::Error`]
+    fn new_weighted<W, H, S, CI>(
+        hards: H,
+        softs: S,
+        inprocessing: bool,
+        policy: OverflowPolicy,
+    ) -> Result<Self, Error>
+    where
+        W: WeightLike,
+        H: IntoIterator<Item = Clause>,
+        S: IntoIterator<Item = (CI, isize)>,
+        CI: IntoIterator<Item = (Clause, W)>,
+        Self: PreproInit,
+    {
+        let softs = softs
+            .into_iter()
+            .map(|(cls, offset)| {
+                let cls = cls
+                    .into_iter()
+                    .map(|(cl, w)| Ok((cl, policy.to_u64(w)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((cls, offset))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(<Self as PreproInit>::new(hards, softs, inprocessing))
+    }
+    /// Gets the preprocessed instance with weights of type `W`
+    ///
+    /// # Errors
+    ///
+    /// If a weight does not fit into `W` and the policy is
+    /// [`OverflowPolicy::Error`]
+    #[allow(clippy::type_complexity)]
+    fn prepro_instance_weighted<W: WeightLike>(
+        &mut self,
+        policy: OverflowPolicy,
+    ) -> Result<(Cnf, Vec<(WeightedSoftClauses<W>, isize)>), Error> {
+        let (hards, objs) = self.prepro_instance();
+        let objs = objs
+            .into_iter()
+            .map(|(cls, offset)| {
+                let cls = cls
+                    .into_iter()
+                    .map(|(cl, w)| Ok((cl, policy.from_u64(w as u64)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok((cls, offset))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok((hards, objs))
+    }
+    /// Adds a label with a weight of type `W`, see
+    /// [`PreproClauses::add_label`]
+    ///
+    /// # Errors
+    ///
+    /// If the weight does not fit and the policy is [`OverflowPolicy::Error`],
+    /// or if adding the label fails
+    fn add_label_weighted<W: WeightLike>(
+        &mut self,
+        label: Lit,
+        weight: W,
+        policy: OverflowPolicy,
+    ) -> Result<Lit, Error> {
+        let weight = policy.to_u64(weight)?;
+        let weight = usize::try_from(weight).map_err(|_| Error::WeightOverflow)?;
+        self.add_label(label, weight)
+    }
+}
+
+impl<PP: PreproClauses> PreproWeights for PP {}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::{clause, instances::Cnf, lit};
+
+    use super::{OverflowPolicy, PreproWeights};
+    use crate::{Error, NoopPrepro, PreproClauses};
+
+    #[test]
+    fn overflow_policies() {
+        assert_eq!(OverflowPolicy::Error.to_u64(5u32).ok(), Some(5));
+        assert!(matches!(
+            OverflowPolicy::Error.to_u64(u128::MAX),
+            Err(Error::WeightOverflow)
+        ));
+        assert_eq!(
+            OverflowPolicy::Saturate.to_u64(u128::MAX).ok(),
+            Some(u64::MAX)
+        );
+        assert!(matches!(
+            OverflowPolicy::Error.from_u64::<u32>(1 << 40),
+            Err(Error::WeightOverflow)
+        ));
+        assert_eq!(
+            OverflowPolicy::Saturate.from_u64::<u32>(1 << 40).ok(),
+            Some(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn weighted_instances() {
+        let mut cnf = Cnf::new();
+        cnf.add_binary(lit![0], lit![1]);
+        let softs = vec![(clause![!lit![0]], 3u32), (clause![!lit![1]], 1 << 20)];
+        let Ok(mut pp) =
+            NoopPrepro::new_weighted(cnf.clone(), [(softs, 0)], true, OverflowPolicy::Error)
+        else {
+            panic!("weights fit");
+        };
+        pp.preprocess("[u]", 0, 1e9);
+        let Ok((_, objs)) = pp.prepro_instance_weighted::<u32>(OverflowPolicy::Error) else {
+            panic!("weights fit");
+        };
+        assert_eq!(objs[0].0[1].1, 1 << 20);
+        assert!(matches!(
+            pp.prepro_instance_weighted::<u16>(OverflowPolicy::Error),
+            Err(Error::WeightOverflow)
+        ));
+        let Ok((_, objs)) = pp.prepro_instance_weighted::<u16>(OverflowPolicy::Saturate) else {
+            panic!("weights saturate");
+        };
+        assert_eq!(
+            objs[0].0,
+            vec![(clause![!lit![0]], 3), (clause![!lit![1]], u16::MAX)]
+        );
+        assert!(pp
+            .add_label_weighted(lit![2], 2u8, OverflowPolicy::Error)
+            .is_ok());
+        let softs = vec![(clause![!lit![0]], u128::MAX)];
+        assert!(NoopPrepro::new_weighted(cnf, [(softs, 0)], true, OverflowPolicy::Error).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! Builds the crate against MaxPre sources given through `MAXPRE_SRC_DIR`, in
+//! a separate target directory so that the build script runs again
+
+#![cfg(feature = "cxx")]
+
+use std::{path::Path, process::Command};
+
+fn cargo(env: (&str, &str), args: &[&str]) -> std::process::Output {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    Command::new(env!("CARGO"))
+        .arg(args[0])
+        .args([
+            "--offline",
+            "--manifest-path",
+            &format!("{crate_dir}/Cargo.toml"),
+        ])
+        .args(&args[1..])
+        .env("CARGO_TARGET_DIR", env!("CARGO_TARGET_TMPDIR"))
+        .env_remove("MAXPRE_SRC_DIR")
+        .env_remove("MAXPRE_LIB_DIR")
+        .env(env.0, env.1)
+        .output()
+        .expect("failed to run cargo")
+}
+
+fn git(dir: &str, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args([
+            "-c",
+            "user.name=maxpre-rs",
+            "-c",
+            "user.email=maxpre-rs@localhost",
+        ])
+        .args(args)
+        .status()
+        .expect("failed to run git");
+    assert!(status.success());
+}
+
+#[test]
+fn src_dir() {
+    // A git repository with a copy of the vendored sources, which is not
+    // recognized as vendored. It is kept between runs and the timestamps are
+    // preserved, so that MaxPre is only rebuilt if the sources changed.
+    let src_dir = format!("{}/maxpre-src", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::create_dir_all(&src_dir).unwrap();
+    let status = Command::new("cp")
+        .args([
+            "-rp",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/cppsrc/."),
+            &src_dir,
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    if !Path::new(&src_dir).join(".git").exists() {
+        git(&src_dir, &["init", "-q"]);
+        git(&src_dir, &["add", "-A"]);
+        git(&src_dir, &["commit", "-q", "-m", "MaxPre sources"]);
+    }
+    let out = cargo(
+        ("MAXPRE_SRC_DIR", &src_dir),
+        &[
+            "test",
+            "--lib",
+            "--",
+            "base::tests::close",
+            "base::tests::version",
+        ],
+    );
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    // The version test also checks the commit exposed by the build script
+    assert!(String::from_utf8_lossy(&out.stdout).contains("2 passed"));
+}
+
+#[test]
+fn lib_dir_is_rejected() {
+    let out = cargo(("MAXPRE_LIB_DIR", "/nonexistent"), &["build", "--lib"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("MAXPRE_LIB_DIR is not supported"));
+}